@@ -31,4 +31,4 @@ cp -f .env.remote .env
 cp -f nginx/nginx.conf.remote nginx/nginx.conf
 docker compose --project-name="taskscore" down --rmi all
 docker compose --project-name="taskscore" build --no-cache
-docker compose --project-name="taskscore" up -d
+docker compose --project-name="taskscore" up -d --wait
@@ -0,0 +1,304 @@
+use std::{fs, process};
+
+use clap::{Parser, Subcommand};
+
+use task_score::config::app_config::{AppConfig, RepositoryKind};
+use task_score::model::{Task, User, user::{Team, generate_password}, health::HealthStatus, snapshot::RepositorySnapshot};
+use task_score::repository::backup;
+use task_score::repository::demo_data::seed_demo_data;
+use task_score::repository::repository::{DynRepository, create_repository};
+
+// Maintenance of a TaskScore instance, using the same configuration (Rocket.toml, TS_ environment variables) as the server.
+// Only the sqlite and file repositories keep what the commands change. The neo4j repository holds everything but the
+// graph's own schema in memory, so against it only 'migrate' is available
+#[derive(Parser)]
+#[command(name = "taskscore-admin", version, about = "Manages users, tasks, teams and data of a TaskScore instance",
+    long_about = "Manages users, tasks, teams and data of a TaskScore instance. Changes are only kept by the sqlite and file repositories, \
+        against the neo4j repository only 'migrate' is available")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage users
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage tasks
+    #[command(subcommand)]
+    Task(TaskCommand),
+    /// Manage teams
+    #[command(subcommand)]
+    Team(TeamCommand),
+    /// Apply pending database migrations and show the schema version
+    Migrate,
+    /// Fill an empty repository with the demo data
+    Seed,
+    /// Write all data as JSON to a file or stdout
+    Export {
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Replace all data with the content of an export
+    Import {
+        file: String,
+        /// Only report errors and conflicts without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// List all users
+    List,
+    /// Create a user, a password is generated unless one is given
+    Create {
+        username: String,
+        display_name: String,
+        #[arg(long)]
+        admin: bool,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Prevent a user from logging in, ending all of their sessions
+    Deactivate {
+        username: String,
+    },
+    /// Allow a deactivated user to log in again
+    Activate {
+        username: String,
+    },
+    /// Set a new password, a password is generated unless one is given
+    ResetPassword {
+        username: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TaskCommand {
+    /// List all tasks
+    List,
+    /// Create a task
+    Create {
+        name: String,
+        points: u16,
+        #[arg(long)]
+        disabled: bool,
+        #[arg(long)]
+        requires_verification: bool,
+    },
+    /// Change name, points or state of a task
+    Edit {
+        id: u32,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        points: Option<u16>,
+        #[arg(long)]
+        enabled: Option<bool>,
+        #[arg(long)]
+        requires_verification: Option<bool>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TeamCommand {
+    /// Create a team managed by the given user
+    Create {
+        name: String,
+        manager: String,
+    },
+    /// Add a user to a team
+    AddMember {
+        team: String,
+        username: String,
+    },
+}
+
+#[rocket::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let config = match AppConfig::load(&AppConfig::figment()) {
+        Ok(config) => config,
+        Err(errors) => {
+            errors.iter().for_each(|error| eprintln!("Invalid configuration: {}", error));
+            process::exit(1);
+        }
+    };
+    if config.repository == RepositoryKind::Memory {
+        eprintln!("Warning: the memory repository is configured, changes are lost when this command exits");
+    }
+    if config.repository == RepositoryKind::Neo4j && !matches!(cli.command, Command::Migrate) {
+        eprintln!("Only 'migrate' is available for the neo4j repository, which does not keep changes made by this tool; use the sqlite or file repository");
+        process::exit(1);
+    }
+
+    let repository = match create_repository(&config).await {
+        Ok(repository) => repository,
+        Err(err_msg) => {
+            eprintln!("Unable to open the repository: {}", err_msg);
+            process::exit(1);
+        }
+    };
+
+    if let Err(err_msg) = run(cli.command, &repository).await {
+        eprintln!("{}", err_msg);
+        process::exit(1);
+    }
+}
+
+async fn run(command: Command, repository: &DynRepository) -> Result<(), String> {
+    match command {
+        Command::User(command) => run_user(command, repository).await,
+        Command::Task(command) => run_task(command, repository).await,
+        Command::Team(command) => run_team(command, repository).await,
+        Command::Migrate => {
+            // Migrations are applied when the repository is opened, so only the outcome is left to show
+            let checks = repository.check_health().await;
+            for check in checks.iter() {
+                println!("{}: {:?} {}", check.name, check.status, check.detail.clone().unwrap_or_default());
+            }
+            if checks.iter().any(|check| check.status == HealthStatus::Down) {
+                return Err("The repository is not ready".to_owned());
+            }
+            Ok(())
+        },
+        Command::Seed => {
+            seed_demo_data(repository.as_ref()).await?;
+            println!("Demo data added");
+            Ok(())
+        },
+        Command::Export { output } => {
+            let snapshot = repository.export_snapshot().await?;
+            let json = serde_json::to_string_pretty(&snapshot).map_err(|err| err.to_string())?;
+            match output {
+                Some(path) => fs::write(&path, json).map_err(|err| format!("Unable to write '{}': {}", path, err)),
+                None => {
+                    println!("{}", json);
+                    Ok(())
+                }
+            }
+        },
+        Command::Import { file, dry_run } => {
+            let content = fs::read_to_string(&file).map_err(|err| format!("Unable to read '{}': {}", file, err))?;
+            let snapshot: RepositorySnapshot = serde_json::from_str(&content).map_err(|err| format!("Unable to parse '{}': {}", file, err))?;
+            let report = backup::import(repository.as_ref(), snapshot, dry_run).await?;
+            report.conflicts.iter().for_each(|conflict| println!("Conflict: {}", conflict));
+            report.errors.iter().for_each(|error| eprintln!("Error: {}", error));
+            if !report.errors.is_empty() {
+                return Err(format!("The import of '{}' has been refused", file));
+            }
+            let verb = if report.applied { "Imported" } else { "Would import" };
+            println!("{} {} users, {} tasks, {} teams and {} scores", verb, report.users, report.tasks, report.teams, report.scores);
+            Ok(())
+        },
+    }
+}
+
+async fn run_user(command: UserCommand, repository: &DynRepository) -> Result<(), String> {
+    match command {
+        UserCommand::List => {
+            for user in repository.get_all_users().await {
+                println!("{}\t{}\t{}\t{} points{}{}", user.id, user.username, user.display_name, user.points,
+                    if user.is_admin { "\tadmin" } else { "" }, if user.enabled { "" } else { "\tdeactivated" });
+            }
+            Ok(())
+        },
+        UserCommand::Create { username, display_name, admin, password } => {
+            let (password, generated) = password_or_generated(password);
+            let user = repository.create_and_add_user(username, display_name, password.clone(), admin).await?;
+            println!("Created user with id {}", user.lock().unwrap().id);
+            if generated {
+                println!("Password: {}", password);
+            }
+            Ok(())
+        },
+        UserCommand::Deactivate { username } => {
+            let user = find_user(repository, &username).await?;
+            repository.set_user_enabled(user.id, false).await?;
+            println!("Deactivated user '{}'", username);
+            Ok(())
+        },
+        UserCommand::Activate { username } => {
+            let user = find_user(repository, &username).await?;
+            repository.set_user_enabled(user.id, true).await?;
+            println!("Activated user '{}'", username);
+            Ok(())
+        },
+        UserCommand::ResetPassword { username, password } => {
+            let user = find_user(repository, &username).await?;
+            let (password, generated) = password_or_generated(password);
+            repository.set_password(user.id, password.clone()).await?;
+            println!("Password of '{}' has been reset", username);
+            if generated {
+                println!("Password: {}", password);
+            }
+            Ok(())
+        },
+    }
+}
+
+async fn run_task(command: TaskCommand, repository: &DynRepository) -> Result<(), String> {
+    match command {
+        TaskCommand::List => {
+            for task in repository.get_all_tasks().await {
+                println!("{}\t{}\t{} points{}{}", task.id, task.name, task.points, if task.enabled { "" } else { "\tdisabled" },
+                    if task.requires_verification { "\trequires verification" } else { "" });
+            }
+            Ok(())
+        },
+        TaskCommand::Create { name, points, disabled, requires_verification } => {
+            if name.trim().is_empty() {
+                return Err("The name of a task must not be empty".to_owned());
+            }
+            let task = repository.save_task(Task { id: 0, name, points, enabled: !disabled, requires_verification }).await?;
+            println!("Created task with id {}", task.id);
+            Ok(())
+        },
+        TaskCommand::Edit { id, name, points, enabled, requires_verification } => {
+            let mut task = repository.get_task(id).await.ok_or(format!("Task with id {} does not exist", id))?;
+            task.name = name.unwrap_or(task.name);
+            task.points = points.unwrap_or(task.points);
+            task.enabled = enabled.unwrap_or(task.enabled);
+            task.requires_verification = requires_verification.unwrap_or(task.requires_verification);
+            repository.save_task(task).await?;
+            println!("Updated task {}", id);
+            Ok(())
+        },
+    }
+}
+
+async fn run_team(command: TeamCommand, repository: &DynRepository) -> Result<(), String> {
+    match command {
+        TeamCommand::Create { name, manager } => {
+            let manager = repository.find_user_by_username(&manager).await.ok_or(format!("User '{}' does not exist", manager))?;
+            let team_id = repository.add_team(Team::new(0, name.clone(), manager)).await.ok_or(format!("Unable to create team '{}'", name))?;
+            println!("Created team with id {}", team_id);
+            Ok(())
+        },
+        TeamCommand::AddMember { team, username } => {
+            let user = find_user(repository, &username).await?;
+            // Acts with admin rights, so it does not matter who manages the team
+            let authority = User::new(0, "taskscore-admin".to_owned(), "TaskScore Admin".to_owned(), true);
+            repository.add_user_to_team(&team, user.id, authority).await?;
+            println!("Added '{}' to team '{}'", username, team);
+            Ok(())
+        },
+    }
+}
+
+async fn find_user(repository: &DynRepository, username: &String) -> Result<User, String> {
+    repository.find_user_by_username_const(username).await.ok_or(format!("User '{}' does not exist", username))
+}
+
+fn password_or_generated(password: Option<String>) -> (String, bool) {
+    match password {
+        Some(password) => (password, false),
+        None => (generate_password(), true),
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+use rocket::figment::{Figment, providers::Env};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const MASK: &str = "********";
+
+// A configuration value that must never show up in logs or responses
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(MASK)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RepositoryKind {
+    Neo4j,
+    Memory,
+    File,
+    Sqlite,
+}
+
+fn default_repository() -> RepositoryKind {
+    RepositoryKind::Neo4j
+}
+
+fn default_data_file() -> String {
+    "taskscore-data.json".to_owned()
+}
+
+fn default_sqlite_file() -> String {
+    "taskscore.sqlite".to_owned()
+}
+
+fn default_log_level() -> String {
+    "info".to_owned()
+}
+
+fn default_log_format() -> String {
+    "text".to_owned()
+}
+
+// Application configuration, taken from Rocket.toml and TS_ prefixed environment variables (including those in .env)
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AppConfig {
+    #[serde(default = "default_repository")]
+    pub repository: RepositoryKind,
+    // Only required by the neo4j repository
+    #[serde(default)]
+    pub database_address: Option<String>,
+    #[serde(default)]
+    pub database_principal: Option<String>,
+    #[serde(default)]
+    pub database_password: Option<Secret>,
+    // Only used by the file repository
+    #[serde(default = "default_data_file")]
+    pub data_file: String,
+    // Only used by the sqlite repository
+    #[serde(default = "default_sqlite_file")]
+    pub sqlite_file: String,
+    // Not 'log_level', which Rocket reads from the same sources and only accepts off, critical, normal or debug for
+    #[serde(rename = "app_log_level", default = "default_log_level")]
+    pub log_level: String,
+    #[serde(default = "default_log_format")]
+    pub log_format: String,
+}
+
+impl AppConfig {
+    // Figment to launch Rocket with, so both Rocket and the application read from the same sources
+    pub fn figment() -> Figment {
+        dotenv::dotenv().ok();
+        rocket::Config::figment().merge(Env::prefixed("TS_"))
+    }
+
+    pub fn load(figment: &Figment) -> Result<AppConfig, Vec<String>> {
+        let config: AppConfig = figment.extract().map_err(|err| err.into_iter().map(|e| e.to_string()).collect::<Vec<String>>())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+
+        if self.repository == RepositoryKind::Neo4j {
+            let address = self.database_address.as_deref().unwrap_or("");
+            match address.rsplit_once(':') {
+                Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => (),
+                _ => errors.push(format!("database_address '{}' must be given as <host>:<port>", address)),
+            }
+            if self.database_principal.as_deref().unwrap_or("").trim().is_empty() {
+                errors.push("database_principal must not be empty".to_owned());
+            }
+            if self.database_password.as_ref().map_or(true, |password| password.expose().is_empty()) {
+                errors.push("database_password must not be empty".to_owned());
+            }
+        }
+        if self.repository == RepositoryKind::File && self.data_file.trim().is_empty() {
+            errors.push("data_file must not be empty".to_owned());
+        }
+        if self.repository == RepositoryKind::Sqlite && self.sqlite_file.trim().is_empty() {
+            errors.push("sqlite_file must not be empty".to_owned());
+        }
+        if self.log_format != "text" && self.log_format != "json" {
+            errors.push(format!("log_format '{}' must be either 'text' or 'json'", self.log_format));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    // The values shown to admins, secrets are masked
+    pub fn to_key_values(&self) -> Vec<(String, String)> {
+        vec![
+            ("repository".to_owned(), format!("{:?}", self.repository).to_lowercase()),
+            ("database_address".to_owned(), self.database_address.clone().unwrap_or_default()),
+            ("database_principal".to_owned(), self.database_principal.clone().unwrap_or_default()),
+            ("database_password".to_owned(), self.database_password.as_ref().map_or("".to_owned(), |password| format!("{:?}", password))),
+            ("data_file".to_owned(), self.data_file.clone()),
+            ("sqlite_file".to_owned(), self.sqlite_file.clone()),
+            ("app_log_level".to_owned(), self.log_level.clone()),
+            ("log_format".to_owned(), self.log_format.clone()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::{Figment, providers::Serialized};
+
+    use super::{AppConfig, RepositoryKind};
+
+    fn figment(address: &str, password: &str) -> Figment {
+        Figment::new()
+            .merge(Serialized::default("database_address", address))
+            .merge(Serialized::default("database_principal", "tsadmin"))
+            .merge(Serialized::default("database_password", password))
+    }
+
+    #[test]
+    fn test_load_ok() {
+        let config = AppConfig::load(&figment("taskscore-neo4j:7687", "G3n3ricPwd")).unwrap();
+        assert_eq!("G3n3ricPwd", config.database_password.unwrap().expose());
+        assert_eq!("text", config.log_format);
+    }
+
+    #[test]
+    fn test_log_level_apart_from_rocket() {
+        let figment = Figment::from(rocket::Config::default())
+            .merge(Serialized::default("repository", "memory"))
+            .merge(Serialized::default("app_log_level", "info,taskscore=debug"));
+        assert_eq!("info,taskscore=debug", AppConfig::load(&figment).unwrap().log_level);
+        assert!(figment.extract::<rocket::Config>().is_ok());
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let errors = AppConfig::load(&figment("taskscore-neo4j", "")).unwrap_err();
+        assert_eq!(2, errors.len());
+    }
+
+    #[test]
+    fn test_load_missing() {
+        let result = AppConfig::load(&Figment::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_memory_without_database() {
+        let config = AppConfig::load(&Figment::new().merge(Serialized::default("repository", "memory"))).unwrap();
+        assert!(RepositoryKind::Memory == config.repository);
+    }
+
+    #[test]
+    fn test_secrets_masked() {
+        let config = AppConfig::load(&figment("taskscore-neo4j:7687", "G3n3ricPwd")).unwrap();
+        assert!(!format!("{:?}", config).contains("G3n3ricPwd"));
+        assert!(!config.to_key_values().iter().any(|(_, value)| value.contains("G3n3ricPwd")));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod app_config;
+pub mod settings;
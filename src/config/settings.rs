@@ -0,0 +1,219 @@
+use std::{collections::HashMap, sync::{Arc, RwLock}};
+
+use tracing::warn;
+
+use crate::model::{setting::{Setting, SettingValue}, streak::StreakCalendar, level::LevelCurve, kudos::KudosLimits};
+
+pub const SESSION_LIFETIME_MINUTES: &str = "session_lifetime_minutes";
+pub const SELF_REGISTRATION_ENABLED: &str = "self_registration_enabled";
+pub const SCORING_PAUSED: &str = "scoring_paused";
+pub const STREAK_SKIP_WEEKENDS: &str = "streak_skip_weekends";
+pub const STREAK_HOLIDAYS: &str = "streak_holidays";
+pub const STREAK_BONUS_INTERVAL: &str = "streak_bonus_interval";
+pub const STREAK_BONUS_POINTS: &str = "streak_bonus_points";
+pub const LEVEL_CURVE: &str = "level_curve";
+pub const KUDOS_WEEKLY_ALLOWANCE: &str = "kudos_weekly_allowance";
+pub const KUDOS_RECIPIENT_CAP: &str = "kudos_recipient_cap";
+pub const VERIFICATION_EXPIRY_HOURS: &str = "verification_expiry_hours";
+
+// Sessions and claims last at most a year, larger values would overflow the durations computed from them
+const MAX_SESSION_LIFETIME_MINUTES: i64 = 365 * 24 * 60;
+const MAX_VERIFICATION_EXPIRY_HOURS: i64 = 365 * 24;
+
+struct SettingDefinition {
+    key: &'static str,
+    description: &'static str,
+    default: SettingValue,
+}
+
+fn definitions() -> Vec<SettingDefinition> {
+    vec![
+        SettingDefinition { key: SESSION_LIFETIME_MINUTES, description: "Minutes after login a session expires", default: SettingValue::Integer(8 * 60) },
+        SettingDefinition { key: SELF_REGISTRATION_ENABLED, description: "Whether users may register themselves", default: SettingValue::Bool(false) },
+        SettingDefinition { key: SCORING_PAUSED, description: "Whether scoring is paused, e.g. during holidays", default: SettingValue::Bool(false) },
+        SettingDefinition { key: STREAK_SKIP_WEEKENDS, description: "Whether weekends are skipped instead of breaking a streak", default: SettingValue::Bool(true) },
+        SettingDefinition { key: STREAK_HOLIDAYS, description: "Comma separated dates (YYYY-MM-DD) that are skipped instead of breaking a streak", default: SettingValue::Text(String::new()) },
+        SettingDefinition { key: STREAK_BONUS_INTERVAL, description: "Every how many days in a row a streak earns a bonus, 0 for none", default: SettingValue::Integer(5) },
+        SettingDefinition { key: STREAK_BONUS_POINTS, description: "Points booked as a streak bonus", default: SettingValue::Integer(25) },
+        SettingDefinition { key: LEVEL_CURVE, description: "Comma separated 'points:title' pairs, the lifetime points each level starts at",
+            default: SettingValue::Text("0:Newcomer, 100:Helper, 250:Regular, 500:Pro, 1000:Master, 2500:Legend".to_owned()) },
+        SettingDefinition { key: KUDOS_WEEKLY_ALLOWANCE, description: "Kudos points every user may give away per week", default: SettingValue::Integer(50) },
+        SettingDefinition { key: KUDOS_RECIPIENT_CAP, description: "Kudos points a user may give to the same user per week", default: SettingValue::Integer(20) },
+        SettingDefinition { key: VERIFICATION_EXPIRY_HOURS, description: "Hours after which unverified scores expire", default: SettingValue::Integer(72) },
+    ]
+}
+
+// Settings changeable at runtime. Values are held in memory and kept in sync with the repository by whoever changes them,
+// clones share the values so background jobs see the changes as well
+#[derive(Clone)]
+pub struct Settings {
+    values: Arc<RwLock<HashMap<String, SettingValue>>>,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        let values = definitions().into_iter().map(|definition| (definition.key.to_owned(), definition.default)).collect();
+        Settings { values: Arc::new(RwLock::new(values)) }
+    }
+
+    // Takes over stored values, ignoring those that do not (or no longer) match a definition
+    pub fn load(&self, stored: Vec<(String, SettingValue)>) {
+        for (key, value) in stored {
+            match Settings::validate(&key, &value) {
+                Ok(_) => { self.values.write().unwrap().insert(key, value); },
+                Err(err_msg) => warn!(key = %key, error = %err_msg, "Ignoring stored setting"),
+            }
+        }
+    }
+
+    pub fn validate(key: &str, value: &SettingValue) -> Result<(), String> {
+        let definition = definitions().into_iter().find(|definition| definition.key == key).ok_or(format!("Setting '{}' does not exist", key))?;
+
+        if !definition.default.is_same_type(value) {
+            return Err(format!("Setting '{}' requires a value of the same type as {:?}", key, definition.default));
+        }
+        if (key == SESSION_LIFETIME_MINUTES || key == VERIFICATION_EXPIRY_HOURS) && matches!(value, SettingValue::Integer(minutes) if *minutes <= 0) {
+            return Err(format!("Setting '{}' must be positive", key));
+        }
+        if matches!((key, value), (SESSION_LIFETIME_MINUTES, SettingValue::Integer(minutes)) if *minutes > MAX_SESSION_LIFETIME_MINUTES) {
+            return Err(format!("Setting '{}' must not exceed {} (a year)", key, MAX_SESSION_LIFETIME_MINUTES));
+        }
+        if matches!((key, value), (VERIFICATION_EXPIRY_HOURS, SettingValue::Integer(hours)) if *hours > MAX_VERIFICATION_EXPIRY_HOURS) {
+            return Err(format!("Setting '{}' must not exceed {} (a year)", key, MAX_VERIFICATION_EXPIRY_HOURS));
+        }
+        if [STREAK_BONUS_INTERVAL, STREAK_BONUS_POINTS, KUDOS_WEEKLY_ALLOWANCE, KUDOS_RECIPIENT_CAP].contains(&key) && matches!(value, SettingValue::Integer(number) if *number < 0) {
+            return Err(format!("Setting '{}' must not be negative", key));
+        }
+        if let (STREAK_HOLIDAYS, SettingValue::Text(holidays)) = (key, value) {
+            StreakCalendar::parse(false, holidays)?;
+        }
+        if let (LEVEL_CURVE, SettingValue::Text(curve)) = (key, value) {
+            LevelCurve::parse(curve)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set(&self, key: &str, value: SettingValue) -> Result<(), String> {
+        Settings::validate(key, &value)?;
+        self.values.write().unwrap().insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Setting> {
+        let definition = definitions().into_iter().find(|definition| definition.key == key)?;
+        let value = self.values.read().unwrap().get(key).cloned().unwrap_or(definition.default.clone());
+
+        Some(Setting { key: key.to_owned(), description: definition.description.to_owned(), value, default: definition.default })
+    }
+
+    pub fn get_all(&self) -> Vec<Setting> {
+        definitions().iter().filter_map(|definition| self.get(definition.key)).collect()
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        matches!(self.values.read().unwrap().get(key), Some(SettingValue::Bool(true)))
+    }
+
+    pub fn get_integer(&self, key: &str) -> i64 {
+        match self.get(key).map(|setting| setting.value) {
+            Some(SettingValue::Integer(value)) => value,
+            _ => 0
+        }
+    }
+
+    pub fn get_text(&self, key: &str) -> String {
+        match self.get(key).map(|setting| setting.value) {
+            Some(SettingValue::Text(value)) => value,
+            _ => String::new()
+        }
+    }
+
+    pub fn scoring_paused(&self) -> bool {
+        self.get_bool(SCORING_PAUSED)
+    }
+
+    pub fn self_registration_enabled(&self) -> bool {
+        self.get_bool(SELF_REGISTRATION_ENABLED)
+    }
+
+    pub fn session_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.get_integer(SESSION_LIFETIME_MINUTES))
+    }
+
+    pub fn verification_expiry(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.get_integer(VERIFICATION_EXPIRY_HOURS))
+    }
+
+    // The holidays have been validated when they were set
+    pub fn streak_calendar(&self) -> StreakCalendar {
+        StreakCalendar::parse(self.get_bool(STREAK_SKIP_WEEKENDS), &self.get_text(STREAK_HOLIDAYS)).unwrap_or_default()
+    }
+
+    // The curve has been validated when it was set
+    pub fn level_curve(&self) -> LevelCurve {
+        LevelCurve::parse(&self.get_text(LEVEL_CURVE)).unwrap_or_default()
+    }
+
+    pub fn kudos_limits(&self) -> KudosLimits {
+        KudosLimits { weekly_allowance: self.get_integer(KUDOS_WEEKLY_ALLOWANCE) as u32, recipient_cap: self.get_integer(KUDOS_RECIPIENT_CAP) as u32 }
+    }
+
+    // The interval and the points of a streak bonus, none if either is 0
+    pub fn streak_bonus(&self) -> Option<(u32, i64)> {
+        let interval = self.get_integer(STREAK_BONUS_INTERVAL) as u32;
+        let points = self.get_integer(STREAK_BONUS_POINTS);
+        Some((interval, points)).filter(|_| interval > 0 && points > 0)
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::setting::SettingValue;
+
+    use super::{Settings, SCORING_PAUSED, SESSION_LIFETIME_MINUTES, STREAK_BONUS_INTERVAL, STREAK_HOLIDAYS, LEVEL_CURVE, VERIFICATION_EXPIRY_HOURS};
+
+    #[test]
+    fn test_defaults() {
+        let settings = Settings::new();
+        assert!(!settings.scoring_paused());
+        assert_eq!(480, settings.session_lifetime().num_minutes());
+        assert_eq!(Some((5, 25)), settings.streak_bonus());
+        assert!(settings.streak_calendar().skip_weekends);
+        assert_eq!(3, settings.level_curve().level_of(250));
+    }
+
+    #[test]
+    fn test_set_ok() {
+        let settings = Settings::new();
+        assert!(settings.set(SCORING_PAUSED, SettingValue::Bool(true)).is_ok());
+        assert!(settings.scoring_paused());
+    }
+
+    #[test]
+    fn test_set_invalid() {
+        let settings = Settings::new();
+        assert!(settings.set("unknown", SettingValue::Bool(true)).is_err());
+        assert!(settings.set(SCORING_PAUSED, SettingValue::Integer(1)).is_err());
+        assert!(settings.set(SESSION_LIFETIME_MINUTES, SettingValue::Integer(0)).is_err());
+        assert!(settings.set(SESSION_LIFETIME_MINUTES, SettingValue::Integer(i64::MAX)).is_err());
+        assert!(settings.set(VERIFICATION_EXPIRY_HOURS, SettingValue::Integer(i64::MAX)).is_err());
+        assert!(settings.set(STREAK_BONUS_INTERVAL, SettingValue::Integer(-1)).is_err());
+        assert!(settings.set(STREAK_HOLIDAYS, SettingValue::Text("2022-12-24, Christmas".to_owned())).is_err());
+        assert!(settings.set(LEVEL_CURVE, SettingValue::Text("0:Newcomer, 50:Newcomer again, 20:Helper".to_owned())).is_err());
+    }
+
+    #[test]
+    fn test_load_ignores_invalid() {
+        let settings = Settings::new();
+        settings.load(vec![(SESSION_LIFETIME_MINUTES.to_owned(), SettingValue::Integer(60)), (SCORING_PAUSED.to_owned(), SettingValue::Text("yes".to_owned()))]);
+        assert_eq!(60, settings.session_lifetime().num_minutes());
+        assert!(!settings.scoring_paused());
+    }
+}
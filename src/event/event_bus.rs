@@ -0,0 +1,53 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use chrono::Utc;
+use rocket::tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::model::event::{ActivityEvent, ActivityMessage};
+
+const CHANNEL_CAPACITY: usize = 256;
+const HISTORY_LEN: usize = 100;
+
+pub struct EventBus {
+    sender: Sender<ActivityMessage>,
+    // Recently published messages, kept to replay them to reconnecting clients
+    history: Mutex<VecDeque<ActivityMessage>>,
+    // The history is lost on a restart, so ids start at the microseconds since the epoch instead of 1. A client
+    // reconnecting with an id from before the restart then still receives every message published since
+    first_id: u64,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        EventBus { sender, history: Mutex::new(VecDeque::with_capacity(HISTORY_LEN)), first_id: Utc::now().timestamp_micros() as u64 }
+    }
+
+    pub fn publish(&self, team_ids: Vec<u32>, event: ActivityEvent) {
+        let mut history = self.history.lock().unwrap();
+        let id = history.back().map_or(self.first_id, |last| last.id + 1);
+        let message = ActivityMessage { id, team_ids, event };
+
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(message.clone());
+
+        // Sending only fails if nobody is listening, which is fine
+        let _ = self.sender.send(message);
+    }
+
+    pub fn subscribe(&self) -> Receiver<ActivityMessage> {
+        self.sender.subscribe()
+    }
+
+    pub fn published_after(&self, last_event_id: u64) -> Vec<ActivityMessage> {
+        self.history.lock().unwrap().iter().filter(|message| message.id > last_event_id).cloned().collect()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
@@ -0,0 +1,2 @@
+pub mod event_bus;
+pub mod webhook_service;
@@ -0,0 +1,293 @@
+use std::{collections::VecDeque, sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}}, time::Duration};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rocket::tokio::{self, sync::broadcast::{Receiver, error::RecvError}};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+
+use crate::model::{event::{ActivityEvent, ActivityMessage}, webhook::{Webhook, NewWebhook, WebhookDelivery, DeliveryStatus}};
+use crate::repository::repository::DynRepository;
+
+pub const SIGNATURE_HEADER: &str = "X-TaskScore-Signature";
+pub const EVENT_HEADER: &str = "X-TaskScore-Event";
+pub const DELIVERY_HEADER: &str = "X-TaskScore-Delivery";
+
+const DELIVERY_LOG_LEN: usize = 500;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_RETRY_DELAYS: [Duration; 4] = [Duration::from_secs(5), Duration::from_secs(30), Duration::from_secs(120), Duration::from_secs(600)];
+
+#[derive(serde::Serialize)]
+struct WebhookPayload<'a> {
+    delivery_id: u64,
+    event_id: u64,
+    event_type: &'a str,
+    sent_at: String,
+    data: &'a ActivityEvent,
+}
+
+// Posts every published activity to the subscribed urls. The subscriptions and the dead letters are kept by the
+// repository, only the log of recent deliveries is lost on a restart
+pub struct WebhookService {
+    client: reqwest::Client,
+    repository: DynRepository,
+    deliveries: Mutex<VecDeque<WebhookDelivery>>,
+    retry_delays: Vec<Duration>,
+    next_delivery_id: AtomicU64,
+}
+
+impl WebhookService {
+    pub fn new(repository: DynRepository) -> WebhookService {
+        WebhookService::with_retry_delays(repository, DEFAULT_RETRY_DELAYS.to_vec())
+    }
+
+    pub fn with_retry_delays(repository: DynRepository, retry_delays: Vec<Duration>) -> WebhookService {
+        WebhookService {
+            client: reqwest::Client::new(),
+            repository,
+            deliveries: Mutex::new(VecDeque::with_capacity(DELIVERY_LOG_LEN)),
+            retry_delays,
+            next_delivery_id: AtomicU64::new(1),
+        }
+    }
+
+    pub async fn add_webhook(&self, new_webhook: NewWebhook) -> Result<Webhook, String> {
+        new_webhook.validate()?;
+
+        let webhook = Webhook { id: 0, url: new_webhook.url, event_types: new_webhook.event_types, enabled: true, secret: new_webhook.secret };
+        self.repository.add_webhook(webhook).await
+    }
+
+    pub async fn get_all_webhooks(&self) -> Vec<Webhook> {
+        self.repository.get_webhooks().await
+    }
+
+    pub async fn remove_webhook(&self, id: u32) -> Result<(), String> {
+        self.repository.remove_webhook(id).await
+    }
+
+    pub fn get_deliveries(&self, webhook_id: u32) -> Vec<WebhookDelivery> {
+        self.deliveries.lock().unwrap().iter().filter(|d| d.webhook_id == webhook_id).cloned().collect()
+    }
+
+    pub async fn get_dead_letters(&self) -> Vec<WebhookDelivery> {
+        self.repository.get_dead_letters().await
+    }
+
+    pub fn start(self: Arc<Self>, mut receiver: Receiver<ActivityMessage>) {
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => self.dispatch(message).await,
+                    Err(RecvError::Lagged(missed)) => warn!(missed, "Webhook dispatcher skipped events"),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn dispatch(self: &Arc<Self>, message: ActivityMessage) {
+        let event_type = message.event.name();
+        let webhooks: Vec<Webhook> = self.repository.get_webhooks().await.into_iter().filter(|w| w.accepts(event_type)).collect();
+
+        for webhook in webhooks {
+            let service = self.clone();
+            let message = message.clone();
+            // Each delivery retries on its own, so a slow endpoint does not hold back the others
+            tokio::spawn(async move {
+                service.deliver(&webhook, &message).await;
+            });
+        }
+    }
+
+    pub async fn deliver(&self, webhook: &Webhook, message: &ActivityMessage) -> WebhookDelivery {
+        let now = Utc::now();
+        let mut delivery = WebhookDelivery {
+            id: self.next_delivery_id.fetch_add(1, Ordering::SeqCst),
+            webhook_id: webhook.id,
+            event_id: message.id,
+            event_type: message.event.name().to_owned(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            last_response_status: None,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.log(&delivery);
+
+        let payload = WebhookPayload {
+            delivery_id: delivery.id,
+            event_id: message.id,
+            event_type: message.event.name(),
+            sent_at: now.to_rfc3339(),
+            data: &message.event,
+        };
+        let body = serde_json::to_string(&payload).unwrap();
+        let signature = sign(&webhook.secret, &body);
+
+        loop {
+            delivery.attempts += 1;
+            let response = self.client.post(&webhook.url)
+                .header("Content-Type", "application/json")
+                .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+                .header(EVENT_HEADER, message.event.name())
+                .header(DELIVERY_HEADER, delivery.id.to_string())
+                .timeout(REQUEST_TIMEOUT)
+                .body(body.clone())
+                .send().await;
+
+            match response {
+                Ok(response) => {
+                    delivery.last_response_status = Some(response.status().as_u16());
+                    delivery.last_error = if response.status().is_success() { None } else { Some(format!("Endpoint responded with {}", response.status())) };
+                },
+                Err(err) => {
+                    delivery.last_response_status = None;
+                    delivery.last_error = Some(err.to_string());
+                }
+            }
+            delivery.updated_at = Utc::now();
+
+            if delivery.last_error.is_none() {
+                delivery.status = DeliveryStatus::Delivered;
+                break;
+            }
+
+            warn!(webhook_id = webhook.id, delivery_id = delivery.id, attempt = delivery.attempts, error = delivery.last_error.as_deref().unwrap_or(""), "Webhook delivery failed");
+            match self.retry_delays.get(delivery.attempts as usize - 1) {
+                Some(delay) => {
+                    self.log(&delivery);
+                    tokio::time::sleep(*delay).await;
+                },
+                None => {
+                    info!(webhook_id = webhook.id, delivery_id = delivery.id, "Webhook delivery moved to dead letters");
+                    delivery.status = DeliveryStatus::DeadLetter;
+                    if let Err(err_msg) = self.repository.add_dead_letter(delivery.clone()).await {
+                        error!(webhook_id = webhook.id, delivery_id = delivery.id, error = %err_msg, "Unable to keep the dead letter");
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.log(&delivery);
+        delivery
+    }
+
+    fn log(&self, delivery: &WebhookDelivery) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        match deliveries.iter().position(|d| d.id == delivery.id) {
+            Some(index) => deliveries[index] = delivery.clone(),
+            None => {
+                if deliveries.len() == DELIVERY_LOG_LEN {
+                    deliveries.pop_front();
+                }
+                deliveries.push_back(delivery.clone());
+            }
+        }
+    }
+}
+
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rocket::tokio::{self, io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener, sync::oneshot};
+
+    use std::sync::Arc;
+
+    use crate::model::{event::{ActivityEvent, ActivityMessage}, webhook::{NewWebhook, DeliveryStatus}};
+    use crate::repository::legacy_repository::LegacyRepository;
+
+    use super::{WebhookService, sign};
+
+    fn service(retry_delays: Vec<Duration>) -> WebhookService {
+        WebhookService::with_retry_delays(Arc::new(LegacyRepository::empty()), retry_delays)
+    }
+
+    fn message() -> ActivityMessage {
+        let event = ActivityEvent::Score { user_id: 1, display_name: "Flori".to_owned(), task_id: 4, task_name: "Kaffee kochen".to_owned(), points: 75, total_points: 75 };
+        ActivityMessage { id: 1, team_ids: vec![], event }
+    }
+
+    // Accepts a single request, answers it with 200 and hands over what has been received
+    async fn stand_in() -> (String, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, receiver) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = vec![];
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&received).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text.lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|len| len.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if received.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await.unwrap();
+            let _ = sender.send(String::from_utf8_lossy(&received).to_string());
+        });
+
+        (url, receiver)
+    }
+
+    #[rocket::async_test]
+    async fn test_deliver_signed_payload() {
+        let (url, received) = stand_in().await;
+        let service = service(vec![]);
+        let webhook = service.add_webhook(NewWebhook { url, secret: "s3cr3t".to_owned(), event_types: vec!["score".to_owned()] }).await.unwrap();
+
+        let delivery = service.deliver(&webhook, &message()).await;
+        assert!(DeliveryStatus::Delivered == delivery.status);
+        assert_eq!(1, delivery.attempts);
+
+        let request = received.await.unwrap();
+        let body = request.split("\r\n\r\n").nth(1).unwrap();
+        assert!(body.contains("Kaffee kochen"));
+        assert!(request.to_lowercase().contains(&format!("x-taskscore-signature: sha256={}", sign("s3cr3t", body))));
+        assert_eq!(1, service.get_deliveries(webhook.id).len());
+    }
+
+    #[rocket::async_test]
+    async fn test_deliver_dead_letter_after_retries() {
+        // Nothing listens on a port freed right after binding it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+
+        let service = service(vec![Duration::ZERO, Duration::ZERO]);
+        let webhook = service.add_webhook(NewWebhook { url, secret: "s3cr3t".to_owned(), event_types: vec![] }).await.unwrap();
+
+        let delivery = service.deliver(&webhook, &message()).await;
+        assert!(DeliveryStatus::DeadLetter == delivery.status);
+        assert_eq!(3, delivery.attempts);
+        assert_eq!(1, service.get_dead_letters().await.len());
+    }
+
+    #[rocket::async_test]
+    async fn test_add_webhook_invalid_url() {
+        let service = service(vec![]);
+        let result = service.add_webhook(NewWebhook { url: "ftp://example.org".to_owned(), secret: "s3cr3t".to_owned(), event_types: vec![] }).await;
+        assert!(result.is_err());
+        assert!(service.get_all_webhooks().await.is_empty());
+    }
+}
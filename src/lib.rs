@@ -0,0 +1,138 @@
+use chrono::Utc;
+use config::app_config::AppConfig;
+use config::settings::Settings;
+use event::webhook_service::WebhookService;
+use metrics::metrics::Metrics;
+use metrics::request_metrics::RequestMetrics;
+use logging::request_tracing::RequestTracing;
+use model::event::ActivityEvent;
+use repository::repository::{DynRepository, create_repository};
+use rocket::{Build, Rocket};
+use rocket::fairing::AdHoc;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::response::status::NotFound;
+use rocket::serde::json::Json;
+use std::sync::Arc;
+use std::time::Duration;
+
+use resource::admin_resource::*;
+use resource::config_resource::*;
+use resource::event_resource::*;
+use resource::health_resource::*;
+use resource::metrics_resource::*;
+use resource::points_resource::*;
+use resource::season_resource::*;
+use resource::kudos_resource::*;
+use resource::challenge_resource::*;
+use resource::schedule_resource::*;
+use resource::report_resource::*;
+use resource::reward_resource::*;
+use resource::score_resource::*;
+use resource::session_resource::*;
+use resource::task_resource::*;
+use resource::user_resource::*;
+use resource::verification_resource::*;
+use resource::webhook_resource::*;
+use resource::response::Response;
+use rocket_okapi::{openapi, openapi_get_routes};
+
+
+pub mod config;
+pub mod event;
+pub mod logging;
+pub mod metrics;
+pub mod model;
+pub mod repository;
+pub mod resource;
+
+#[macro_use] extern crate rocket;
+
+pub const CONTEXT_ROOT: &str = "/rest";
+// How often the housekeeping ends due seasons, resolves ended challenges, settles schedules and expires score claims
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_secs(60);
+
+#[openapi]
+#[get("/")]
+fn hello() -> Json<Response<String>> {
+    Json(Response{data: "Hello, world!".to_owned()})
+}
+
+#[catch(404)]
+fn not_found() -> NotFound<()> {
+    NotFound(())
+}
+
+// Assembles the application with the repository chosen by the configuration, ready to be launched (or tested)
+pub async fn build_rocket(config: AppConfig) -> Result<Rocket<Build>, String> {
+    let repository = create_repository(&config).await?;
+    let webhook_service = Arc::new(WebhookService::new(repository.clone()));
+
+    let rocket = rocket::custom(AppConfig::figment())
+
+    .manage(repository)
+    .manage(config)
+    .manage(Settings::new())
+    .attach(AdHoc::on_ignite("Settings", |rocket| Box::pin(async move {
+        let stored = rocket.state::<DynRepository>().unwrap().get_settings().await;
+        rocket.state::<Settings>().unwrap().load(stored);
+        rocket
+    })))
+    .manage(webhook_service)
+    .attach(AdHoc::on_liftoff("Webhook dispatcher", |rocket| Box::pin(async move {
+        let receiver = rocket.state::<DynRepository>().unwrap().event_bus().subscribe();
+        rocket.state::<Arc<WebhookService>>().unwrap().clone().start(receiver);
+    })))
+    .attach(RequestMetrics)
+    .attach(RequestTracing)
+    .attach(AdHoc::on_liftoff("Score metrics", |rocket| Box::pin(async move {
+        let mut receiver = rocket.state::<DynRepository>().unwrap().event_bus().subscribe();
+        rocket::tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => if let ActivityEvent::Score { task_name, points, .. } = message.event {
+                        Metrics::global().record_score(&task_name, points);
+                    },
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    })))
+    .attach(AdHoc::on_liftoff("Housekeeping", |rocket| Box::pin(async move {
+        let repository = rocket.state::<DynRepository>().unwrap().clone();
+        let settings = rocket.state::<Settings>().unwrap().clone();
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(HOUSEKEEPING_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = Utc::now();
+                repository.end_due_seasons(now).await;
+                repository.resolve_challenges(now).await;
+                repository.settle_schedules(now).await;
+                repository.expire_score_claims(now - settings.verification_expiry()).await;
+            }
+        });
+    })))
+    .mount(CONTEXT_ROOT, openapi_get_routes![hello,
+        export_data, import_data, import_users_csv, import_tasks_csv,
+        get_config, get_all_settings, get_setting, put_setting, get_setting_history,
+        get_liveness, get_readiness,
+        score, get_score_of_user, get_score_of_current_user, get_score_history_of_user,
+        get_score_report,
+        get_all_rewards, get_reward, add_reward, update_reward, redeem_reward, get_balance,
+        get_own_redemptions, get_redemptions_of_user, get_pending_redemptions, approve_redemption, reject_redemption,
+        get_own_ledger, get_ledger_of_user, get_period_points, book_points,
+        get_all_seasons, get_active_season, get_season, get_season_leaderboard, start_season, end_season, get_trophies_of_user,
+        give_kudos, get_own_kudos, get_kudos_allowance, get_kudos_of_user,
+        claim_score, get_own_claims, get_pending_claims, confirm_claim, reject_claim,
+        get_all_challenges, get_challenge, propose_challenge, accept_challenge, decline_challenge,
+        get_all_schedules, put_schedule, remove_schedule, get_assignments_of_user,
+        login, get_current_session, logout,
+        get_user, get_current_user, get_all_users, add_user, register_user, get_user_by_username,
+        get_task, get_all_tasks,
+        get_all_webhooks, add_webhook, remove_webhook, get_webhook_deliveries, get_webhook_dead_letters])
+    .mount(CONTEXT_ROOT, routes![get_events, get_metrics, get_users_csv_template, get_tasks_csv_template])
+    .register(CONTEXT_ROOT, catchers![not_found]);
+
+    Ok(rocket)
+}
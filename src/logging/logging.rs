@@ -0,0 +1,21 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::config::app_config::AppConfig;
+
+const DEFAULT_LOG_FILTER: &str = "info";
+
+// The log level is an env filter like 'info,task_score=debug', the format is either 'json' or 'text'
+pub fn init(config: &AppConfig) {
+    let filter = EnvFilter::try_new(&config.log_level).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = if config.log_format == "json" {
+        builder.json().with_current_span(true).try_init()
+    } else {
+        builder.try_init()
+    };
+
+    if let Err(err) = result {
+        eprintln!("Unable to initialize logging: {}", err);
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Instant;
+
+use rand::Rng;
+use rocket::{Request, Response, Data, http::Header, fairing::{Fairing, Info, Kind}, request::{FromRequest, Outcome}};
+use rocket_okapi::OpenApiFromRequest;
+use tracing::{info, info_span, Span};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+const REQUEST_ID_LEN: usize = 16;
+const MAX_INCOMING_REQUEST_ID_LEN: usize = 64;
+
+// Correlation id of a request, either taken over from the X-Request-Id header or generated
+#[derive(Clone, OpenApiFromRequest)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn of(request: &Request<'_>) -> RequestId {
+        request.local_cache(|| RequestId::from_header(request).unwrap_or_else(RequestId::generate)).clone()
+    }
+
+    // Span to instrument the work done on behalf of the request with, e.g. repository calls
+    pub fn span(&self) -> Span {
+        info_span!("request", request_id = %self.0)
+    }
+
+    fn from_header(request: &Request<'_>) -> Option<RequestId> {
+        request.headers().get_one(REQUEST_ID_HEADER)
+            .filter(|id| !id.is_empty() && id.len() <= MAX_INCOMING_REQUEST_ID_LEN)
+            .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map(|id| RequestId(id.to_owned()))
+    }
+
+    fn generate() -> RequestId {
+        let mut rng = rand::thread_rng();
+        RequestId((0..REQUEST_ID_LEN).map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char).collect())
+    }
+}
+
+#[async_trait]
+impl <'a> FromRequest<'a> for RequestId {
+    type Error = String;
+
+    async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestId::of(request))
+    }
+}
+
+struct RequestStart(Option<Instant>);
+
+// Assigns every request its id, echoes it as response header and logs the handled request
+pub struct RequestTracing;
+
+#[async_trait]
+impl Fairing for RequestTracing {
+    fn info(&self) -> Info {
+        Info { name: "Request tracing", kind: Kind::Request | Kind::Response }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        RequestId::of(request);
+        request.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let request_id = RequestId::of(request);
+        let duration_ms = request.local_cache(|| RequestStart(None)).0.map(|start| start.elapsed().as_millis() as u64);
+
+        info!(request_id = %request_id.0, method = %request.method(), uri = %request.uri().path(), status = response.status().code, duration_ms, "Request handled");
+        response.set_header(Header::new(REQUEST_ID_HEADER, request_id.0));
+    }
+}
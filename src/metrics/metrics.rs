@@ -0,0 +1,86 @@
+use std::{sync::OnceLock, time::Duration};
+
+use prometheus::{HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder, Encoder};
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub db_query_duration: HistogramVec,
+    pub db_query_errors: IntCounterVec,
+    pub scores: IntCounter,
+    pub points_awarded: IntCounterVec,
+    pub logins: IntCounter,
+    pub failed_logins: IntCounter,
+    pub active_sessions: IntGauge,
+}
+
+impl Metrics {
+    // Metrics are recorded from resources, fairings and repositories alike, hence there is a single process wide instance
+    pub fn global() -> &'static Metrics {
+        METRICS.get_or_init(Metrics::new)
+    }
+
+    fn new() -> Metrics {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(Opts::new("taskscore_http_requests_total", "Number of handled HTTP requests"), &["method", "route", "status"]).unwrap();
+        let http_request_duration = HistogramVec::new(HistogramOpts::new("taskscore_http_request_duration_seconds", "Duration of handling HTTP requests"), &["method", "route"]).unwrap();
+        let db_query_duration = HistogramVec::new(HistogramOpts::new("taskscore_db_query_duration_seconds", "Duration of database statements")
+            .buckets(vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]), &["statement"]).unwrap();
+        let db_query_errors = IntCounterVec::new(Opts::new("taskscore_db_query_errors_total", "Number of failed database statements"), &["statement"]).unwrap();
+        let scores = IntCounter::new("taskscore_scores_total", "Number of scored tasks").unwrap();
+        let points_awarded = IntCounterVec::new(Opts::new("taskscore_points_awarded_total", "Points awarded for scored tasks"), &["task"]).unwrap();
+        let logins = IntCounter::new("taskscore_logins_total", "Number of successful logins").unwrap();
+        let failed_logins = IntCounter::new("taskscore_failed_logins_total", "Number of failed logins").unwrap();
+        let active_sessions = IntGauge::new("taskscore_active_sessions", "Number of sessions logged in and neither logged out nor expired").unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_request_duration.clone())).unwrap();
+        registry.register(Box::new(db_query_duration.clone())).unwrap();
+        registry.register(Box::new(db_query_errors.clone())).unwrap();
+        registry.register(Box::new(scores.clone())).unwrap();
+        registry.register(Box::new(points_awarded.clone())).unwrap();
+        registry.register(Box::new(logins.clone())).unwrap();
+        registry.register(Box::new(failed_logins.clone())).unwrap();
+        registry.register(Box::new(active_sessions.clone())).unwrap();
+
+        Metrics { registry, http_requests, http_request_duration, db_query_duration, db_query_errors, scores, points_awarded, logins, failed_logins, active_sessions }
+    }
+
+    pub fn observe_db_query(&self, statement_name: &str, duration: Duration, succeeded: bool) {
+        self.db_query_duration.with_label_values(&[statement_name]).observe(duration.as_secs_f64());
+        if !succeeded {
+            self.db_query_errors.with_label_values(&[statement_name]).inc();
+        }
+    }
+
+    pub fn record_score(&self, task_name: &str, points: u16) {
+        self.scores.inc();
+        self.points_awarded.with_label_values(&[task_name]).inc_by(points as u64);
+    }
+
+    // Renders all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> Result<String, String> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).map_err(|err| err.to_string())?;
+        String::from_utf8(buffer).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+
+    #[test]
+    fn test_render_contains_recorded_score() {
+        let metrics = Metrics::global();
+        metrics.record_score("Kaffee kochen", 75);
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains("taskscore_points_awarded_total{task=\"Kaffee kochen\"}"));
+        assert!(rendered.contains("taskscore_scores_total"));
+    }
+}
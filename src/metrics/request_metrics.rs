@@ -0,0 +1,34 @@
+use std::time::Instant;
+
+use rocket::{Request, Response, Data, fairing::{Fairing, Info, Kind}};
+
+use super::metrics::Metrics;
+
+struct RequestStart(Option<Instant>);
+
+// Counts handled requests and measures their duration per route
+pub struct RequestMetrics;
+
+#[async_trait]
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info { name: "Request metrics", kind: Kind::Request | Kind::Response }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let start = request.local_cache(|| RequestStart(None));
+        // Using the mounted uri instead of the requested one keeps the number of label values bounded
+        let route = request.route().map_or("unmatched".to_owned(), |route| route.uri.to_string());
+        let method = request.method().as_str();
+
+        let metrics = Metrics::global();
+        metrics.http_requests.with_label_values(&[method, &route, &response.status().code.to_string()]).inc();
+        if let Some(start) = start.0 {
+            metrics.http_request_duration.with_label_values(&[method, &route]).observe(start.elapsed().as_secs_f64());
+        }
+    }
+}
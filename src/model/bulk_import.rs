@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use super::{Task, User, user::generate_password};
+
+pub const USER_TEMPLATE: &str = "username,display_name,team,password\njdoe,John Doe,Church,\n";
+pub const TASK_TEMPLATE: &str = "name,points,enabled\nKaffee kochen,75,true\n";
+
+#[derive(serde::Deserialize)]
+struct UserRow {
+    username: Option<String>,
+    display_name: Option<String>,
+    team: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct TaskRow {
+    name: Option<String>,
+    points: Option<String>,
+    enabled: Option<String>,
+    // Optional column, tasks do not require verification unless it says so
+    requires_verification: Option<String>,
+}
+
+// A user to be created, the password is already hashed
+pub struct ImportedUser {
+    pub user: User,
+    pub team: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct RowResult {
+    // Line within the file, the header being line 1
+    pub line: u64,
+    pub errors: Vec<String>,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct GeneratedPassword {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct CsvImportReport {
+    pub dry_run: bool,
+    pub applied: bool,
+    pub rows: Vec<RowResult>,
+    // Only shown once, for rows without a password
+    pub generated_passwords: Vec<GeneratedPassword>,
+}
+
+impl CsvImportReport {
+    pub fn is_valid(&self) -> bool {
+        self.rows.iter().all(|row| row.errors.is_empty())
+    }
+}
+
+fn read_rows<T: DeserializeOwned>(content: &str) -> Result<Vec<(u64, Result<T, String>)>, String> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(content.as_bytes());
+    reader.headers().map_err(|err| format!("Unable to read the header: {}", err))?;
+
+    Ok(reader.deserialize().enumerate()
+        .map(|(index, row)| (index as u64 + 2, row.map_err(|err| err.to_string())))
+        .collect())
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.is_empty())
+}
+
+// Validates every row against the file itself and the existing usernames and team names
+pub fn parse_users(content: &str, existing_usernames: &HashSet<String>, team_names: &HashSet<String>) -> Result<(Vec<ImportedUser>, Vec<RowResult>, Vec<GeneratedPassword>), String> {
+    let mut users = vec![];
+    let mut results = vec![];
+    let mut passwords = vec![];
+    let mut usernames = HashSet::new();
+
+    for (line, row) in read_rows::<UserRow>(content)? {
+        let mut errors = vec![];
+        match row {
+            Err(err_msg) => errors.push(err_msg),
+            Ok(row) => {
+                let username = non_empty(row.username);
+                let display_name = non_empty(row.display_name);
+                let team = non_empty(row.team);
+                match &username {
+                    None => errors.push("Username is missing".to_owned()),
+                    Some(username) if existing_usernames.contains(username) => errors.push(format!("Username '{}' is not available", username)),
+                    Some(username) if !usernames.insert(username.clone()) => errors.push(format!("Username '{}' is used more than once", username)),
+                    Some(_) => (),
+                }
+                if display_name.is_none() {
+                    errors.push("Display name is missing".to_owned());
+                }
+                if let Some(team) = team.as_ref().filter(|team| !team_names.contains(*team)) {
+                    errors.push(format!("Team '{}' does not exist", team));
+                }
+
+                if let (true, Some(username), Some(display_name)) = (errors.is_empty(), username, display_name) {
+                    let password = non_empty(row.password).unwrap_or_else(|| {
+                        let password = generate_password();
+                        passwords.push(GeneratedPassword { username: username.clone(), password: password.clone() });
+                        password
+                    });
+                    let mut user = User::new(0, username, display_name, false);
+                    user.set_password(password);
+                    users.push(ImportedUser { user, team });
+                }
+            }
+        }
+        results.push(RowResult { line, errors });
+    }
+
+    Ok((users, results, passwords))
+}
+
+fn parse_flag(value: Option<String>, default: bool, column: &str) -> Result<bool, String> {
+    match non_empty(value).map(|value| value.to_lowercase()).as_deref() {
+        None => Ok(default),
+        Some("true") | Some("yes") | Some("1") => Ok(true),
+        Some("false") | Some("no") | Some("0") => Ok(false),
+        Some(other) => Err(format!("'{}' is not a valid value for {}", other, column)),
+    }
+}
+
+// Validates every row against the file itself and the names of the existing tasks
+pub fn parse_tasks(content: &str, existing_names: &HashSet<String>) -> Result<(Vec<Task>, Vec<RowResult>), String> {
+    let mut tasks = vec![];
+    let mut results = vec![];
+    let mut names = HashSet::new();
+
+    for (line, row) in read_rows::<TaskRow>(content)? {
+        let mut errors = vec![];
+        match row {
+            Err(err_msg) => errors.push(err_msg),
+            Ok(row) => {
+                let name = non_empty(row.name);
+                match &name {
+                    None => errors.push("Name is missing".to_owned()),
+                    Some(name) if existing_names.contains(name) => errors.push(format!("Task '{}' already exists", name)),
+                    Some(name) if !names.insert(name.clone()) => errors.push(format!("Task '{}' is used more than once", name)),
+                    Some(_) => (),
+                }
+                let points = match non_empty(row.points) {
+                    None => Err("Points are missing".to_owned()),
+                    Some(points) => points.parse::<u16>().map_err(|_| format!("'{}' is not a number of points between 0 and 65535", points)),
+                };
+                let enabled = parse_flag(row.enabled, true, "enabled");
+                let requires_verification = parse_flag(row.requires_verification, false, "requires_verification");
+
+                match (name, points, enabled, requires_verification) {
+                    (Some(name), Ok(points), Ok(enabled), Ok(requires_verification)) if errors.is_empty() =>
+                        tasks.push(Task { id: 0, name, points, enabled, requires_verification }),
+                    (_, points, enabled, requires_verification) => {
+                        errors.extend(points.err());
+                        errors.extend(enabled.err());
+                        errors.extend(requires_verification.err());
+                    }
+                }
+            }
+        }
+        results.push(RowResult { line, errors });
+    }
+
+    Ok((tasks, results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_users() {
+        let existing: HashSet<String> = ["topher".to_owned()].into();
+        let teams: HashSet<String> = ["Church".to_owned()].into();
+        let content = "username,display_name,team,password\njdoe,John Doe,Church,secret\nmmuster,Max, ,\ntopher,Topher,,\njdoe,,Babes,\n";
+
+        let (users, results, passwords) = parse_users(content, &existing, &teams).unwrap();
+
+        assert_eq!(2, users.len());
+        assert_eq!(Some("Church".to_owned()), users[0].team);
+        assert!(users[0].user.verify_password(&Some("secret".to_owned())));
+        assert_eq!(None, users[1].team);
+        assert_eq!("mmuster", passwords[0].username);
+        assert!(users[1].user.verify_password(&Some(passwords[0].password.clone())));
+
+        assert!(results[0].errors.is_empty() && results[1].errors.is_empty());
+        assert_eq!(vec!["Username 'topher' is not available".to_owned()], results[2].errors);
+        assert_eq!(5, results[3].line);
+        assert_eq!(3, results[3].errors.len());
+    }
+
+    #[test]
+    fn test_parse_tasks() {
+        let existing: HashSet<String> = ["Kaffee kochen".to_owned()].into();
+        let content = "name,points,enabled\nFenster putzen,20,\nKaffee kochen,75,true\nMüll rausbringen,-3,vielleicht\n";
+
+        let (tasks, results) = parse_tasks(content, &existing).unwrap();
+
+        assert_eq!(1, tasks.len());
+        assert!(tasks[0].enabled);
+        assert_eq!(vec!["Task 'Kaffee kochen' already exists".to_owned()], results[1].errors);
+        assert_eq!(2, results[2].errors.len());
+
+        let (tasks, _) = parse_tasks("name,points,requires_verification\nFenster putzen,20,yes\n", &existing).unwrap();
+        assert!(tasks[0].requires_verification);
+    }
+
+    #[test]
+    fn test_templates_are_valid() {
+        let teams: HashSet<String> = ["Church".to_owned()].into();
+        assert!(parse_users(USER_TEMPLATE, &HashSet::new(), &teams).unwrap().1.iter().all(|row| row.errors.is_empty()));
+        assert!(parse_tasks(TASK_TEMPLATE, &HashSet::new()).unwrap().1.iter().all(|row| row.errors.is_empty()));
+    }
+}
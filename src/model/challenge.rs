@@ -0,0 +1,267 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{User, event::ActivityEvent, ledger::{PointTransaction, TransactionKind}, snapshot::TeamRecord, trophy::Trophy};
+
+// Either side of a challenge, given as {"user": 1} or {"team": 2}
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Participant {
+    User(u32),
+    Team(u32),
+}
+
+impl Participant {
+    // The current members of a team, a user stands for themselves
+    pub fn member_ids(&self, teams: &[TeamRecord]) -> Vec<u32> {
+        match self {
+            Participant::User(user_id) => vec![*user_id],
+            Participant::Team(team_id) => teams.iter().find(|team| team.id == *team_id).map(|team| team.member_ids.clone()).unwrap_or_default(),
+        }
+    }
+
+    fn team_ids(&self, teams: &[TeamRecord]) -> Vec<u32> {
+        match self {
+            Participant::User(user_id) => teams.iter().filter(|team| team.member_ids.contains(user_id)).map(|team| team.id).collect(),
+            Participant::Team(team_id) => vec![*team_id],
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeStatus {
+    Proposed,
+    Accepted,
+    Declined,
+    Resolved,
+}
+
+impl ChallengeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallengeStatus::Proposed => "proposed",
+            ChallengeStatus::Accepted => "accepted",
+            ChallengeStatus::Declined => "declined",
+            ChallengeStatus::Resolved => "resolved",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<ChallengeStatus> {
+        match status {
+            "proposed" => Some(ChallengeStatus::Proposed),
+            "accepted" => Some(ChallengeStatus::Accepted),
+            "declined" => Some(ChallengeStatus::Declined),
+            "resolved" => Some(ChallengeStatus::Resolved),
+            _ => None,
+        }
+    }
+}
+
+// What a side has scored of the challenge's tasks within its window
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Debug, JsonSchema)]
+pub struct ChallengeResult {
+    pub participant: Participant,
+    pub count: u32,
+    pub points: u64,
+}
+
+// A time-boxed head-to-head, the side with more points from the given tasks wins
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Challenge {
+    pub id: u32,
+    pub name: String,
+    pub challenger: Participant,
+    pub opponent: Participant,
+    pub task_ids: Vec<u32>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    // Points every member of the losing side pays to every member of the winning side
+    pub stake: u16,
+    pub award_trophy: bool,
+    pub status: ChallengeStatus,
+    pub proposed_by: String,
+    pub decided_by: Option<String>,
+    // Final once the challenge is resolved, the standings so far while it is running
+    #[serde(default)]
+    pub results: Vec<ChallengeResult>,
+    // None on a draw
+    pub winner: Option<Participant>,
+}
+
+#[derive(serde::Deserialize, JsonSchema)]
+pub struct ChallengeRequest {
+    pub name: String,
+    pub challenger: Participant,
+    pub opponent: Participant,
+    pub task_ids: Vec<u32>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(default)]
+    pub stake: u16,
+    #[serde(default)]
+    pub award_trophy: bool,
+}
+
+impl Challenge {
+    pub fn new(proposed_by: String, request: ChallengeRequest) -> Challenge {
+        Challenge { id: 0, name: request.name.trim().to_owned(), challenger: request.challenger, opponent: request.opponent, task_ids: request.task_ids,
+            starts_at: request.starts_at, ends_at: request.ends_at, stake: request.stake, award_trophy: request.award_trophy,
+            status: ChallengeStatus::Proposed, proposed_by, decided_by: None, results: vec![], winner: None }
+    }
+
+    // Checks the challenge on its own, whether the participants and tasks exist is up to the caller
+    pub fn check(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("The name of a challenge must not be empty".to_owned());
+        }
+        if self.challenger == self.opponent {
+            return Err("A challenge needs two different participants".to_owned());
+        }
+        if matches!(self.challenger, Participant::User(_)) != matches!(self.opponent, Participant::User(_)) {
+            return Err("Users can only challenge users and teams only teams".to_owned());
+        }
+        if self.task_ids.is_empty() {
+            return Err("A challenge needs at least one task".to_owned());
+        }
+        if self.ends_at <= self.starts_at || self.ends_at <= now {
+            return Err("A challenge has to end after it starts and in the future".to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn decide(&mut self, accepted: bool, decided_by: String, now: DateTime<Utc>) -> Result<(), String> {
+        if self.status != ChallengeStatus::Proposed {
+            return Err(format!("Challenge '{}' has already been {}", self.name, self.status.as_str()));
+        }
+        if accepted && self.ends_at <= now {
+            return Err(format!("Challenge '{}' has already ended", self.name));
+        }
+        self.status = if accepted { ChallengeStatus::Accepted } else { ChallengeStatus::Declined };
+        self.decided_by = Some(decided_by);
+        Ok(())
+    }
+
+    // Accepted challenges are resolved once they have ended
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.status == ChallengeStatus::Accepted && self.ends_at <= now
+    }
+
+    pub fn standings(&self, users: &[User], teams: &[TeamRecord]) -> Vec<ChallengeResult> {
+        [self.challenger, self.opponent].iter().map(|participant| {
+            let member_ids = participant.member_ids(teams);
+            let scores: Vec<u64> = users.iter()
+                .filter(|user| member_ids.contains(&user.id))
+                .flat_map(|user| user.scores.iter())
+                .filter(|score| self.task_ids.contains(&score.task.id) && score.scored_at >= self.starts_at && score.scored_at < self.ends_at)
+                .map(|score| score.points as u64)
+                .collect();
+            ChallengeResult { participant: *participant, count: scores.len() as u32, points: scores.iter().sum() }
+        }).collect()
+    }
+
+    // Records the final standings and the winner, returning the stakes to book
+    pub fn resolve(&mut self, users: &[User], teams: &[TeamRecord]) -> Vec<PointTransaction> {
+        self.results = self.standings(users, teams);
+        self.status = ChallengeStatus::Resolved;
+        self.winner = match (&self.results[0], &self.results[1]) {
+            (challenger, opponent) if challenger.points > opponent.points => Some(self.challenger),
+            (challenger, opponent) if challenger.points < opponent.points => Some(self.opponent),
+            _ => None
+        };
+
+        let (winner, loser) = match self.winner {
+            Some(winner) if self.stake > 0 => (winner, if winner == self.challenger { self.opponent } else { self.challenger }),
+            _ => return vec![]
+        };
+        let reference = Some(format!("Challenge: {}", self.name));
+        let lost = loser.member_ids(teams).into_iter()
+            .map(|user_id| PointTransaction::new(user_id, TransactionKind::Stake, -(self.stake as i64), reference.clone(), None));
+        let won = winner.member_ids(teams).into_iter()
+            .map(|user_id| PointTransaction::new(user_id, TransactionKind::Stake, self.stake as i64, reference.clone(), None));
+        lost.chain(won).collect()
+    }
+
+    pub fn trophies(&self, teams: &[TeamRecord]) -> Vec<Trophy> {
+        match self.winner {
+            Some(winner) if self.award_trophy => winner.member_ids(teams).into_iter().map(|user_id| Trophy::new(user_id, format!("Challenge won: {}", self.name), None)).collect(),
+            _ => vec![]
+        }
+    }
+
+    // The teams of both sides, or those the users are members of
+    pub fn team_ids(&self, teams: &[TeamRecord]) -> Vec<u32> {
+        let mut team_ids = self.challenger.team_ids(teams);
+        team_ids.extend(self.opponent.team_ids(teams));
+        team_ids.sort_unstable();
+        team_ids.dedup();
+        team_ids
+    }
+
+    pub fn event(&self) -> ActivityEvent {
+        match self.status {
+            ChallengeStatus::Proposed => ActivityEvent::ChallengeProposed { challenge_id: self.id, name: self.name.clone(), challenger: self.challenger, opponent: self.opponent },
+            ChallengeStatus::Resolved => ActivityEvent::ChallengeResolved { challenge_id: self.id, name: self.name.clone(), winner: self.winner, results: self.results.clone() },
+            status => ActivityEvent::ChallengeDecided { challenge_id: self.id, name: self.name.clone(), status },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::model::{Task, Score};
+
+    use super::*;
+
+    fn teams() -> Vec<TeamRecord> {
+        vec![
+            TeamRecord { id: 1, name: "Babes".to_owned(), manager_id: 1, member_ids: vec![1] },
+            TeamRecord { id: 2, name: "Church".to_owned(), manager_id: 2, member_ids: vec![2, 3] },
+        ]
+    }
+
+    fn challenge(stake: u16) -> Challenge {
+        let now = Utc::now();
+        Challenge::new("roterkohl".to_owned(), ChallengeRequest { name: "Most coffees".to_owned(), challenger: Participant::Team(2), opponent: Participant::Team(1),
+            task_ids: vec![4], starts_at: now - Duration::days(7), ends_at: now, stake, award_trophy: true })
+    }
+
+    fn user(id: u32, coffees_ago: &[i64]) -> User {
+        let mut user = User::new(id, format!("user{}", id), format!("User {}", id), false);
+        let task = Task { id: 4, name: "Kaffee kochen".to_owned(), points: 75, enabled: true, requires_verification: false };
+        user.scores = coffees_ago.iter().map(|days| Score { task: task.clone(), points: 75, scored_at: Utc::now() - Duration::days(*days) }).collect();
+        user
+    }
+
+    #[test]
+    fn test_check() {
+        let now = Utc::now() - Duration::days(1);
+        assert!(challenge(0).check(now).is_ok());
+        assert!(Challenge { opponent: Participant::Team(2), ..challenge(0) }.check(now).is_err());
+        assert!(Challenge { opponent: Participant::User(1), ..challenge(0) }.check(now).is_err());
+        assert!(Challenge { task_ids: vec![], ..challenge(0) }.check(now).is_err());
+        assert!(challenge(0).check(Utc::now() + Duration::days(1)).is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        // The coffee of 10 days ago was made before the challenge started
+        let users = vec![user(1, &[1, 2]), user(2, &[1]), user(3, &[3, 10])];
+        let mut challenge = challenge(10);
+
+        let stakes = challenge.resolve(&users, &teams());
+        assert_eq!(ChallengeStatus::Resolved, challenge.status);
+        assert_eq!(ChallengeResult { participant: Participant::Team(2), count: 2, points: 150 }, challenge.results[0]);
+        assert_eq!(None, challenge.winner);
+        assert!(stakes.is_empty());
+        assert!(challenge.trophies(&teams()).is_empty());
+
+        let users = vec![user(1, &[1]), user(2, &[1]), user(3, &[3])];
+        let stakes = challenge.resolve(&users, &teams());
+        assert_eq!(Some(Participant::Team(2)), challenge.winner);
+        assert_eq!(vec![(1, -10), (2, 10), (3, 10)], stakes.iter().map(|stake| (stake.user_id, stake.amount)).collect::<Vec<(u32, i64)>>());
+        assert_eq!(2, challenge.trophies(&teams()).len());
+    }
+}
@@ -0,0 +1,102 @@
+use rocket::{Request, request::{FromRequest, Outcome}};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Task, reward::RedemptionStatus, ledger::TransactionKind, verification::ClaimStatus, challenge::{Participant, ChallengeStatus, ChallengeResult}};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub user_id: u32,
+    pub display_name: String,
+    pub points: u64,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityEvent {
+    Score { user_id: u32, display_name: String, task_id: u32, task_name: String, points: u16, total_points: u64 },
+    Trophy { user_id: u32, display_name: String, trophy: String },
+    LeaderboardChange { leaderboard: Vec<LeaderboardEntry> },
+    UserCreated { user_id: u32, username: String, display_name: String },
+    TeamCreated { team_id: u32, name: String },
+    TaskCreated { task_id: u32, name: String, points: u16, enabled: bool },
+    TaskUpdated { task_id: u32, name: String, points: u16, enabled: bool },
+    TeamMemberAdded { team_id: u32, name: String, user_id: u32 },
+    RewardRedeemed { redemption_id: u32, user_id: u32, display_name: String, reward_id: u32, reward_name: String, cost: u16 },
+    RedemptionDecided { redemption_id: u32, user_id: u32, reward_name: String, status: RedemptionStatus },
+    PointsBooked { transaction_id: u64, user_id: u32, kind: TransactionKind, amount: i64, total_points: u64 },
+    SeasonStarted { season_id: u32, name: String },
+    SeasonEnded { season_id: u32, name: String, standings: Vec<LeaderboardEntry> },
+    LevelUp { user_id: u32, display_name: String, level: u32, title: String },
+    ScoreClaimed { claim_id: u32, user_id: u32, display_name: String, task_id: u32, task_name: String, points: u16 },
+    ScoreClaimDecided { claim_id: u32, user_id: u32, task_name: String, status: ClaimStatus },
+    ChallengeProposed { challenge_id: u32, name: String, challenger: Participant, opponent: Participant },
+    ChallengeDecided { challenge_id: u32, name: String, status: ChallengeStatus },
+    ChallengeResolved { challenge_id: u32, name: String, winner: Option<Participant>, results: Vec<ChallengeResult> },
+    Kudos { kudos_id: u32, from_user_id: u32, from_display_name: String, to_user_id: u32, to_display_name: String, message: String, points: u16 },
+}
+
+impl ActivityEvent {
+    pub fn task_saved(task: &Task, created: bool) -> ActivityEvent {
+        let (task_id, name, points, enabled) = (task.id, task.name.clone(), task.points, task.enabled);
+        if created {
+            ActivityEvent::TaskCreated { task_id, name, points, enabled }
+        } else {
+            ActivityEvent::TaskUpdated { task_id, name, points, enabled }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActivityEvent::Score { .. } => "score",
+            ActivityEvent::Trophy { .. } => "trophy",
+            ActivityEvent::LeaderboardChange { .. } => "leaderboard_change",
+            ActivityEvent::UserCreated { .. } => "user_created",
+            ActivityEvent::TeamCreated { .. } => "team_created",
+            ActivityEvent::TaskCreated { .. } => "task_created",
+            ActivityEvent::TaskUpdated { .. } => "task_updated",
+            ActivityEvent::TeamMemberAdded { .. } => "team_member_added",
+            ActivityEvent::RewardRedeemed { .. } => "reward_redeemed",
+            ActivityEvent::RedemptionDecided { .. } => "redemption_decided",
+            ActivityEvent::PointsBooked { .. } => "points_booked",
+            ActivityEvent::SeasonStarted { .. } => "season_started",
+            ActivityEvent::SeasonEnded { .. } => "season_ended",
+            ActivityEvent::LevelUp { .. } => "level_up",
+            ActivityEvent::ScoreClaimed { .. } => "score_claimed",
+            ActivityEvent::ScoreClaimDecided { .. } => "score_claim_decided",
+            ActivityEvent::ChallengeProposed { .. } => "challenge_proposed",
+            ActivityEvent::ChallengeDecided { .. } => "challenge_decided",
+            ActivityEvent::ChallengeResolved { .. } => "challenge_resolved",
+            ActivityEvent::Kudos { .. } => "kudos",
+        }
+    }
+}
+
+// An event as it has been published, numbered so clients can resume after reconnecting
+#[derive(serde::Serialize, Clone)]
+pub struct ActivityMessage {
+    pub id: u64,
+    pub team_ids: Vec<u32>,
+    pub event: ActivityEvent,
+}
+
+impl ActivityMessage {
+    // Events without any team (e.g. leaderboard changes) are of interest to everyone
+    pub fn concerns_team(&self, team_id: u32) -> bool {
+        self.team_ids.is_empty() || self.team_ids.contains(&team_id)
+    }
+}
+
+pub struct LastEventId(pub Option<u64>);
+
+#[async_trait]
+impl <'a> FromRequest<'a> for LastEventId {
+    type Error = String;
+
+    async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        let last_event_id = request.headers().get_one("Last-Event-ID")
+            .and_then(|id| str::parse::<u64>(id.trim()).ok());
+
+        Outcome::Success(LastEventId(last_event_id))
+    }
+}
@@ -0,0 +1,39 @@
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    pub detail: Option<String>,
+}
+
+impl DependencyHealth {
+    pub fn up(name: &str, detail: Option<String>) -> DependencyHealth {
+        DependencyHealth { name: name.to_owned(), status: HealthStatus::Up, detail }
+    }
+
+    pub fn down(name: &str, detail: String) -> DependencyHealth {
+        DependencyHealth { name: name.to_owned(), status: HealthStatus::Down, detail: Some(detail) }
+    }
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checks: Vec<DependencyHealth>,
+}
+
+impl HealthReport {
+    // The report is only up if every single dependency is
+    pub fn create(checks: Vec<DependencyHealth>) -> HealthReport {
+        let status = if checks.iter().all(|check| check.status == HealthStatus::Up) { HealthStatus::Up } else { HealthStatus::Down };
+        HealthReport { status, checks }
+    }
+}
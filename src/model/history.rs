@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc, TimeZone};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::Score;
+
+#[derive(FromFormField, serde::Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryGrouping {
+    Day,
+    Week,
+    Month,
+    Task,
+}
+
+#[derive(Clone, Default)]
+pub struct ScoreFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub task_id: Option<u32>,
+}
+
+impl ScoreFilter {
+    // Dates are given as 'YYYY-MM-DD', both bounds are inclusive
+    pub fn parse(from: Option<&str>, to: Option<&str>, task_id: Option<u32>) -> Result<ScoreFilter, String> {
+        let from = match from {
+            Some(from) => Some(Utc.from_utc_datetime(&parse_date(from)?.and_hms_opt(0, 0, 0).unwrap())),
+            None => None
+        };
+        let to = match to {
+            Some(to) => Some(Utc.from_utc_datetime(&parse_date(to)?.and_hms_milli_opt(23, 59, 59, 999).unwrap())),
+            None => None
+        };
+
+        if from.is_some() && to.is_some() && from > to {
+            return Err("'from' must not be after 'to'".to_owned());
+        }
+
+        Ok(ScoreFilter { from, to, task_id })
+    }
+
+    pub fn matches(&self, score: &Score) -> bool {
+        self.from.map_or(true, |from| score.scored_at >= from)
+            && self.to.map_or(true, |to| score.scored_at <= to)
+            && self.task_id.map_or(true, |task_id| score.task.id == task_id)
+    }
+}
+
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").or(Err(format!("'{}' is not a valid date (expected YYYY-MM-DD)", date)))
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct ScoreHistoryEntry {
+    // The day, week or month, or the name of the task
+    pub key: String,
+    pub task_id: Option<u32>,
+    pub points: u32,
+    pub count: u32,
+    #[serde(skip)]
+    latest: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct ScoreHistory {
+    pub user_id: u32,
+    pub total_points: u32,
+    pub total_count: u32,
+    pub entries: Vec<ScoreHistoryEntry>,
+}
+
+impl ScoreHistory {
+    // Without a grouping every matching score becomes an entry of its own, keyed by its timestamp
+    pub fn create(user_id: u32, scores: &[Score], filter: &ScoreFilter, grouping: Option<HistoryGrouping>) -> ScoreHistory {
+        let matching: Vec<&Score> = scores.iter().filter(|score| filter.matches(score)).collect();
+
+        let total_points = matching.iter().map(|score| score.points as u32).sum();
+        let total_count = matching.len() as u32;
+
+        let entries = match grouping {
+            Some(grouping) => ScoreHistory::group(&matching, grouping),
+            None => matching.iter().map(|score| ScoreHistoryEntry {
+                key: score.scored_at.to_rfc3339(),
+                task_id: Some(score.task.id),
+                points: score.points as u32,
+                count: 1,
+                latest: score.scored_at,
+            }).collect()
+        };
+
+        ScoreHistory { user_id, total_points, total_count, entries }
+    }
+
+    // Task groups are keyed by the task id and labelled with the name the task had at its latest score,
+    // so renamed tasks stay together and tasks sharing a name stay apart
+    fn group(scores: &[&Score], grouping: HistoryGrouping) -> Vec<ScoreHistoryEntry> {
+        let mut groups: BTreeMap<(Option<u32>, String), ScoreHistoryEntry> = BTreeMap::new();
+
+        for score in scores {
+            let (key, task_id) = match grouping {
+                HistoryGrouping::Day => (score.scored_at.format("%Y-%m-%d").to_string(), None),
+                HistoryGrouping::Week => {
+                    let week = score.scored_at.iso_week();
+                    (format!("{}-W{:02}", week.year(), week.week()), None)
+                },
+                HistoryGrouping::Month => (score.scored_at.format("%Y-%m").to_string(), None),
+                HistoryGrouping::Task => (score.task.name.clone(), Some(score.task.id)),
+            };
+
+            let group_key = match task_id {
+                Some(task_id) => (Some(task_id), String::new()),
+                None => (None, key.clone()),
+            };
+            let entry = groups.entry(group_key).or_insert(ScoreHistoryEntry { key: key.clone(), task_id, points: 0, count: 0, latest: score.scored_at });
+            if score.scored_at >= entry.latest {
+                entry.key = key;
+                entry.latest = score.scored_at;
+            }
+            entry.points += score.points as u32;
+            entry.count += 1;
+        }
+
+        groups.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::model::{Score, Task};
+
+    use super::{HistoryGrouping, ScoreFilter, ScoreHistory};
+
+    fn score(task_id: u32, points: u16, year: i32, month: u32, day: u32) -> Score {
+        let task = Task { id: task_id, name: format!("Task {}", task_id), points, enabled: true, requires_verification: false };
+        Score { task, points, scored_at: Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(12, 0, 0).unwrap()) }
+    }
+
+    fn scores() -> Vec<Score> {
+        vec![
+            score(1, 10, 2022, 3, 1),
+            score(1, 10, 2022, 3, 1),
+            score(4, 75, 2022, 3, 8),
+            score(4, 75, 2022, 4, 2),
+        ]
+    }
+
+    #[test]
+    fn test_history_without_grouping() {
+        let history = ScoreHistory::create(1, &scores(), &ScoreFilter::default(), None);
+        assert_eq!(4, history.entries.len());
+        assert_eq!(170, history.total_points);
+        assert_eq!(4, history.total_count);
+    }
+
+    #[test]
+    fn test_history_grouped_by_day() {
+        let history = ScoreHistory::create(1, &scores(), &ScoreFilter::default(), Some(HistoryGrouping::Day));
+        assert_eq!(3, history.entries.len());
+        assert_eq!("2022-03-01", history.entries[0].key);
+        assert_eq!(20, history.entries[0].points);
+        assert_eq!(2, history.entries[0].count);
+    }
+
+    #[test]
+    fn test_history_grouped_by_month() {
+        let history = ScoreHistory::create(1, &scores(), &ScoreFilter::default(), Some(HistoryGrouping::Month));
+        assert_eq!(2, history.entries.len());
+        assert_eq!("2022-03", history.entries[0].key);
+        assert_eq!(95, history.entries[0].points);
+    }
+
+    #[test]
+    fn test_history_grouped_by_task() {
+        let history = ScoreHistory::create(1, &scores(), &ScoreFilter::default(), Some(HistoryGrouping::Task));
+        assert_eq!(2, history.entries.len());
+        assert_eq!(Some(4), history.entries[1].task_id);
+        assert_eq!(150, history.entries[1].points);
+
+        let mut renamed = score(4, 75, 2022, 4, 3);
+        renamed.task.name = "Kaffee kochen".to_owned();
+        let mut same_name = score(5, 75, 2022, 4, 3);
+        same_name.task.name = "Kaffee kochen".to_owned();
+        let scores = [scores(), vec![renamed, same_name]].concat();
+        let history = ScoreHistory::create(1, &scores, &ScoreFilter::default(), Some(HistoryGrouping::Task));
+        assert_eq!(3, history.entries.len());
+        assert_eq!(("Kaffee kochen", Some(4), 3), (history.entries[1].key.as_str(), history.entries[1].task_id, history.entries[1].count));
+        assert_eq!(Some(5), history.entries[2].task_id);
+    }
+
+    #[test]
+    fn test_history_filtered() {
+        let filter = ScoreFilter::parse(Some("2022-03-01"), Some("2022-03-31"), Some(4)).unwrap();
+        let history = ScoreHistory::create(1, &scores(), &filter, None);
+        assert_eq!(1, history.total_count);
+        assert_eq!(75, history.total_points);
+    }
+
+    #[test]
+    fn test_filter_invalid() {
+        assert!(ScoreFilter::parse(Some("03/01/2022"), None, None).is_err());
+        assert!(ScoreFilter::parse(Some("2022-04-01"), Some("2022-03-01"), None).is_err());
+    }
+}
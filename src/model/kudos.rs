@@ -0,0 +1,144 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{event::ActivityEvent, ledger::{PointTransaction, TransactionKind}};
+
+const MAX_MESSAGE_LEN: usize = 280;
+
+// Recognition of one user by another, the points are booked for the recipient
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Kudos {
+    pub id: u32,
+    pub from_user_id: u32,
+    pub to_user_id: u32,
+    pub message: String,
+    pub points: u16,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(serde::Deserialize, JsonSchema)]
+pub struct KudosRequest {
+    pub to_user_id: u32,
+    pub message: String,
+    pub points: u16,
+}
+
+impl Kudos {
+    pub fn new(from_user_id: u32, request: KudosRequest) -> Kudos {
+        Kudos { id: 0, from_user_id, to_user_id: request.to_user_id, message: request.message.trim().to_owned(), points: request.points, at: Utc::now() }
+    }
+
+    // Checks the kudos on their own, the limits depend on what has been given before
+    pub fn check(&self) -> Result<(), String> {
+        if self.from_user_id == self.to_user_id {
+            return Err("Kudos cannot be given to oneself".to_owned());
+        }
+        if self.points == 0 {
+            return Err("Kudos have to be worth at least one point".to_owned());
+        }
+        if self.message.is_empty() || self.message.chars().count() > MAX_MESSAGE_LEN {
+            return Err(format!("The message must have between 1 and {} characters", MAX_MESSAGE_LEN));
+        }
+        Ok(())
+    }
+
+    pub fn transaction(&self, from_display_name: &str) -> PointTransaction {
+        PointTransaction { created_at: self.at, ..PointTransaction::new(self.to_user_id, TransactionKind::Kudos, self.points as i64, Some(format!("Kudos from {}", from_display_name)), None) }
+    }
+
+    pub fn event(&self, from_display_name: String, to_display_name: String) -> ActivityEvent {
+        ActivityEvent::Kudos { kudos_id: self.id, from_user_id: self.from_user_id, from_display_name, to_user_id: self.to_user_id, to_display_name,
+            message: self.message.clone(), points: self.points }
+    }
+}
+
+// Weeks start on Monday at midnight (UTC)
+pub fn week_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    let monday = at.naive_utc().date() - Duration::days(at.weekday().num_days_from_monday() as i64);
+    Utc.from_utc_datetime(&monday.and_hms_opt(0, 0, 0).unwrap())
+}
+
+#[derive(Clone, Copy)]
+pub struct KudosLimits {
+    // Points a user may give away per week
+    pub weekly_allowance: u32,
+    // Points a user may give to the same recipient per week
+    pub recipient_cap: u32,
+}
+
+impl KudosLimits {
+    // The kudos given before are those of the giver, others are ignored
+    pub fn check(&self, kudos: &Kudos, given_before: &[Kudos]) -> Result<(), String> {
+        let week = week_start(kudos.at);
+        let this_week: Vec<&Kudos> = given_before.iter().filter(|given| given.from_user_id == kudos.from_user_id && given.at >= week).collect();
+
+        let given: u32 = this_week.iter().map(|given| given.points as u32).sum();
+        if given + kudos.points as u32 > self.weekly_allowance {
+            return Err(format!("Only {} of the weekly {} kudos points are left", self.weekly_allowance.saturating_sub(given), self.weekly_allowance));
+        }
+        let to_recipient: u32 = this_week.iter().filter(|given| given.to_user_id == kudos.to_user_id).map(|given| given.points as u32).sum();
+        if to_recipient + kudos.points as u32 > self.recipient_cap {
+            return Err(format!("At most {} kudos points per week can go to the same user", self.recipient_cap));
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct KudosAllowance {
+    pub user_id: u32,
+    pub weekly_allowance: u32,
+    pub given: u32,
+    pub remaining: u32,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl KudosAllowance {
+    pub fn create(user_id: u32, kudos: &[Kudos], limits: &KudosLimits, now: DateTime<Utc>) -> KudosAllowance {
+        let week = week_start(now);
+        let given = kudos.iter().filter(|given| given.from_user_id == user_id && given.at >= week).map(|given| given.points as u32).sum();
+        KudosAllowance { user_id, weekly_allowance: limits.weekly_allowance, given, remaining: limits.weekly_allowance.saturating_sub(given), resets_at: week + Duration::days(7) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn kudos(to_user_id: u32, points: u16, day: u32) -> Kudos {
+        // March 7th 2022 is a Monday
+        let at = Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2022, 3, day).unwrap().and_hms_opt(12, 0, 0).unwrap());
+        Kudos { id: 0, from_user_id: 1, to_user_id, message: "Danke".to_owned(), points, at }
+    }
+
+    const LIMITS: KudosLimits = KudosLimits { weekly_allowance: 30, recipient_cap: 20 };
+
+    #[test]
+    fn test_check() {
+        assert!(kudos(2, 5, 7).check().is_ok());
+        assert!(kudos(1, 5, 7).check().is_err());
+        assert!(kudos(2, 0, 7).check().is_err());
+        assert!(Kudos { message: String::new(), ..kudos(2, 5, 7) }.check().is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let given = vec![kudos(2, 15, 4), kudos(2, 15, 7), kudos(3, 10, 8)];
+        assert!(LIMITS.check(&kudos(2, 5, 9), &given).is_ok());
+        assert!(LIMITS.check(&kudos(2, 6, 9), &given).is_err());
+        assert!(LIMITS.check(&kudos(4, 6, 9), &given).is_err());
+        // Last week's kudos no longer count
+        assert!(LIMITS.check(&kudos(2, 20, 14), &given).is_ok());
+    }
+
+    #[test]
+    fn test_allowance() {
+        let given = vec![kudos(2, 15, 4), kudos(2, 15, 7)];
+        let allowance = KudosAllowance::create(1, &given, &LIMITS, kudos(2, 1, 10).at);
+        assert_eq!(15, allowance.given);
+        assert_eq!(15, allowance.remaining);
+        assert_eq!(kudos(2, 1, 14).at - Duration::hours(12), allowance.resets_at);
+    }
+}
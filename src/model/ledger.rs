@@ -0,0 +1,212 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Score, history::ScoreFilter};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Score,
+    Revoke,
+    Redeem,
+    Refund,
+    Bonus,
+    Adjustment,
+    Kudos,
+    Stake,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Score => "score",
+            TransactionKind::Revoke => "revoke",
+            TransactionKind::Redeem => "redeem",
+            TransactionKind::Refund => "refund",
+            TransactionKind::Bonus => "bonus",
+            TransactionKind::Adjustment => "adjustment",
+            TransactionKind::Kudos => "kudos",
+            TransactionKind::Stake => "stake",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<TransactionKind> {
+        match kind {
+            "score" => Some(TransactionKind::Score),
+            "revoke" => Some(TransactionKind::Revoke),
+            "redeem" => Some(TransactionKind::Redeem),
+            "refund" => Some(TransactionKind::Refund),
+            "bonus" => Some(TransactionKind::Bonus),
+            "adjustment" => Some(TransactionKind::Adjustment),
+            "kudos" => Some(TransactionKind::Kudos),
+            "stake" => Some(TransactionKind::Stake),
+            _ => None,
+        }
+    }
+
+    // Earned points make up the lifetime total, redemptions and refunds only move the balance
+    pub fn is_earned(&self) -> bool {
+        !matches!(self, TransactionKind::Redeem | TransactionKind::Refund)
+    }
+
+    // The others are booked by scoring, redeeming, giving kudos and resolving challenges
+    pub fn is_manual(&self) -> bool {
+        matches!(self, TransactionKind::Revoke | TransactionKind::Bonus | TransactionKind::Adjustment)
+    }
+}
+
+// An entry of the append-only points ledger, it is never changed once it has been booked
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct PointTransaction {
+    pub id: u64,
+    pub user_id: u32,
+    pub kind: TransactionKind,
+    // Negative if points are taken away
+    pub amount: i64,
+    // What the points were booked for, e.g. the task or the reward
+    pub reference: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    // The season that was running when earned points were booked
+    #[serde(default)]
+    pub season_id: Option<u32>,
+}
+
+impl PointTransaction {
+    pub fn new(user_id: u32, kind: TransactionKind, amount: i64, reference: Option<String>, created_by: Option<String>) -> PointTransaction {
+        PointTransaction { id: 0, user_id, kind, amount, reference, created_by, created_at: Utc::now(), season_id: None }
+    }
+
+    pub fn for_score(user_id: u32, score: &Score) -> PointTransaction {
+        PointTransaction { created_at: score.scored_at, ..PointTransaction::new(user_id, TransactionKind::Score, score.points as i64, Some(score.task.name.clone()), None) }
+    }
+
+    // Checks a transaction that is booked by hand
+    pub fn check_manual(&self) -> Result<(), String> {
+        if !self.kind.is_manual() {
+            return Err(format!("Transactions of kind '{}' cannot be booked by hand", self.kind.as_str()));
+        }
+        if self.amount == 0 {
+            return Err("The amount must not be 0".to_owned());
+        }
+        if self.kind == TransactionKind::Revoke && self.amount > 0 {
+            return Err("A revoke has to take points away".to_owned());
+        }
+        if self.kind == TransactionKind::Bonus && self.amount < 0 {
+            return Err("A bonus has to add points".to_owned());
+        }
+        Ok(())
+    }
+}
+
+// Points booked by hand, the user and the booking admin are taken from the request
+#[derive(serde::Deserialize, JsonSchema)]
+pub struct PointBooking {
+    pub kind: TransactionKind,
+    pub amount: i64,
+    pub reference: Option<String>,
+}
+
+fn sum_of<'a>(transactions: impl Iterator<Item = &'a PointTransaction>) -> i64 {
+    transactions.map(|transaction| transaction.amount).sum()
+}
+
+// Lifetime points only ever count what has been earned, they never drop below 0
+pub fn lifetime_points(user_id: u32, ledger: &[PointTransaction]) -> u64 {
+    let earned = sum_of(ledger.iter().filter(|transaction| transaction.user_id == user_id && transaction.kind.is_earned()));
+    earned.max(0) as u64
+}
+
+// Lifetime points are what has been earned, the balance is what is left of them to spend
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct Balance {
+    pub user_id: u32,
+    pub lifetime_points: u64,
+    pub spent_points: u64,
+    pub balance: i64,
+}
+
+impl Balance {
+    pub fn create(user_id: u32, ledger: &[PointTransaction]) -> Balance {
+        let transactions: Vec<&PointTransaction> = ledger.iter().filter(|transaction| transaction.user_id == user_id).collect();
+        let spent = -sum_of(transactions.iter().copied().filter(|transaction| !transaction.kind.is_earned()));
+
+        Balance { user_id, lifetime_points: lifetime_points(user_id, ledger), spent_points: spent.max(0) as u64, balance: sum_of(transactions.into_iter()) }
+    }
+}
+
+// Points earned within a period, e.g. a season
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct PeriodPoints {
+    pub user_id: u32,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub points: i64,
+}
+
+impl PeriodPoints {
+    pub fn create(user_id: u32, ledger: &[PointTransaction], filter: &ScoreFilter) -> PeriodPoints {
+        let points = sum_of(ledger.iter().filter(|transaction| transaction.user_id == user_id && transaction.kind.is_earned()
+            && filter.from.map_or(true, |from| transaction.created_at >= from)
+            && filter.to.map_or(true, |to| transaction.created_at <= to)));
+
+        PeriodPoints { user_id, from: filter.from, to: filter.to, points }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::*;
+
+    fn transaction(user_id: u32, kind: TransactionKind, amount: i64, day: u32) -> PointTransaction {
+        PointTransaction { created_at: Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2022, 3, day).unwrap().and_hms_opt(12, 0, 0).unwrap()), ..PointTransaction::new(user_id, kind, amount, None, None) }
+    }
+
+    fn ledger() -> Vec<PointTransaction> {
+        vec![
+            transaction(1, TransactionKind::Score, 75, 1),
+            transaction(1, TransactionKind::Score, 75, 2),
+            transaction(2, TransactionKind::Score, 10, 2),
+            transaction(1, TransactionKind::Redeem, -100, 3),
+            transaction(1, TransactionKind::Revoke, -75, 4),
+            transaction(1, TransactionKind::Bonus, 20, 5),
+            transaction(1, TransactionKind::Refund, 100, 6),
+            transaction(1, TransactionKind::Redeem, -50, 7),
+        ]
+    }
+
+    #[test]
+    fn test_balance() {
+        let balance = Balance::create(1, &ledger());
+        assert_eq!(95, balance.lifetime_points);
+        assert_eq!(50, balance.spent_points);
+        assert_eq!(45, balance.balance);
+        assert_eq!(10, Balance::create(2, &ledger()).balance);
+    }
+
+    #[test]
+    fn test_lifetime_points_do_not_drop_below_zero() {
+        let ledger = vec![transaction(1, TransactionKind::Score, 10, 1), transaction(1, TransactionKind::Adjustment, -30, 2)];
+        assert_eq!(0, lifetime_points(1, &ledger));
+        assert_eq!(-20, Balance::create(1, &ledger).balance);
+    }
+
+    #[test]
+    fn test_period_points() {
+        let filter = ScoreFilter::parse(Some("2022-03-02"), Some("2022-03-04"), None).unwrap();
+        assert_eq!(0, PeriodPoints::create(1, &ledger(), &filter).points);
+        let filter = ScoreFilter::parse(Some("2022-03-05"), None, None).unwrap();
+        assert_eq!(20, PeriodPoints::create(1, &ledger(), &filter).points);
+    }
+
+    #[test]
+    fn test_check_manual() {
+        assert!(PointTransaction::new(1, TransactionKind::Bonus, 20, None, None).check_manual().is_ok());
+        assert!(PointTransaction::new(1, TransactionKind::Adjustment, -20, None, None).check_manual().is_ok());
+        assert!(PointTransaction::new(1, TransactionKind::Score, 20, None, None).check_manual().is_err());
+        assert!(PointTransaction::new(1, TransactionKind::Revoke, 20, None, None).check_manual().is_err());
+        assert!(PointTransaction::new(1, TransactionKind::Bonus, 0, None, None).check_manual().is_err());
+    }
+}
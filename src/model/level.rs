@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::ledger::PointTransaction;
+
+// Levels by the lifetime points they start at, the first one starts at 0
+#[derive(Clone)]
+pub struct LevelCurve {
+    levels: Vec<(u64, String)>,
+}
+
+impl LevelCurve {
+    // Given as comma separated 'points:title' pairs, e.g. '0:Newcomer, 100:Helper'
+    pub fn parse(curve: &str) -> Result<LevelCurve, String> {
+        let mut levels: Vec<(u64, String)> = vec![];
+        for level in curve.split(',').map(str::trim).filter(|level| !level.is_empty()) {
+            let (points, title) = level.split_once(':').ok_or(format!("'{}' is not a level (expected points:title)", level))?;
+            let points = points.trim().parse::<u64>().or(Err(format!("'{}' are no valid points", points.trim())))?;
+            let title = title.trim();
+            if title.is_empty() {
+                return Err(format!("The level starting at {} points needs a title", points));
+            }
+            if levels.last().map_or(points != 0, |(previous, _)| points <= *previous) {
+                return Err("Levels have to start at 0 points and require more points with each level".to_owned());
+            }
+            levels.push((points, title.to_owned()));
+        }
+
+        if levels.is_empty() {
+            return Err("The level curve needs at least one level".to_owned());
+        }
+        Ok(LevelCurve { levels })
+    }
+
+    // Levels are numbered from 1
+    pub fn level_of(&self, points: u64) -> u32 {
+        self.levels.iter().filter(|(min_points, _)| points >= *min_points).count() as u32
+    }
+
+    pub fn title_of(&self, level: u32) -> String {
+        self.levels[level as usize - 1].1.clone()
+    }
+
+    fn min_points_of(&self, level: u32) -> Option<u64> {
+        self.levels.get(level as usize - 1).map(|(min_points, _)| *min_points)
+    }
+}
+
+impl Default for LevelCurve {
+    fn default() -> Self {
+        LevelCurve { levels: vec![(0, "Newcomer".to_owned())] }
+    }
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct LevelReached {
+    pub level: u32,
+    pub title: String,
+    pub reached_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct LevelInfo {
+    pub level: u32,
+    pub title: String,
+    // None at the highest level
+    pub next_level_points: Option<u64>,
+    // Percent of the way from the current level to the next one
+    pub progress: u8,
+    // Every level-up in the order they happened, replayed from the ledger
+    pub history: Vec<LevelReached>,
+}
+
+impl LevelInfo {
+    pub fn create(user_id: u32, points: u64, ledger: &[PointTransaction], curve: &LevelCurve) -> LevelInfo {
+        let level = curve.level_of(points);
+        let min_points = curve.min_points_of(level).unwrap_or(0);
+        let next_level_points = curve.min_points_of(level + 1);
+        let progress = next_level_points.map_or(100, |next| ((points - min_points) * 100 / (next - min_points)) as u8);
+
+        let mut history = vec![];
+        let mut earned: i64 = 0;
+        let mut reached = 1;
+        for transaction in ledger.iter().filter(|transaction| transaction.user_id == user_id && transaction.kind.is_earned()) {
+            earned += transaction.amount;
+            while curve.min_points_of(reached + 1).map_or(false, |next| earned >= next as i64) {
+                reached += 1;
+                history.push(LevelReached { level: reached, title: curve.title_of(reached), reached_at: transaction.created_at });
+            }
+        }
+
+        LevelInfo { level, title: curve.title_of(level), next_level_points, progress, history }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::ledger::TransactionKind;
+
+    use super::*;
+
+    fn curve() -> LevelCurve {
+        LevelCurve::parse("0:Newcomer, 100:Helper, 250:Regular").unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(3, curve().levels.len());
+        assert!(LevelCurve::parse("").is_err());
+        assert!(LevelCurve::parse("10:Newcomer").is_err());
+        assert!(LevelCurve::parse("0:Newcomer, 100:Helper, 100:Regular").is_err());
+        assert!(LevelCurve::parse("0:Newcomer, 100").is_err());
+        assert!(LevelCurve::parse("0:Newcomer, many:Helper").is_err());
+    }
+
+    #[test]
+    fn test_level_info() {
+        let ledger = vec![
+            PointTransaction::new(1, TransactionKind::Score, 150, None, None),
+            PointTransaction::new(1, TransactionKind::Redeem, -150, None, None),
+            PointTransaction::new(2, TransactionKind::Score, 500, None, None),
+            PointTransaction::new(1, TransactionKind::Score, 25, None, None),
+        ];
+
+        let info = LevelInfo::create(1, 175, &ledger, &curve());
+        assert_eq!(2, info.level);
+        assert_eq!("Helper", info.title);
+        assert_eq!(Some(250), info.next_level_points);
+        assert_eq!(50, info.progress);
+        assert_eq!(1, info.history.len());
+
+        // Reaching several levels at once records each of them
+        let info = LevelInfo::create(2, 500, &ledger, &curve());
+        assert_eq!(None, info.next_level_points);
+        assert_eq!(100, info.progress);
+        assert_eq!(vec![2, 3], info.history.iter().map(|reached| reached.level).collect::<Vec<u32>>());
+    }
+}
@@ -5,4 +5,22 @@ pub use session::Session;
 
 pub mod session;
 pub mod user;
-pub mod task;
\ No newline at end of file
+pub mod task;
+pub mod history;
+pub mod verification;
+pub mod level;
+pub mod streak;
+pub mod report;
+pub mod reward;
+pub mod ledger;
+pub mod kudos;
+pub mod challenge;
+pub mod schedule;
+pub mod season;
+pub mod trophy;
+pub mod event;
+pub mod webhook;
+pub mod health;
+pub mod setting;
+pub mod snapshot;
+pub mod bulk_import;
\ No newline at end of file
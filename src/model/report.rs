@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashSet};
+
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Score, history::ScoreFilter, snapshot::TeamRecord};
+
+#[derive(FromFormField, serde::Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGrouping {
+    User,
+    Team,
+    Task,
+}
+
+#[derive(FromFormField, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+// Scores of one user for one task, summed up by the repository
+#[derive(Clone)]
+pub struct ScoreAggregate {
+    pub user_id: u32,
+    pub display_name: String,
+    pub task_id: u32,
+    pub task_name: String,
+    pub count: u32,
+    pub points: u32,
+}
+
+impl ScoreAggregate {
+    // For repositories holding the scores in memory anyway
+    pub fn of_user(user_id: u32, display_name: &str, scores: &[Score], filter: &ScoreFilter) -> Vec<ScoreAggregate> {
+        let mut by_task: BTreeMap<u32, ScoreAggregate> = BTreeMap::new();
+        for score in scores.iter().filter(|score| filter.matches(score)) {
+            let aggregate = by_task.entry(score.task.id).or_insert(ScoreAggregate {
+                user_id, display_name: display_name.to_owned(), task_id: score.task.id, task_name: score.task.name.clone(), count: 0, points: 0 });
+            aggregate.count += 1;
+            aggregate.points += score.points as u32;
+        }
+        by_task.into_values().collect()
+    }
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct ScoreReportRow {
+    pub id: u32,
+    pub name: String,
+    pub count: u32,
+    pub points: u32,
+    // Points per score
+    pub average: f64,
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct ScoreReport {
+    pub group_by: ReportGrouping,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub rows: Vec<ScoreReportRow>,
+    // Users in several teams are counted once per team in the rows, but only once here
+    pub total_count: u32,
+    pub total_points: u32,
+    pub average: f64,
+}
+
+fn average(points: u32, count: u32) -> f64 {
+    if count == 0 { 0.0 } else { points as f64 / count as f64 }
+}
+
+// Quotes a value only if a spreadsheet would otherwise misread it
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+impl ScoreReport {
+    // Only scores of the given users are part of the report, all users if there is no restriction
+    pub fn create(aggregates: Vec<ScoreAggregate>, teams: &[TeamRecord], visible_user_ids: Option<&HashSet<u32>>, filter: &ScoreFilter, group_by: ReportGrouping) -> ScoreReport {
+        let aggregates: Vec<ScoreAggregate> = aggregates.into_iter()
+            .filter(|aggregate| visible_user_ids.map_or(true, |user_ids| user_ids.contains(&aggregate.user_id)))
+            .collect();
+
+        let mut rows: BTreeMap<u32, ScoreReportRow> = BTreeMap::new();
+        let mut add = |id: u32, name: &str, aggregate: &ScoreAggregate| {
+            let row = rows.entry(id).or_insert(ScoreReportRow { id, name: name.to_owned(), count: 0, points: 0, average: 0.0 });
+            row.count += aggregate.count;
+            row.points += aggregate.points;
+        };
+        for aggregate in aggregates.iter() {
+            match group_by {
+                ReportGrouping::User => add(aggregate.user_id, &aggregate.display_name, aggregate),
+                ReportGrouping::Task => add(aggregate.task_id, &aggregate.task_name, aggregate),
+                ReportGrouping::Team => teams.iter()
+                    .filter(|team| team.member_ids.contains(&aggregate.user_id))
+                    .for_each(|team| add(team.id, &team.name, aggregate)),
+            }
+        }
+
+        let mut rows: Vec<ScoreReportRow> = rows.into_values().collect();
+        rows.iter_mut().for_each(|row| row.average = average(row.points, row.count));
+
+        let total_count = aggregates.iter().map(|aggregate| aggregate.count).sum();
+        let total_points = aggregates.iter().map(|aggregate| aggregate.points).sum();
+        let format_date = |date: Option<chrono::DateTime<chrono::Utc>>| date.map(|date| date.format("%Y-%m-%d").to_string());
+
+        ScoreReport { group_by, from: format_date(filter.from), to: format_date(filter.to), rows, total_count, total_points, average: average(total_points, total_count) }
+    }
+
+    // One line per row, produced lazily so the response can be streamed
+    pub fn into_csv_lines(self) -> impl Iterator<Item = String> + Send + 'static {
+        let header = "id,name,count,points,average\n".to_owned();
+        let total = format!(",Total,{},{},{:.2}\n", self.total_count, self.total_points, self.average);
+
+        std::iter::once(header)
+            .chain(self.rows.into_iter().map(|row| format!("{},{},{},{},{:.2}\n", row.id, csv_field(&row.name), row.count, row.points, row.average)))
+            .chain(std::iter::once(total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate(user_id: u32, task_id: u32, count: u32, points: u32) -> ScoreAggregate {
+        ScoreAggregate { user_id, display_name: format!("User {}", user_id), task_id, task_name: format!("Task {}", task_id), count, points }
+    }
+
+    fn aggregates() -> Vec<ScoreAggregate> {
+        vec![aggregate(1, 1, 2, 20), aggregate(1, 4, 1, 75), aggregate(2, 4, 3, 225), aggregate(3, 1, 1, 10)]
+    }
+
+    fn teams() -> Vec<TeamRecord> {
+        vec![
+            TeamRecord { id: 1, name: "Babes".to_owned(), manager_id: 1, member_ids: vec![1] },
+            TeamRecord { id: 2, name: "Church, Inc.".to_owned(), manager_id: 2, member_ids: vec![1, 2] },
+        ]
+    }
+
+    #[test]
+    fn test_report_by_user() {
+        let report = ScoreReport::create(aggregates(), &teams(), None, &ScoreFilter::default(), ReportGrouping::User);
+        assert_eq!(3, report.rows.len());
+        assert_eq!(95, report.rows[0].points);
+        assert_eq!(3, report.rows[0].count);
+        assert_eq!(75.0, report.rows[1].average);
+        assert_eq!(330, report.total_points);
+        assert_eq!(7, report.total_count);
+    }
+
+    #[test]
+    fn test_report_by_team() {
+        let report = ScoreReport::create(aggregates(), &teams(), None, &ScoreFilter::default(), ReportGrouping::Team);
+        assert_eq!(2, report.rows.len());
+        assert_eq!(95, report.rows[0].points);
+        assert_eq!(320, report.rows[1].points);
+        // User 3 is in no team, but still part of the totals
+        assert_eq!(330, report.total_points);
+    }
+
+    #[test]
+    fn test_report_restricted_to_visible_users() {
+        let visible: HashSet<u32> = [2].into();
+        let report = ScoreReport::create(aggregates(), &teams(), Some(&visible), &ScoreFilter::default(), ReportGrouping::Task);
+        assert_eq!(1, report.rows.len());
+        assert_eq!(4, report.rows[0].id);
+        assert_eq!(225, report.total_points);
+    }
+
+    #[test]
+    fn test_csv_lines() {
+        let report = ScoreReport::create(aggregates(), &teams(), None, &ScoreFilter::default(), ReportGrouping::Team);
+        let lines: Vec<String> = report.into_csv_lines().collect();
+        assert_eq!("id,name,count,points,average\n", lines[0]);
+        assert_eq!("2,\"Church, Inc.\",6,320,53.33\n", lines[2]);
+        assert_eq!(",Total,7,330,47.14\n", lines[3]);
+    }
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Reward {
+    pub id: u32,
+    pub name: String,
+    pub cost: u16,
+    // Redemptions left, there is no limit without a stock
+    pub stock: Option<u32>,
+    pub enabled: bool,
+}
+
+impl Reward {
+    pub fn check_redeemable(&self, balance: i64) -> Result<(), String> {
+        if !self.enabled {
+            return Err("Reward is not enabled".to_owned());
+        }
+        if self.stock == Some(0) {
+            return Err("Reward is out of stock".to_owned());
+        }
+        if balance < self.cost as i64 {
+            return Err(format!("Reward costs {} points, but only {} are left", self.cost, balance));
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RedemptionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl RedemptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RedemptionStatus::Pending => "pending",
+            RedemptionStatus::Approved => "approved",
+            RedemptionStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<RedemptionStatus> {
+        match status {
+            "pending" => Some(RedemptionStatus::Pending),
+            "approved" => Some(RedemptionStatus::Approved),
+            "rejected" => Some(RedemptionStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Redemption {
+    pub id: u32,
+    pub reward_id: u32,
+    pub reward_name: String,
+    pub user_id: u32,
+    // The cost at the time of the redemption, later price changes do not matter
+    pub cost: u16,
+    pub status: RedemptionStatus,
+    pub requested_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl Redemption {
+    pub fn new(id: u32, reward: &Reward, user_id: u32) -> Redemption {
+        Redemption { id, reward_id: reward.id, reward_name: reward.name.clone(), user_id, cost: reward.cost,
+            status: RedemptionStatus::Pending, requested_at: Utc::now(), decided_by: None, decided_at: None }
+    }
+
+    // Pending redemptions are paid for right away, rejected ones are refunded
+    pub fn is_charged(&self) -> bool {
+        self.status != RedemptionStatus::Rejected
+    }
+
+    pub fn decide(&mut self, approved: bool, decided_by: String) -> Result<(), String> {
+        if self.status != RedemptionStatus::Pending {
+            return Err(format!("Redemption {} has already been {}", self.id, self.status.as_str()));
+        }
+        self.status = if approved { RedemptionStatus::Approved } else { RedemptionStatus::Rejected };
+        self.decided_by = Some(decided_by);
+        self.decided_at = Some(Utc::now());
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Task, User, ledger::{PointTransaction, TransactionKind}, snapshot::TeamRecord};
+
+// Enough to find the next 29th of February
+const MAX_DAYS_AHEAD: u32 = 4 * 366 + 1;
+// Keeps rules like '* * * * *' from running away within a range
+const MAX_OCCURRENCES: usize = 10_000;
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// A cron-like rule (minute hour day-of-month month day-of-week, in UTC) or one of the shorthands
+// 'daily' and 'weekly mon,thu', which are due at 23:59
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    rule: String,
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days_of_month: Option<Vec<u32>>,
+    months: Vec<u32>,
+    days_of_week: Option<Vec<u32>>,
+}
+
+impl Recurrence {
+    pub fn parse(rule: &str) -> Result<Recurrence, String> {
+        let rule = rule.trim().to_lowercase();
+        let cron = match rule.split_once(' ').map_or((rule.as_str(), ""), |(kind, days)| (kind, days.trim())) {
+            ("daily", "") => "59 23 * * *".to_owned(),
+            ("weekly", days) if !days.is_empty() => {
+                let days = days.split(',')
+                    .map(|day| WEEKDAYS.iter().position(|weekday| *weekday == day.trim()).map(|index| index.to_string()).ok_or(format!("'{}' is no day of the week", day.trim())))
+                    .collect::<Result<Vec<String>, String>>()?;
+                format!("59 23 * * {}", days.join(","))
+            },
+            ("weekly", _) => return Err("Weekly rules need the days, e.g. 'weekly mon,thu'".to_owned()),
+            _ => rule.clone(),
+        };
+
+        let fields: Vec<&str> = cron.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("'{}' is neither 'daily', 'weekly <days>' nor a rule of five cron fields", rule));
+        }
+        // Sunday is 0 or 7
+        let days_of_week = parse_field(fields[4], 0, 7, "day of the week")?.map(|days| {
+            let mut days: Vec<u32> = days.into_iter().map(|day| day % 7).collect();
+            days.sort_unstable();
+            days.dedup();
+            days
+        });
+        Ok(Recurrence {
+            rule,
+            minutes: parse_field(fields[0], 0, 59, "minute")?.unwrap_or_else(|| (0..60).collect()),
+            hours: parse_field(fields[1], 0, 23, "hour")?.unwrap_or_else(|| (0..24).collect()),
+            days_of_month: parse_field(fields[2], 1, 31, "day of the month")?,
+            months: parse_field(fields[3], 1, 12, "month")?.unwrap_or_else(|| (1..13).collect()),
+            days_of_week,
+        })
+    }
+
+    // Like cron, a day matches either restriction if both the day of the month and of the week are restricted
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month.as_ref().map(|days| days.contains(&day.day()));
+        let day_of_week = self.days_of_week.as_ref().map(|days| days.contains(&day.weekday().num_days_from_sunday()));
+        self.months.contains(&day.month()) && match (day_of_month, day_of_week) {
+            (Some(day_of_month), Some(day_of_week)) => day_of_month || day_of_week,
+            (day_of_month, day_of_week) => day_of_month.or(day_of_week).unwrap_or(true),
+        }
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut day = after.naive_utc().date();
+        for _ in 0..MAX_DAYS_AHEAD {
+            if self.matches_day(day) {
+                let times = self.hours.iter().flat_map(|hour| self.minutes.iter().map(move |minute| (*hour, *minute)));
+                for (hour, minute) in times {
+                    let at = Utc.from_utc_datetime(&day.and_hms_opt(hour, minute, 0)?);
+                    if at > after {
+                        return Some(at);
+                    }
+                }
+            }
+            day = day.succ_opt()?;
+        }
+        None
+    }
+
+    pub fn previous_before(&self, before: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut day = before.naive_utc().date();
+        for _ in 0..MAX_DAYS_AHEAD {
+            if self.matches_day(day) {
+                let times = self.hours.iter().rev().flat_map(|hour| self.minutes.iter().rev().map(move |minute| (*hour, *minute)));
+                for (hour, minute) in times {
+                    let at = Utc.from_utc_datetime(&day.and_hms_opt(hour, minute, 0)?);
+                    if at < before {
+                        return Some(at);
+                    }
+                }
+            }
+            day = day.pred_opt()?;
+        }
+        None
+    }
+}
+
+// None for '*', the sorted values otherwise
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<Option<Vec<u32>>, String> {
+    if field == "*" {
+        return Ok(None);
+    }
+    let invalid = || format!("'{}' is no valid {} ({}-{})", field, name, min, max);
+    let number = |value: &str| value.parse::<u32>().ok().filter(|value| (min..=max).contains(value)).ok_or_else(invalid);
+
+    let mut values = vec![];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (number(first)?, number(last)?),
+            None if part.contains('/') => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        values.extend((first..=last).step_by(step as usize));
+    }
+    values.sort_unstable();
+    values.dedup();
+    Ok(Some(values))
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        Recurrence::parse(&rule)
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.rule
+    }
+}
+
+// Who takes turns, given as {"users": [1, 2]} or {"team": 2}
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Rota {
+    Users(Vec<u32>),
+    // The current members of the team, in the order they joined
+    Team(u32),
+}
+
+impl Rota {
+    pub fn member_ids(&self, teams: &[TeamRecord]) -> Vec<u32> {
+        match self {
+            Rota::Users(user_ids) => user_ids.clone(),
+            Rota::Team(team_id) => teams.iter().find(|team| team.id == *team_id).map(|team| team.member_ids.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+// When a task is due and who is next, one per task
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Schedule {
+    pub task_id: u32,
+    #[schemars(with = "String")]
+    pub recurrence: Recurrence,
+    pub rota: Rota,
+    // Occurrences and turns are counted from here
+    pub starts_at: DateTime<Utc>,
+    // Booked for an assignment done on time
+    pub bonus: u16,
+    // Taken away for a missed assignment
+    pub malus: u16,
+    // Bonus and malus have been booked for everything due until then, the start or the last settled occurrence
+    pub settled_until: DateTime<Utc>,
+    // The number of occurrences settled so far, which makes it the turn of the next one
+    #[serde(default)]
+    pub settled_turns: u32,
+}
+
+#[derive(serde::Deserialize, JsonSchema)]
+pub struct ScheduleRequest {
+    pub recurrence: String,
+    pub rota: Rota,
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bonus: u16,
+    #[serde(default)]
+    pub malus: u16,
+}
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AssignmentStatus {
+    Open,
+    Done,
+    Missed,
+}
+
+// An occurrence of a scheduled task, done if the assignee scored the task after the previous one was due
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct Assignment {
+    pub task_id: u32,
+    pub task_name: String,
+    pub user_id: u32,
+    pub window_start: DateTime<Utc>,
+    pub due: DateTime<Utc>,
+    pub status: AssignmentStatus,
+}
+
+impl Schedule {
+    pub fn new(task_id: u32, request: ScheduleRequest) -> Result<Schedule, String> {
+        let recurrence = Recurrence::parse(&request.recurrence)?;
+        if matches!(&request.rota, Rota::Users(user_ids) if user_ids.is_empty()) {
+            return Err("The rota needs at least one user".to_owned());
+        }
+        let starts_at = request.starts_at.unwrap_or_else(Utc::now);
+        Ok(Schedule { task_id, recurrence, rota: request.rota, starts_at, bonus: request.bonus, malus: request.malus, settled_until: starts_at, settled_turns: 0 })
+    }
+
+    // When the next occurrence to settle is due
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.recurrence.next_after(self.settled_until)
+    }
+
+    // The turn and window of every occurrence due within the range. Counted back and forth from the last settled
+    // occurrence, so the effort does not grow with the age of the schedule
+    fn occurrences(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<(u32, DateTime<Utc>, DateTime<Utc>)> {
+        let mut occurrences = vec![];
+        let (mut turn, mut due) = (self.settled_turns, self.settled_until);
+        while turn > 0 && due >= from && occurrences.len() < MAX_OCCURRENCES {
+            let window_start = match turn {
+                1 => self.starts_at,
+                _ => match self.recurrence.previous_before(due) {
+                    Some(previous) => previous.max(self.starts_at),
+                    None => break,
+                },
+            };
+            occurrences.push((turn - 1, window_start, due));
+            turn -= 1;
+            due = window_start;
+        }
+        occurrences.reverse();
+
+        let (mut turn, mut window_start) = (self.settled_turns, self.settled_until);
+        while let Some(due) = self.recurrence.next_after(window_start).filter(|due| *due <= until) {
+            if occurrences.len() >= MAX_OCCURRENCES {
+                break;
+            }
+            occurrences.push((turn, window_start, due));
+            turn += 1;
+            window_start = due;
+        }
+        occurrences
+    }
+
+    // The assignments due within the given range, for everybody in the rota
+    pub fn assignments(&self, task: &Task, users: &[User], teams: &[TeamRecord], from: DateTime<Utc>, to: DateTime<Utc>, now: DateTime<Utc>) -> Vec<Assignment> {
+        let member_ids = self.rota.member_ids(teams);
+        if member_ids.is_empty() {
+            return vec![];
+        }
+
+        self.occurrences(from, to).into_iter()
+            .filter(|(_, _, due)| *due >= from)
+            .map(|(turn, window_start, due)| {
+                let user_id = member_ids[turn as usize % member_ids.len()];
+                let done = users.iter().find(|user| user.id == user_id).map_or(false, |user| user.scores.iter()
+                    .any(|score| score.task.id == task.id && score.scored_at > window_start && score.scored_at <= due));
+                let status = if done { AssignmentStatus::Done } else if due <= now { AssignmentStatus::Missed } else { AssignmentStatus::Open };
+                Assignment { task_id: task.id, task_name: task.name.clone(), user_id, window_start, due, status }
+            })
+            .collect()
+    }
+
+    // Books bonus and malus for what has been due since the last time, only once per assignment.
+    // Nothing is booked while the task is disabled, as nobody is able to score it, but the turns go on
+    pub fn settle(&mut self, task: &Task, users: &[User], teams: &[TeamRecord], now: DateTime<Utc>) -> Vec<PointTransaction> {
+        if self.next_due().map_or(true, |due| now < due) {
+            return vec![];
+        }
+        let previously = self.settled_until;
+        let assignments = if task.enabled { self.assignments(task, users, teams, previously, now, now) } else { vec![] };
+        let settled = assignments.into_iter()
+            .filter(|assignment| assignment.due > previously)
+            .filter_map(|assignment| match assignment.status {
+                AssignmentStatus::Done if self.bonus > 0 =>
+                    Some(PointTransaction::new(assignment.user_id, TransactionKind::Bonus, self.bonus as i64, Some(format!("On time: {}", task.name)), None)),
+                AssignmentStatus::Missed if self.malus > 0 =>
+                    Some(PointTransaction::new(assignment.user_id, TransactionKind::Revoke, -(self.malus as i64), Some(format!("Missed: {}", task.name)), None)),
+                _ => None
+            })
+            .collect();
+        if let Some((turn, _, due)) = self.occurrences(previously, now).into_iter().filter(|(_, _, due)| *due > previously).last() {
+            self.settled_turns = turn + 1;
+            self.settled_until = due;
+        }
+        settled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::model::Score;
+
+    use super::*;
+
+    // March 7th 2022 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2022, 3, day).unwrap().and_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Some(at(7, 23, 59)), Recurrence::parse("daily").unwrap().next_after(at(7, 12, 0)));
+        assert_eq!(Some(at(10, 23, 59)), Recurrence::parse("weekly mon, thu").unwrap().next_after(at(8, 0, 0)));
+        assert_eq!(Some(at(9, 8, 30)), Recurrence::parse("30 8 * * 1-5").unwrap().next_after(at(8, 8, 30)));
+        assert_eq!(Some(at(15, 18, 0)), Recurrence::parse("0 18 */7 * *").unwrap().next_after(at(8, 18, 0)));
+        assert_eq!(Some(at(7, 23, 59)), Recurrence::parse("weekly mon, thu").unwrap().previous_before(at(10, 23, 59)));
+        assert_eq!(Some(at(8, 8, 30)), Recurrence::parse("30 8 * * 1-5").unwrap().previous_before(at(8, 8, 31)));
+        assert!(Recurrence::parse("weekly").is_err());
+        assert!(Recurrence::parse("weekly someday").is_err());
+        assert!(Recurrence::parse("60 8 * * *").is_err());
+        assert!(Recurrence::parse("0 8 * *").is_err());
+    }
+
+    fn schedule(bonus: u16, malus: u16) -> Schedule {
+        let request = ScheduleRequest { recurrence: "daily".to_owned(), rota: Rota::Users(vec![1, 2]), starts_at: Some(at(7, 0, 0)), bonus, malus };
+        Schedule::new(4, request).unwrap()
+    }
+
+    fn task() -> Task {
+        Task { id: 4, name: "Kaffee kochen".to_owned(), points: 75, enabled: true, requires_verification: false }
+    }
+
+    fn user(id: u32, scored_at: &[DateTime<Utc>]) -> User {
+        let mut user = User::new(id, format!("user{}", id), format!("User {}", id), false);
+        user.scores = scored_at.iter().map(|scored_at| Score { task: task(), points: 75, scored_at: *scored_at }).collect();
+        user
+    }
+
+    #[test]
+    fn test_assignments_take_turns() {
+        let users = vec![user(1, &[at(7, 9, 0)]), user(2, &[at(7, 10, 0)])];
+        let assignments = schedule(0, 0).assignments(&task(), &users, &[], at(7, 0, 0), at(9, 23, 59), at(8, 12, 0));
+
+        assert_eq!(vec![1, 2, 1], assignments.iter().map(|assignment| assignment.user_id).collect::<Vec<u32>>());
+        assert_eq!(vec![AssignmentStatus::Done, AssignmentStatus::Open, AssignmentStatus::Open],
+            assignments.iter().map(|assignment| assignment.status).collect::<Vec<AssignmentStatus>>());
+    }
+
+    #[test]
+    fn test_settle() {
+        let users = vec![user(1, &[at(7, 9, 0)]), user(2, &[])];
+        let mut schedule = schedule(5, 10);
+
+        let settled = schedule.settle(&task(), &users, &[], at(9, 0, 0));
+        assert_eq!(vec![(1, 5), (2, -10)], settled.iter().map(|transaction| (transaction.user_id, transaction.amount)).collect::<Vec<(u32, i64)>>());
+        assert!(schedule.settle(&task(), &users, &[], at(9, 0, 0) + Duration::hours(1)).is_empty());
+        assert_eq!(1, schedule.settle(&task(), &users, &[], at(10, 0, 0)).len());
+    }
+
+    #[test]
+    fn test_turns_go_on_from_the_last_settlement() {
+        let users = vec![user(1, &[]), user(2, &[])];
+        let mut schedule = schedule(0, 0);
+        schedule.settle(&task(), &users, &[], at(9, 0, 0));
+        assert_eq!(2, schedule.settled_turns);
+        assert_eq!(at(8, 23, 59), schedule.settled_until);
+
+        let assignments = schedule.assignments(&task(), &users, &[], at(7, 0, 0), at(10, 23, 59), at(9, 12, 0));
+        assert_eq!(vec![1, 2, 1, 2], assignments.iter().map(|assignment| assignment.user_id).collect::<Vec<u32>>());
+        assert_eq!(at(7, 0, 0), assignments[0].window_start);
+        assert_eq!(at(8, 23, 59), assignments[2].window_start);
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{event::{ActivityEvent, LeaderboardEntry}, ledger::PointTransaction, trophy::Trophy};
+
+const TROPHY_PLACES: [&str; 3] = ["1st place", "2nd place", "3rd place"];
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Season {
+    pub id: u32,
+    pub name: String,
+    pub start: DateTime<Utc>,
+    // Open as long as the season is running
+    pub end: Option<DateTime<Utc>>,
+    // When the season ends by itself, none if only an admin ends it
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+    // The final standings, archived when the season ends
+    #[serde(default)]
+    pub standings: Vec<LeaderboardEntry>,
+}
+
+#[derive(serde::Deserialize, JsonSchema)]
+pub struct SeasonRequest {
+    pub name: String,
+    // Right away if not given
+    pub start: Option<DateTime<Utc>>,
+    // The season runs until an admin ends it if not given
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Season {
+    pub fn new(id: u32, name: String, start: DateTime<Utc>, ends_at: Option<DateTime<Utc>>) -> Season {
+        Season { id, name, start, end: None, ends_at, standings: vec![] }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.end.is_none()
+    }
+
+    // Earned points are only booked for the season between its start and its planned end
+    pub fn is_running(&self, now: DateTime<Utc>) -> bool {
+        self.is_active() && self.start <= now && self.ends_at.map_or(true, |ends_at| now < ends_at)
+    }
+
+    // Still open although its planned end has passed
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.is_active() && self.ends_at.map_or(false, |ends_at| ends_at <= now)
+    }
+
+    // A due season ends at its planned end, no matter how late it is archived
+    pub fn finish(&mut self, standings: Vec<LeaderboardEntry>, now: DateTime<Utc>) -> Result<(), String> {
+        if !self.is_active() {
+            return Err(format!("Season '{}' has already ended", self.name));
+        }
+        self.end = Some(self.ends_at.filter(|ends_at| *ends_at <= now).unwrap_or(now));
+        self.standings = standings;
+        Ok(())
+    }
+
+    // The best three get a trophy, as long as they earned anything at all
+    pub fn trophies(&self) -> Vec<Trophy> {
+        self.standings.iter().zip(TROPHY_PLACES.iter())
+            .filter(|(entry, _)| entry.points > 0)
+            .map(|(entry, place)| Trophy::new(entry.user_id, format!("{}: {}", self.name, place), Some(self.id)))
+            .collect()
+    }
+
+    pub fn trophy_awarded(&self, trophy: &Trophy) -> ActivityEvent {
+        let display_name = self.standings.iter().find(|entry| entry.user_id == trophy.user_id).map(|entry| entry.display_name.clone()).unwrap_or_default();
+        ActivityEvent::Trophy { user_id: trophy.user_id, display_name, trophy: trophy.name.clone() }
+    }
+}
+
+// Ranks the given users (id and display name) by the points they earned within the season
+pub fn standings(season_id: u32, users: &[(u32, String)], ledger: &[PointTransaction]) -> Vec<LeaderboardEntry> {
+    let mut points: Vec<(u32, String, u64)> = users.iter()
+        .map(|(user_id, display_name)| {
+            let earned: i64 = ledger.iter()
+                .filter(|transaction| transaction.user_id == *user_id && transaction.season_id == Some(season_id) && transaction.kind.is_earned())
+                .map(|transaction| transaction.amount)
+                .sum();
+            (*user_id, display_name.clone(), earned.max(0) as u64)
+        })
+        .collect();
+    points.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+    points.into_iter().enumerate()
+        .map(|(index, (user_id, display_name, points))| LeaderboardEntry { rank: index as u32 + 1, user_id, display_name, points })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::model::ledger::TransactionKind;
+
+    use super::*;
+
+    fn transaction(user_id: u32, kind: TransactionKind, amount: i64, season_id: Option<u32>) -> PointTransaction {
+        PointTransaction { season_id, ..PointTransaction::new(user_id, kind, amount, None, None) }
+    }
+
+    fn users() -> Vec<(u32, String)> {
+        vec![(1, "Flori".to_owned()), (2, "Michi".to_owned()), (3, "Franki".to_owned()), (4, "Topher".to_owned())]
+    }
+
+    #[test]
+    fn test_standings_only_count_the_season() {
+        let ledger = vec![
+            transaction(4, TransactionKind::Score, 375, None),
+            transaction(1, TransactionKind::Score, 10, Some(1)),
+            transaction(2, TransactionKind::Score, 52, Some(1)),
+            transaction(2, TransactionKind::Redeem, -50, Some(1)),
+            transaction(4, TransactionKind::Score, 75, Some(2)),
+        ];
+
+        let standings = standings(1, &users(), &ledger);
+        assert_eq!(vec![2, 1, 3, 4], standings.iter().map(|entry| entry.user_id).collect::<Vec<u32>>());
+        assert_eq!(52, standings[0].points);
+        assert_eq!(0, standings[3].points);
+    }
+
+    #[test]
+    fn test_finish_awards_trophies() {
+        let mut season = Season::new(1, "Q1".to_owned(), Utc::now(), None);
+        let ledger = vec![transaction(1, TransactionKind::Score, 10, Some(1)), transaction(2, TransactionKind::Score, 52, Some(1))];
+        season.finish(standings(1, &users(), &ledger), Utc::now()).unwrap();
+
+        let trophies = season.trophies();
+        assert_eq!(2, trophies.len());
+        assert_eq!("Q1: 1st place", trophies[0].name);
+        assert_eq!(2, trophies[0].user_id);
+        assert!(season.finish(vec![], Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_season_ends_at_its_planned_end() {
+        let start = Utc::now() - Duration::days(90);
+        let ends_at = Utc::now() - Duration::days(1);
+        let mut season = Season::new(1, "Q1".to_owned(), start, Some(ends_at));
+        assert!(season.is_running(ends_at - Duration::seconds(1)));
+        assert!(!season.is_running(ends_at));
+        assert!(!season.is_running(start - Duration::seconds(1)));
+        assert!(season.is_due(Utc::now()));
+
+        season.finish(vec![], Utc::now()).unwrap();
+        assert_eq!(Some(ends_at), season.end);
+        assert!(!season.is_due(Utc::now()));
+    }
+}
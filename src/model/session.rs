@@ -7,8 +7,10 @@ use rocket_okapi::{request::{OpenApiFromRequest, RequestHeaderInput}, gen::OpenA
 use schemars::{JsonSchema};
 use base64;
 
-use crate::repository::neo4j_repsitory::Neo4JRepository;
-use crate::repository::repository::Repository;
+use crate::repository::repository::DynRepository;
+use crate::config::settings::Settings;
+use crate::logging::request_tracing::RequestId;
+use tracing::Instrument;
 
 use super::User;
 use rand::Rng;
@@ -58,7 +60,7 @@ impl <'a> FromRequest<'a> for Session {
     type Error = String;
 
     async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
-        let repository = request.rocket().state::<Neo4JRepository>();
+        let repository = request.rocket().state::<DynRepository>();
         if repository.is_none() {
             return Outcome::Failure((Status::InternalServerError, "Missing status".to_owned()))
         }
@@ -72,12 +74,19 @@ impl <'a> FromRequest<'a> for Session {
         let cookie = cookie.unwrap();
 
         let sid = cookie.value().to_owned();
-        let session = repository.get_session(&sid).await;
+        let session = repository.get_session(&sid).instrument(RequestId::of(request).span()).await;
         if session.is_none() {
             return Outcome::Failure((Status::Unauthorized, "Session not available".to_owned()))
         }
 
         let mut session = session.unwrap();
+        let expired = request.rocket().state::<Settings>()
+            .map_or(false, |settings| Utc::now() - session.started > settings.session_lifetime());
+        if expired {
+            let _ = repository.logout(&sid).instrument(RequestId::of(request).span()).await;
+            return Outcome::Failure((Status::Unauthorized, "Session expired".to_owned()))
+        }
+
         session.refresh();
         Outcome::Success(session)
     }
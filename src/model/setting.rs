@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum SettingValue {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+}
+
+impl SettingValue {
+    pub fn is_same_type(&self, other: &SettingValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct Setting {
+    pub key: String,
+    pub description: String,
+    pub value: SettingValue,
+    pub default: SettingValue,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct SettingChange {
+    pub key: String,
+    pub old_value: SettingValue,
+    pub new_value: SettingValue,
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+}
@@ -0,0 +1,394 @@
+use std::collections::HashSet;
+
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Task, Score, User, user::Team, setting::SettingChange, reward::{Reward, Redemption, RedemptionStatus}, ledger::{PointTransaction, TransactionKind}, season::Season, trophy::Trophy, kudos::Kudos, verification::ScoreClaim, challenge::{Challenge, Participant}, schedule::{Schedule, Rota}, webhook::{Webhook, WebhookDelivery}};
+
+// Increased whenever the layout changes in a way older versions cannot read
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+// The first version holding the points ledger
+const LEDGER_VERSION: u32 = 2;
+
+// Snapshots taken before users could be deactivated only contain active users
+fn enabled_default() -> bool {
+    true
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct UserRecord {
+    pub id: u32,
+    pub username: String,
+    pub display_name: String,
+    pub is_admin: bool,
+    // Only informative, the points are derived from the ledger
+    pub points: u64,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    pub pwd_hash: Option<String>,
+    pub scores: Vec<Score>,
+}
+
+impl From<&User> for UserRecord {
+    fn from(user: &User) -> Self {
+        UserRecord {
+            id: user.id,
+            username: user.username.clone(),
+            display_name: user.display_name.clone(),
+            is_admin: user.is_admin,
+            points: user.points,
+            enabled: user.enabled,
+            pwd_hash: user.pwd_hash_components.clone(),
+            scores: user.scores.clone(),
+        }
+    }
+}
+
+impl From<UserRecord> for User {
+    fn from(record: UserRecord) -> Self {
+        User {
+            id: record.id,
+            username: record.username,
+            display_name: record.display_name,
+            is_admin: record.is_admin,
+            points: record.points,
+            enabled: record.enabled,
+            scores: record.scores,
+            pwd_hash_components: record.pwd_hash,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct TeamRecord {
+    pub id: u32,
+    pub name: String,
+    pub manager_id: u32,
+    pub member_ids: Vec<u32>,
+}
+
+impl From<&Team> for TeamRecord {
+    fn from(team: &Team) -> Self {
+        let mut member_ids: Vec<u32> = team.member_ids.iter().cloned().collect();
+        member_ids.sort();
+        TeamRecord { id: team.id, name: team.name.clone(), manager_id: team.manager_id, member_ids }
+    }
+}
+
+// Unlike the API, the snapshot keeps the secret, the payloads could not be signed after a restore otherwise
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct WebhookRecord {
+    pub id: u32,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub enabled: bool,
+    pub secret: String,
+}
+
+impl From<&Webhook> for WebhookRecord {
+    fn from(webhook: &Webhook) -> Self {
+        WebhookRecord { id: webhook.id, url: webhook.url.clone(), event_types: webhook.event_types.clone(), enabled: webhook.enabled, secret: webhook.secret.clone() }
+    }
+}
+
+impl From<WebhookRecord> for Webhook {
+    fn from(record: WebhookRecord) -> Self {
+        Webhook { id: record.id, url: record.url, event_types: record.event_types, enabled: record.enabled, secret: record.secret }
+    }
+}
+
+// Everything a repository holds except for sessions, which do not survive a restart anyway
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct RepositorySnapshot {
+    pub version: u32,
+    pub users: Vec<UserRecord>,
+    pub tasks: Vec<Task>,
+    pub teams: Vec<TeamRecord>,
+    pub setting_changes: Vec<SettingChange>,
+    // Missing in snapshots taken before the reward shop existed
+    #[serde(default)]
+    pub rewards: Vec<Reward>,
+    #[serde(default)]
+    pub redemptions: Vec<Redemption>,
+    // Missing before version 2, see ledger_or_derived
+    #[serde(default)]
+    pub ledger: Vec<PointTransaction>,
+    #[serde(default)]
+    pub seasons: Vec<Season>,
+    #[serde(default)]
+    pub trophies: Vec<Trophy>,
+    #[serde(default)]
+    pub kudos: Vec<Kudos>,
+    #[serde(default)]
+    pub score_claims: Vec<ScoreClaim>,
+    #[serde(default)]
+    pub challenges: Vec<Challenge>,
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookRecord>,
+    #[serde(default)]
+    pub dead_letters: Vec<WebhookDelivery>,
+}
+
+// Outcome of an import, a dry run only reports what an import would do
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct ImportReport {
+    pub version: u32,
+    pub dry_run: bool,
+    pub applied: bool,
+    pub users: usize,
+    pub tasks: usize,
+    pub teams: usize,
+    pub scores: usize,
+    // Problems within the snapshot, an import is refused as long as there are any
+    pub errors: Vec<String>,
+    // Existing data that is overwritten or removed by the import
+    pub conflicts: Vec<String>,
+}
+
+impl RepositorySnapshot {
+    // Checks that the snapshot is readable and consistent in itself
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        if self.version > SNAPSHOT_VERSION {
+            errors.push(format!("Snapshot version {} is newer than the supported version {}", self.version, SNAPSHOT_VERSION));
+        }
+
+        let mut user_ids = HashSet::new();
+        let mut usernames = HashSet::new();
+        for user in self.users.iter() {
+            if !user_ids.insert(user.id) {
+                errors.push(format!("User id {} is used more than once", user.id));
+            }
+            if !usernames.insert(user.username.as_str()) {
+                errors.push(format!("Username '{}' is used more than once", user.username));
+            }
+        }
+
+        let mut task_ids = HashSet::new();
+        for task in self.tasks.iter() {
+            if !task_ids.insert(task.id) {
+                errors.push(format!("Task id {} is used more than once", task.id));
+            }
+        }
+        for user in self.users.iter() {
+            for score in user.scores.iter().filter(|score| !task_ids.contains(&score.task.id)) {
+                errors.push(format!("Score of user '{}' refers to task {} which does not exist", user.username, score.task.id));
+            }
+        }
+
+        let mut team_names = HashSet::new();
+        for team in self.teams.iter() {
+            if !team_names.insert(team.name.as_str()) {
+                errors.push(format!("Team name '{}' is used more than once", team.name));
+            }
+            if !user_ids.contains(&team.manager_id) {
+                errors.push(format!("Manager {} of team '{}' does not exist", team.manager_id, team.name));
+            }
+            for member_id in team.member_ids.iter().filter(|member_id| !user_ids.contains(member_id)) {
+                errors.push(format!("Member {} of team '{}' does not exist", member_id, team.name));
+            }
+        }
+
+        let season_ids: HashSet<u32> = self.seasons.iter().map(|season| season.id).collect();
+        if season_ids.len() < self.seasons.len() {
+            errors.push("Season ids are used more than once".to_owned());
+        }
+        if self.seasons.iter().filter(|season| season.is_active()).count() > 1 {
+            errors.push("More than one season is running".to_owned());
+        }
+        for trophy in self.trophies.iter().filter(|trophy| !user_ids.contains(&trophy.user_id)) {
+            errors.push(format!("Trophy '{}' refers to user {} which does not exist", trophy.name, trophy.user_id));
+        }
+        for kudos in self.kudos.iter().filter(|kudos| !user_ids.contains(&kudos.from_user_id) || !user_ids.contains(&kudos.to_user_id)) {
+            errors.push(format!("Kudos {} refer to a user which does not exist", kudos.id));
+        }
+        for claim in self.score_claims.iter().filter(|claim| !user_ids.contains(&claim.user_id)) {
+            errors.push(format!("Claim {} refers to user {} which does not exist", claim.id, claim.user_id));
+        }
+        for claim in self.score_claims.iter().filter(|claim| !self.tasks.iter().any(|task| task.id == claim.task_id)) {
+            errors.push(format!("Claim {} refers to task {} which does not exist", claim.id, claim.task_id));
+        }
+        for challenge in self.challenges.iter() {
+            let exists = |participant: &Participant| match participant {
+                Participant::User(user_id) => user_ids.contains(user_id),
+                Participant::Team(team_id) => self.teams.iter().any(|team| team.id == *team_id),
+            };
+            if !exists(&challenge.challenger) || !exists(&challenge.opponent) {
+                errors.push(format!("Challenge '{}' refers to a participant which does not exist", challenge.name));
+            }
+        }
+        for schedule in self.schedules.iter() {
+            if !task_ids.contains(&schedule.task_id) {
+                errors.push(format!("Schedule refers to task {} which does not exist", schedule.task_id));
+            }
+            let rota_exists = match &schedule.rota {
+                Rota::Users(rota_user_ids) => rota_user_ids.iter().all(|user_id| user_ids.contains(user_id)),
+                Rota::Team(team_id) => self.teams.iter().any(|team| team.id == *team_id),
+            };
+            if !rota_exists {
+                errors.push(format!("Schedule of task {} refers to a rota member which does not exist", schedule.task_id));
+            }
+        }
+
+        let mut transaction_ids = HashSet::new();
+        for transaction in self.ledger.iter() {
+            if !transaction_ids.insert(transaction.id) {
+                errors.push(format!("Transaction id {} is used more than once", transaction.id));
+            }
+            if !user_ids.contains(&transaction.user_id) {
+                errors.push(format!("Transaction {} refers to user {} which does not exist", transaction.id, transaction.user_id));
+            }
+            if let Some(season_id) = transaction.season_id.filter(|season_id| !season_ids.contains(season_id)) {
+                errors.push(format!("Transaction {} refers to season {} which does not exist", transaction.id, season_id));
+            }
+        }
+
+        let reward_ids: HashSet<u32> = self.rewards.iter().map(|reward| reward.id).collect();
+        if reward_ids.len() < self.rewards.len() {
+            errors.push("Reward ids are used more than once".to_owned());
+        }
+        for redemption in self.redemptions.iter() {
+            if !reward_ids.contains(&redemption.reward_id) {
+                errors.push(format!("Redemption {} refers to reward {} which does not exist", redemption.id, redemption.reward_id));
+            }
+            if !user_ids.contains(&redemption.user_id) {
+                errors.push(format!("Redemption {} refers to user {} which does not exist", redemption.id, redemption.user_id));
+            }
+        }
+
+        errors
+    }
+
+    // Older snapshots have no ledger, so it is made up from the scores and redemptions, with an adjustment
+    // for points that cannot be explained by the scores
+    pub fn ledger_or_derived(&self) -> Vec<PointTransaction> {
+        if self.version >= LEDGER_VERSION {
+            return self.ledger.clone();
+        }
+
+        let mut ledger = vec![];
+        for user in self.users.iter() {
+            ledger.extend(user.scores.iter().map(|score| PointTransaction::for_score(user.id, score)));
+            let scored: i64 = user.scores.iter().map(|score| score.points as i64).sum();
+            if user.points as i64 != scored {
+                let reference = Some("Points without a score".to_owned());
+                ledger.push(PointTransaction::new(user.id, TransactionKind::Adjustment, user.points as i64 - scored, reference, None));
+            }
+        }
+        for redemption in self.redemptions.iter() {
+            let reference = Some(redemption.reward_name.clone());
+            ledger.push(PointTransaction { created_at: redemption.requested_at,
+                ..PointTransaction::new(redemption.user_id, TransactionKind::Redeem, -(redemption.cost as i64), reference.clone(), None) });
+            if redemption.status == RedemptionStatus::Rejected {
+                ledger.push(PointTransaction { created_at: redemption.decided_at.unwrap_or(redemption.requested_at),
+                    ..PointTransaction::new(redemption.user_id, TransactionKind::Refund, redemption.cost as i64, reference, redemption.decided_by.clone()) });
+            }
+        }
+
+        ledger.sort_by_key(|transaction| transaction.created_at);
+        ledger.iter_mut().enumerate().for_each(|(index, transaction)| transaction.id = index as u64 + 1);
+        ledger
+    }
+
+    // Lists what of the current data would be lost or changed when it is replaced by this snapshot
+    pub fn conflicts_with(&self, current: &RepositorySnapshot) -> Vec<String> {
+        let mut conflicts = vec![];
+        for user in current.users.iter() {
+            match self.users.iter().find(|imported| imported.id == user.id) {
+                None => conflicts.push(format!("User {} '{}' is removed", user.id, user.username)),
+                Some(imported) if imported.username != user.username =>
+                    conflicts.push(format!("User {} '{}' is replaced by '{}'", user.id, user.username, imported.username)),
+                Some(imported) if imported.scores.len() < user.scores.len() =>
+                    conflicts.push(format!("User '{}' loses {} scores", user.username, user.scores.len() - imported.scores.len())),
+                Some(_) => (),
+            }
+        }
+        for task in current.tasks.iter() {
+            match self.tasks.iter().find(|imported| imported.id == task.id) {
+                None => conflicts.push(format!("Task {} '{}' is removed", task.id, task.name)),
+                Some(imported) if imported.name != task.name =>
+                    conflicts.push(format!("Task {} '{}' is replaced by '{}'", task.id, task.name, imported.name)),
+                Some(_) => (),
+            }
+        }
+        for team in current.teams.iter().filter(|team| !self.teams.iter().any(|imported| imported.name == team.name)) {
+            conflicts.push(format!("Team '{}' is removed", team.name));
+        }
+
+        conflicts
+    }
+
+    pub fn report(&self, current: &RepositorySnapshot, dry_run: bool) -> ImportReport {
+        ImportReport {
+            version: self.version,
+            dry_run,
+            applied: false,
+            users: self.users.len(),
+            tasks: self.tasks.len(),
+            teams: self.teams.len(),
+            scores: self.users.iter().map(|user| user.scores.len()).sum(),
+            errors: self.validate(),
+            conflicts: self.conflicts_with(current),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: u32, username: &str) -> UserRecord {
+        UserRecord { id, username: username.to_owned(), display_name: username.to_owned(), is_admin: false, points: 0, enabled: true, pwd_hash: None, scores: vec![] }
+    }
+
+    fn snapshot(users: Vec<UserRecord>, teams: Vec<TeamRecord>) -> RepositorySnapshot {
+        let tasks = vec![Task { id: 1, name: "Kaffee kochen".to_owned(), points: 75, enabled: true, requires_verification: false }];
+        RepositorySnapshot { version: SNAPSHOT_VERSION, users, tasks, teams, setting_changes: vec![], rewards: vec![], redemptions: vec![], ledger: vec![], seasons: vec![], trophies: vec![], kudos: vec![], score_claims: vec![], challenges: vec![], schedules: vec![], webhooks: vec![], dead_letters: vec![] }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut scorer = user(2, "topher");
+        scorer.scores.push(Score::new(Task { id: 7, name: "Fenster putzen".to_owned(), points: 5, enabled: true, requires_verification: false }));
+        let team = TeamRecord { id: 1, name: "Church".to_owned(), manager_id: 1, member_ids: vec![1, 3] };
+        let mut imported = snapshot(vec![user(1, "roterkohl"), scorer, user(2, "roterkohl")], vec![team]);
+        imported.version = SNAPSHOT_VERSION + 1;
+
+        let errors = imported.validate();
+        assert_eq!(5, errors.len());
+        assert!(errors.contains(&"User id 2 is used more than once".to_owned()));
+        assert!(errors.contains(&"Username 'roterkohl' is used more than once".to_owned()));
+        assert!(errors.contains(&"Score of user 'topher' refers to task 7 which does not exist".to_owned()));
+        assert!(errors.contains(&"Member 3 of team 'Church' does not exist".to_owned()));
+        assert!(snapshot(vec![user(1, "roterkohl")], vec![]).validate().is_empty());
+    }
+
+    #[test]
+    fn test_conflicts_with() {
+        let current = snapshot(vec![user(1, "roterkohl"), user(2, "topher")], vec![]);
+        let imported = snapshot(vec![user(1, "dliwespf")], vec![]);
+
+        let conflicts = imported.conflicts_with(&current);
+        assert_eq!(vec!["User 1 'roterkohl' is replaced by 'dliwespf'".to_owned(), "User 2 'topher' is removed".to_owned()], conflicts);
+        assert!(current.conflicts_with(&current).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_derived_from_version_1() {
+        let mut scorer = user(1, "topher");
+        scorer.scores.push(Score::new(Task { id: 1, name: "Kaffee kochen".to_owned(), points: 75, enabled: true, requires_verification: false }));
+        scorer.points = 100;
+        let mut old = snapshot(vec![scorer, user(2, "dliwespf")], vec![]);
+        old.version = 1;
+
+        let ledger = old.ledger_or_derived();
+        assert_eq!(2, ledger.len());
+        assert_eq!(vec![1, 2], ledger.iter().map(|transaction| transaction.id).collect::<Vec<u64>>());
+        assert_eq!(100, crate::model::ledger::lifetime_points(1, &ledger));
+
+        old.version = SNAPSHOT_VERSION;
+        assert!(old.ledger_or_derived().is_empty());
+    }
+}
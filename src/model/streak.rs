@@ -0,0 +1,212 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Score, history::parse_date};
+
+// Days off (weekends if skipped and holidays) neither extend nor break a streak
+#[derive(Clone, Default)]
+pub struct StreakCalendar {
+    pub skip_weekends: bool,
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl StreakCalendar {
+    // Holidays are given as comma separated dates (YYYY-MM-DD)
+    pub fn parse(skip_weekends: bool, holidays: &str) -> Result<StreakCalendar, String> {
+        let holidays = holidays.split(',')
+            .map(str::trim)
+            .filter(|holiday| !holiday.is_empty())
+            .map(parse_date)
+            .collect::<Result<Vec<NaiveDate>, String>>()?;
+        Ok(StreakCalendar { skip_weekends, holidays })
+    }
+
+    pub fn is_working_day(&self, day: NaiveDate) -> bool {
+        let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+        !(self.skip_weekends && weekend) && !self.holidays.contains(&day)
+    }
+
+    // Gives up after a year of days off
+    fn previous_working_day(&self, day: NaiveDate) -> NaiveDate {
+        let mut previous = day - Duration::days(1);
+        while !self.is_working_day(previous) && day - previous < Duration::days(366) {
+            previous -= Duration::days(1);
+        }
+        previous
+    }
+}
+
+// Working days in a row with at least one score, of all tasks or of a single one
+#[derive(serde::Serialize, Clone, Default, JsonSchema)]
+pub struct Streak {
+    pub task_id: Option<u32>,
+    pub task_name: Option<String>,
+    // Still running until the next working day has passed without a score
+    pub current: u32,
+    pub longest: u32,
+    pub last_day: Option<NaiveDate>,
+}
+
+impl Streak {
+    pub fn create<'a>(scores: impl Iterator<Item = &'a Score>, calendar: &StreakCalendar, today: NaiveDate) -> Streak {
+        let mut days: Vec<NaiveDate> = scores.map(|score| score.scored_at.naive_utc().date()).filter(|day| calendar.is_working_day(*day)).collect();
+        days.sort();
+        days.dedup();
+
+        let mut streak = Streak::default();
+        let mut run = 0;
+        for (index, day) in days.iter().enumerate() {
+            run = if index > 0 && calendar.previous_working_day(*day) == days[index - 1] { run + 1 } else { 1 };
+            streak.longest = streak.longest.max(run);
+        }
+
+        streak.last_day = days.last().copied();
+        if streak.last_day.map_or(false, |last_day| last_day == today || last_day == calendar.previous_working_day(today)) {
+            streak.current = run;
+        }
+        streak
+    }
+
+    fn of_task(task_id: u32, scores: &[Score], calendar: &StreakCalendar, today: NaiveDate) -> Streak {
+        let task_scores: Vec<&Score> = scores.iter().filter(|score| score.task.id == task_id).collect();
+        Streak {
+            task_id: Some(task_id),
+            task_name: task_scores.last().map(|score| score.task.name.clone()),
+            ..Streak::create(task_scores.into_iter(), calendar, today)
+        }
+    }
+
+    pub fn bonus_reference(&self) -> String {
+        match &self.task_name {
+            Some(task_name) => format!("{} day streak: {}", self.current, task_name),
+            None => format!("{} day streak", self.current),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct StreakInfo {
+    // Scoring any task at all
+    pub activity: Streak,
+    // One per task the user has scored, ordered by task id
+    pub tasks: Vec<Streak>,
+}
+
+impl StreakInfo {
+    pub fn create(scores: &[Score], calendar: &StreakCalendar, today: NaiveDate) -> StreakInfo {
+        let mut task_ids: Vec<u32> = scores.iter().map(|score| score.task.id).collect();
+        task_ids.sort_unstable();
+        task_ids.dedup();
+
+        StreakInfo {
+            activity: Streak::create(scores.iter(), calendar, today),
+            tasks: task_ids.into_iter().map(|task_id| Streak::of_task(task_id, scores, calendar, today)).collect(),
+        }
+    }
+}
+
+// The streaks the latest score has extended to a multiple of the interval. Only the first score of a
+// working day extends a streak, so scoring the same task twice does not earn the bonus twice.
+// The scores may come in any order, of those scored at the same time the last one counts as the latest
+pub fn streak_bonuses(scores: &[Score], calendar: &StreakCalendar, interval: u32) -> Vec<Streak> {
+    let latest_index = match scores.iter().enumerate().max_by_key(|(_, score)| score.scored_at) {
+        Some((index, _)) => index,
+        None => return vec![]
+    };
+    let latest = &scores[latest_index];
+    let earlier: Vec<&Score> = scores.iter().enumerate().filter(|(index, _)| *index != latest_index).map(|(_, score)| score).collect();
+    let day = latest.scored_at.naive_utc().date();
+    if interval == 0 || !calendar.is_working_day(day) {
+        return vec![];
+    }
+
+    let same_day = |score: &&Score| score.scored_at.naive_utc().date() == day;
+    let mut streaks = vec![];
+    if !earlier.iter().any(same_day) {
+        streaks.push(Streak::create(scores.iter(), calendar, day));
+    }
+    if !earlier.iter().filter(|score| score.task.id == latest.task.id).any(same_day) {
+        streaks.push(Streak::of_task(latest.task.id, scores, calendar, day));
+    }
+    streaks.into_iter().filter(|streak| streak.current > 0 && streak.current % interval == 0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::model::Task;
+
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        // March 2022 starts on a Tuesday
+        NaiveDate::from_ymd_opt(2022, 3, day).unwrap()
+    }
+
+    fn score(task_id: u32, on: u32) -> Score {
+        let task = Task { id: task_id, name: format!("Task {}", task_id), points: 10, enabled: true, requires_verification: false };
+        Score { task, points: 10, scored_at: Utc.from_utc_datetime(&day(on).and_hms_opt(12, 0, 0).unwrap()) }
+    }
+
+    fn working_days() -> StreakCalendar {
+        StreakCalendar::parse(true, "2022-03-08").unwrap()
+    }
+
+    #[test]
+    fn test_days_off_do_not_break_the_streak() {
+        // Friday, Monday and Wednesday, the Tuesday in between is a holiday
+        let scores = vec![score(1, 4), score(1, 7), score(1, 9)];
+        let streak = Streak::create(scores.iter(), &working_days(), day(9));
+        assert_eq!(3, streak.current);
+        assert_eq!(3, streak.longest);
+
+        let streak = Streak::create(scores.iter(), &StreakCalendar::default(), day(9));
+        assert_eq!(1, streak.current);
+    }
+
+    #[test]
+    fn test_streak_ends_after_a_missed_working_day() {
+        let scores = vec![score(1, 1), score(1, 2), score(1, 3), score(1, 10)];
+        assert_eq!(1, Streak::create(scores.iter(), &working_days(), day(11)).current);
+        assert_eq!(3, Streak::create(scores.iter(), &working_days(), day(11)).longest);
+        assert_eq!(0, Streak::create(scores.iter(), &working_days(), day(14)).current);
+    }
+
+    #[test]
+    fn test_streak_info_per_task() {
+        let scores = vec![score(1, 1), score(4, 2), score(1, 2), score(1, 3)];
+        let info = StreakInfo::create(&scores, &working_days(), day(4));
+        assert_eq!(3, info.activity.current);
+        assert_eq!(Some(1), info.tasks[0].task_id);
+        assert_eq!(3, info.tasks[0].current);
+        assert_eq!(0, info.tasks[1].current);
+        assert_eq!(1, info.tasks[1].longest);
+    }
+
+    #[test]
+    fn test_streak_bonuses() {
+        let mut scores = vec![score(1, 1), score(1, 2)];
+        assert!(streak_bonuses(&scores, &working_days(), 3).is_empty());
+
+        scores.push(score(1, 3));
+        let bonuses = streak_bonuses(&scores, &working_days(), 3);
+        assert_eq!(vec!["3 day streak", "3 day streak: Task 1"], bonuses.iter().map(Streak::bonus_reference).collect::<Vec<String>>());
+
+        // A second score on the same day extends nothing
+        scores.push(score(1, 3));
+        assert!(streak_bonuses(&scores, &working_days(), 3).is_empty());
+        assert!(StreakCalendar::parse(true, "2022-13-01").is_err());
+    }
+
+    #[test]
+    fn test_streak_bonuses_newest_score_not_last() {
+        // History ordered newest first, the score of the 3rd completes the streak
+        let scores = vec![score(1, 3), score(1, 2), score(1, 1)];
+        let bonuses = streak_bonuses(&scores, &working_days(), 3);
+        assert_eq!(vec!["3 day streak", "3 day streak: Task 1"], bonuses.iter().map(Streak::bonus_reference).collect::<Vec<String>>());
+
+        // The 2nd was the latest day before, its streak of 2 is not a multiple of 3
+        assert!(streak_bonuses(&scores[1..], &working_days(), 3).is_empty());
+    }
+}
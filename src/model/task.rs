@@ -2,15 +2,18 @@
 use chrono::{DateTime, Utc};
 use rocket_okapi::okapi::schemars::JsonSchema;
 
-#[derive(serde::Serialize, Clone, JsonSchema)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
 pub struct Task {
     pub id: u32,
     pub name: String,
     pub points: u16,
     pub enabled: bool,
+    // Scores of the task only count once another member of the user's teams has confirmed them
+    #[serde(default)]
+    pub requires_verification: bool,
 }
 
-#[derive(serde::Serialize, Clone, JsonSchema)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
 pub struct Score {
     pub task: Task,
     pub points: u16,
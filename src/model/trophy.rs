@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct Trophy {
+    pub id: u32,
+    pub user_id: u32,
+    pub name: String,
+    // Set for trophies awarded at the end of a season
+    pub season_id: Option<u32>,
+    pub awarded_at: DateTime<Utc>,
+}
+
+impl Trophy {
+    pub fn new(user_id: u32, name: String, season_id: Option<u32>) -> Trophy {
+        Trophy { id: 0, user_id, name, season_id, awarded_at: Utc::now() }
+    }
+}
@@ -2,15 +2,17 @@
 use std::{sync::{Arc, Mutex}, hash::Hash, collections::{HashSet, HashMap}};
 
 use bcrypt::{DEFAULT_COST};
+use rand::{Rng, distributions::Alphanumeric};
 use bolt_client::bolt_proto::{value::Node, Value};
 use rocket::{Request, request::Outcome, http::Status, request::{ FromRequest}};
 use rocket_okapi::OpenApiFromRequest;
 use schemars::JsonSchema;
 
-use crate::repository::legacy_repository::LegacyRepository;
-use crate::repository::repository::Repository;
+use crate::repository::repository::DynRepository;
 
-use super::{Task, Score};
+use super::{Task, Score, level::LevelInfo, streak::StreakInfo};
+
+const GENERATED_PASSWORD_LEN: usize = 16;
 
 #[derive(serde::Serialize, Clone, JsonSchema, OpenApiFromRequest)]
 pub struct User {
@@ -18,7 +20,10 @@ pub struct User {
     pub username: String,
     pub display_name: String,
     pub is_admin: bool,
-    pub points: u16,
+    // Lifetime points, derived from the points ledger
+    pub points: u64,
+    // Deactivated users keep their scores but are no longer able to log in
+    pub enabled: bool,
     
     #[serde(skip_serializing)]
     pub scores: Vec<Score>,
@@ -29,18 +34,18 @@ pub struct User {
 
 impl User {
     pub fn new(id: u32, username: String, display_name: String, is_admin: bool) -> User {
-        User {id, username: username, display_name: display_name, points: 0, scores: vec![], pwd_hash_components: None, is_admin}
+        User {id, username: username, display_name: display_name, points: 0, enabled: true, scores: vec![], pwd_hash_components: None, is_admin}
     }
 
-    pub fn score_task<'a>(& mut self, task: Task) {
-        self.points += task.points;
-
+    // Only records the score, its points are booked in the ledger by the repository
+    pub fn score_task<'a>(& mut self, task: Task) -> Score {
         let score = Score::new(task);
-        self.scores.push(score);
+        self.scores.push(score.clone());
+        score
     }
 
     pub fn set_password(&mut self, password: String) {
-        self.pwd_hash_components = Some(bcrypt::hash(password, DEFAULT_COST).unwrap());
+        self.pwd_hash_components = Some(hash_password(password));
     }
 
     pub fn verify_password(&self, password_to_verify: &Option<String>) -> bool {
@@ -54,6 +59,34 @@ impl User {
     }
 }
 
+// A user as returned by the user resource, along with what follows from the scores and the ledger
+#[derive(serde::Serialize, JsonSchema)]
+pub struct UserDetails {
+    #[serde(flatten)]
+    pub user: User,
+    pub level: LevelInfo,
+    // Only given for a single user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub streaks: Option<StreakInfo>,
+}
+
+// Only accepted while the self_registration_enabled setting is on, registered users are never admins
+#[derive(serde::Deserialize, JsonSchema)]
+pub struct Registration {
+    pub username: String,
+    pub display_name: String,
+    pub password: String,
+}
+
+pub fn hash_password(password: String) -> String {
+    bcrypt::hash(password, DEFAULT_COST).unwrap()
+}
+
+// Initial passwords for users who were created without one
+pub fn generate_password() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(GENERATED_PASSWORD_LEN).map(char::from).collect()
+}
+
 #[async_trait]
 impl <'a> FromRequest<'a> for User {
     type Error = String;
@@ -103,9 +136,10 @@ impl From<Node> for User {
         let username = get_string(properties, "username", "N/A");
         let display_name = get_string(properties, "display_name", "N/A");
         let is_admin = get_bool(properties, "is_admin", false);
-        let points = get_u16(properties, "points", 0);
+        let points = get_u64(properties, "points", 0);
+        let enabled = get_bool(properties, "enabled", true);
 
-        User{id: 0, username, display_name, is_admin, points, scores: vec![], pwd_hash_components: None}
+        User{id: 0, username, display_name, is_admin, points, enabled, scores: vec![], pwd_hash_components: None}
     }
 }
 
@@ -123,9 +157,9 @@ fn get_bool(properties: &HashMap<String, Value>, key: &str, alternative: bool) -
     }
 }
 
-fn get_u16(properties: &HashMap<String, Value>, key: &str, alternative: u16) -> u16  {
+fn get_u64(properties: &HashMap<String, Value>, key: &str, alternative: u64) -> u64  {
     match properties.get(key) {
-        Some(Value::Integer(val)) => *val as u16,
+        Some(Value::Integer(val)) => u64::try_from(*val).unwrap_or(alternative),
         _ => alternative
     }
 }
@@ -176,7 +210,7 @@ impl <'a> FromRequest<'a> for Team {
     async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
         let teamname_opt = request.headers().get_one("teamname");
         let user_id_opt = request.headers().get_one("userid");
-        let state = request.rocket().state::<LegacyRepository>().unwrap();
+        let state = request.rocket().state::<DynRepository>().unwrap();
 
         if teamname_opt.is_none() {
             return Outcome::Failure((Status::BadRequest, "Team name is required".to_owned()));
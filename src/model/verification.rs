@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+use super::{Task, Score};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimStatus {
+    Pending,
+    Confirmed,
+    Rejected,
+    Expired,
+}
+
+impl ClaimStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClaimStatus::Pending => "pending",
+            ClaimStatus::Confirmed => "confirmed",
+            ClaimStatus::Rejected => "rejected",
+            ClaimStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<ClaimStatus> {
+        match status {
+            "pending" => Some(ClaimStatus::Pending),
+            "confirmed" => Some(ClaimStatus::Confirmed),
+            "rejected" => Some(ClaimStatus::Rejected),
+            "expired" => Some(ClaimStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+// A score of a task that requires verification, it only becomes a score once it has been confirmed
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct ScoreClaim {
+    pub id: u32,
+    pub user_id: u32,
+    pub task_id: u32,
+    pub task_name: String,
+    // The points at the time of the claim, later changes of the task do not matter
+    pub points: u16,
+    pub status: ClaimStatus,
+    pub claimed_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl ScoreClaim {
+    pub fn new(id: u32, task: &Task, user_id: u32) -> ScoreClaim {
+        ScoreClaim { id, user_id, task_id: task.id, task_name: task.name.clone(), points: task.points,
+            status: ClaimStatus::Pending, claimed_at: Utc::now(), decided_by: None, decided_at: None }
+    }
+
+    pub fn decide(&mut self, confirmed: bool, decided_by: String) -> Result<(), String> {
+        if self.status != ClaimStatus::Pending {
+            return Err(format!("Claim {} has already been {}", self.id, self.status.as_str()));
+        }
+        self.status = if confirmed { ClaimStatus::Confirmed } else { ClaimStatus::Rejected };
+        self.decided_by = Some(decided_by);
+        self.decided_at = Some(Utc::now());
+        Ok(())
+    }
+
+    // Pending claims made before the cutoff expire, returns whether this one just did
+    pub fn expire(&mut self, cutoff: DateTime<Utc>) -> bool {
+        if self.status != ClaimStatus::Pending || self.claimed_at >= cutoff {
+            return false;
+        }
+        self.status = ClaimStatus::Expired;
+        self.decided_at = Some(Utc::now());
+        true
+    }
+
+    // The score counts from the time it was claimed
+    pub fn score(&self, task: Task) -> Score {
+        Score { task, points: self.points, scored_at: self.claimed_at }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn claim() -> ScoreClaim {
+        let task = Task { id: 3, name: "Spülmaschine ausräumen".to_owned(), points: 52, enabled: true, requires_verification: true };
+        ScoreClaim::new(1, &task, 2)
+    }
+
+    #[test]
+    fn test_decide() {
+        let mut claim = claim();
+        assert!(claim.decide(true, "roterkohl".to_owned()).is_ok());
+        assert_eq!(ClaimStatus::Confirmed, claim.status);
+        assert!(claim.decide(false, "roterkohl".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_expire() {
+        let mut claim = claim();
+        assert!(!claim.expire(claim.claimed_at - Duration::hours(1)));
+        assert!(claim.expire(claim.claimed_at + Duration::hours(1)));
+        assert_eq!(ClaimStatus::Expired, claim.status);
+        assert!(claim.decide(true, "roterkohl".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use rocket_okapi::okapi::schemars::JsonSchema;
+
+// Only the latest dead letters are kept, the older ones are dropped
+pub const DEAD_LETTER_LEN: usize = 500;
+
+#[derive(serde::Serialize, Clone, JsonSchema)]
+pub struct Webhook {
+    pub id: u32,
+    pub url: String,
+    // Event names as given by ActivityEvent::name(), an empty list subscribes to all events
+    pub event_types: Vec<String>,
+    pub enabled: bool,
+
+    #[serde(skip_serializing)]
+    pub secret: String,
+}
+
+impl Webhook {
+    pub fn accepts(&self, event_type: &str) -> bool {
+        self.enabled && (self.event_types.is_empty() || self.event_types.iter().any(|t| t == event_type))
+    }
+}
+
+#[derive(serde::Deserialize, Clone, JsonSchema)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+impl NewWebhook {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(format!("'{}' is not a valid http(s) url", self.url));
+        }
+        if self.secret.is_empty() {
+            return Err("A secret is required to sign the payloads".to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    DeadLetter,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, JsonSchema)]
+pub struct WebhookDelivery {
+    pub id: u64,
+    pub webhook_id: u32,
+    pub event_id: u64,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_response_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,16 @@
+use crate::model::snapshot::{RepositorySnapshot, ImportReport};
+
+use super::repository::Repository;
+
+// Replaces all data with the snapshot unless it is a dry run or the snapshot is inconsistent
+pub async fn import<R: Repository + Sync + ?Sized>(repository: &R, snapshot: RepositorySnapshot, dry_run: bool) -> Result<ImportReport, String> {
+    let current = repository.export_snapshot().await?;
+    let mut report = snapshot.report(&current, dry_run);
+    if dry_run || !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    repository.import_snapshot(snapshot).await?;
+    report.applied = true;
+    Ok(report)
+}
@@ -0,0 +1,37 @@
+use std::collections::HashSet;
+
+use crate::model::bulk_import::{CsvImportReport, parse_users, parse_tasks};
+
+use super::repository::Repository;
+
+// Creates the users of the CSV file, provided that every row is valid
+pub async fn import_users<R: Repository + Sync + ?Sized>(repository: &R, content: &str, dry_run: bool) -> Result<CsvImportReport, String> {
+    let usernames: HashSet<String> = repository.get_all_users().await.into_iter().map(|user| user.username).collect();
+    let team_names: HashSet<String> = repository.get_all_teams().await.into_iter().map(|team| team.name).collect();
+    let (users, rows, generated_passwords) = parse_users(content, &usernames, &team_names)?;
+
+    let mut report = CsvImportReport { dry_run, applied: false, rows, generated_passwords: vec![] };
+    if dry_run || !report.is_valid() {
+        return Ok(report);
+    }
+
+    repository.add_in_bulk(users, vec![]).await?;
+    report.applied = true;
+    report.generated_passwords = generated_passwords;
+    Ok(report)
+}
+
+// Creates the tasks of the CSV file, provided that every row is valid
+pub async fn import_tasks<R: Repository + Sync + ?Sized>(repository: &R, content: &str, dry_run: bool) -> Result<CsvImportReport, String> {
+    let names: HashSet<String> = repository.get_all_tasks().await.into_iter().map(|task| task.name).collect();
+    let (tasks, rows) = parse_tasks(content, &names)?;
+
+    let mut report = CsvImportReport { dry_run, applied: false, rows, generated_passwords: vec![] };
+    if dry_run || !report.is_valid() {
+        return Ok(report);
+    }
+
+    repository.add_in_bulk(vec![], tasks).await?;
+    report.applied = true;
+    Ok(report)
+}
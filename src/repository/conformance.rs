@@ -0,0 +1,906 @@
+// Behaviour every Repository implementation has to show. Each test gets a fresh repository filled with the
+// demo data (see demo_data.rs) from the fixture of the implementation, so the tests do not depend on each other.
+//
+// Users: 1 roterkohl (admin, 157 points), 2 brutours.de (137), 3 dliwespf (0), 4 topher (admin, 375)
+// Teams: 1 Babes (managed by 1), 2 Church (managed by 2, members 1 to 4)
+
+use chrono::{Duration, Utc};
+use rocket::http::Status;
+
+use crate::model::{User, Task, session::LoginRequest, bulk_import::ImportedUser, history::ScoreFilter, reward::{Reward, RedemptionStatus}, ledger::{Balance, PointTransaction, TransactionKind}, kudos::{Kudos, KudosLimits, KudosRequest}, verification::ClaimStatus, challenge::{Challenge, ChallengeRequest, ChallengeStatus, Participant}, schedule::{Schedule, ScheduleRequest, Rota}, season::Season, user::Team, health::HealthStatus, setting::{SettingValue, SettingChange}, webhook::{Webhook, WebhookDelivery, DeliveryStatus, DEAD_LETTER_LEN}};
+
+use super::repository::Repository;
+
+// Generates a test for each conformance test, the fixture is an async fn taking the test name and returning a fresh repository
+macro_rules! repository_conformance_tests {
+    ($fixture:path $(, #[$attribute:meta])*) => {
+        $crate::repository::conformance::repository_conformance_tests!(@tests $fixture, [$(#[$attribute])*],
+            test_get_user_exists,
+            test_get_user_does_not_exist,
+            test_find_user_by_username_exists,
+            test_find_user_by_username_does_not_exist,
+            test_find_user_by_username_const_exists,
+            test_get_all_users,
+            test_get_task_exists,
+            test_get_task_does_not_exist,
+            test_get_all_tasks,
+            test_score_ok,
+            test_score_user_does_not_exist,
+            test_score_task_does_not_exist,
+            test_score_task_is_disabled,
+            test_score_publishes_event,
+            test_score_publishes_leaderboard_change,
+            test_create_and_add_user_ok,
+            test_create_and_add_user_name_clash,
+            test_add_team,
+            test_add_user_to_team_ok,
+            test_add_user_to_team_already_member,
+            test_add_user_to_team_not_authorized,
+            test_add_user_to_team_team_does_not_exist,
+            test_add_user_to_team_user_does_not_exist,
+            test_add_user_ok,
+            test_add_user_not_admin,
+            test_add_user_name_clash,
+            test_login_ok,
+            test_login_missing_user,
+            test_login_wrong_password,
+            test_get_session_does_not_exist,
+            test_logout_ok,
+            test_logout_not_logged_in,
+            test_check_health,
+            test_settings_without_changes,
+            test_save_setting,
+            test_set_password,
+            test_set_password_user_does_not_exist,
+            test_set_user_enabled,
+            test_save_task_create,
+            test_save_task_update,
+            test_export_snapshot,
+            test_import_snapshot,
+            test_add_in_bulk,
+            test_add_in_bulk_is_all_or_nothing,
+            test_get_all_teams,
+            test_aggregate_scores,
+            test_aggregate_scores_filtered,
+            test_save_reward,
+            test_redeem,
+            test_redeem_out_of_stock,
+            test_decide_redemption,
+            test_snapshot_keeps_rewards,
+            test_score_books_transaction,
+            test_add_transaction,
+            test_import_snapshot_without_ledger,
+            test_start_and_end_season,
+            test_start_season_conflicts,
+            test_season_runs_between_start_and_end,
+            test_snapshot_keeps_seasons,
+            test_give_kudos,
+            test_kudos_limits,
+            test_claim_and_confirm_score,
+            test_reject_and_expire_score_claims,
+            test_challenge_lifecycle,
+            test_decline_challenge,
+            test_save_and_remove_schedule,
+            test_settle_schedules,
+            test_add_and_remove_webhook,
+            test_dead_letters_are_bounded,
+        );
+    };
+    (@tests $fixture:path, [$(#[$attribute:meta])*], $($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            $(#[$attribute])*
+            fn $name() {
+                rocket::futures::executor::block_on(async {
+                    let repository = $fixture(stringify!($name)).await;
+                    $crate::repository::conformance::$name(&repository).await;
+                });
+            }
+        )*
+    };
+}
+
+pub(crate) use repository_conformance_tests;
+
+fn login_request(username: &str, password: &str) -> LoginRequest {
+    LoginRequest { username: username.to_owned(), password: Some(password.to_owned()) }
+}
+
+pub async fn test_get_user_exists<R: Repository + Sync>(repository: &R) {
+    let user = repository.get_user(2).await.unwrap();
+    assert_eq!(2, user.id);
+    assert_eq!("brutours.de", user.username);
+    assert_eq!(137, user.points);
+    assert_eq!(3, user.scores.len());
+}
+
+pub async fn test_get_user_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert!(repository.get_user(10).await.is_none());
+}
+
+pub async fn test_find_user_by_username_exists<R: Repository + Sync>(repository: &R) {
+    let user = repository.find_user_by_username(&"topher".to_owned()).await.unwrap();
+    assert_eq!(4, user.lock().unwrap().id);
+}
+
+pub async fn test_find_user_by_username_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert!(repository.find_user_by_username(&"eduardLaser".to_owned()).await.is_none());
+    assert!(repository.find_user_by_username_const(&"eduardLaser".to_owned()).await.is_none());
+}
+
+pub async fn test_find_user_by_username_const_exists<R: Repository + Sync>(repository: &R) {
+    let user = repository.find_user_by_username_const(&"roterkohl".to_owned()).await.unwrap();
+    assert_eq!(1, user.id);
+    assert!(user.is_admin);
+}
+
+pub async fn test_get_all_users<R: Repository + Sync>(repository: &R) {
+    let mut user_ids: Vec<u32> = repository.get_all_users().await.iter().map(|user| user.id).collect();
+    user_ids.sort();
+    assert_eq!(vec![1, 2, 3, 4], user_ids);
+}
+
+pub async fn test_get_task_exists<R: Repository + Sync>(repository: &R) {
+    let task = repository.get_task(4).await.unwrap();
+    assert_eq!(4, task.id);
+    assert_eq!(75, task.points);
+}
+
+pub async fn test_get_task_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert!(repository.get_task(4711).await.is_none());
+}
+
+pub async fn test_get_all_tasks<R: Repository + Sync>(repository: &R) {
+    assert_eq!(4, repository.get_all_tasks().await.len());
+}
+
+pub async fn test_score_ok<R: Repository + Sync>(repository: &R) {
+    assert_eq!(Ok(450), repository.score(4, 4).await);
+
+    let user = repository.get_user(4).await.unwrap();
+    assert_eq!(450, user.points);
+    assert_eq!(6, user.scores.len());
+}
+
+pub async fn test_score_user_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert_eq!(Err("User does not exist".to_owned()), repository.score(40, 4).await);
+}
+
+pub async fn test_score_task_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert_eq!(Err("Task does not exist".to_owned()), repository.score(4, 40).await);
+}
+
+pub async fn test_score_task_is_disabled<R: Repository + Sync>(repository: &R) {
+    assert_eq!(Err("Task is not enabled".to_owned()), repository.score(4, 2).await);
+    assert_eq!(375, repository.get_user(4).await.unwrap().points);
+}
+
+pub async fn test_score_publishes_event<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+    repository.score(2, 1).await.unwrap();
+
+    let message = receiver.try_recv().unwrap();
+    assert_eq!("score", message.event.name());
+    assert_eq!(vec![2], message.team_ids);
+    // The order of the leaderboard has not changed
+    assert!(receiver.try_recv().is_err());
+}
+
+pub async fn test_score_publishes_leaderboard_change<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+    repository.score(2, 4).await.unwrap();
+
+    assert_eq!("score", receiver.try_recv().unwrap().event.name());
+    assert_eq!("leaderboard_change", receiver.try_recv().unwrap().event.name());
+}
+
+pub async fn test_create_and_add_user_ok<R: Repository + Sync>(repository: &R) {
+    let user = repository.create_and_add_user("Winston".to_owned(), "Wilson".to_owned(), "fml".to_owned(), false).await.unwrap();
+    let new_user_id = user.lock().unwrap().id;
+    assert_eq!(5, new_user_id);
+
+    let new_user = repository.get_user(new_user_id).await.unwrap();
+    assert!(new_user.verify_password(&Some("fml".to_owned())));
+}
+
+pub async fn test_create_and_add_user_name_clash<R: Repository + Sync>(repository: &R) {
+    let result = repository.create_and_add_user("dliwespf".to_owned(), "Wilson".to_owned(), "fml".to_owned(), false).await;
+    assert_eq!("Username is not available", result.err().unwrap());
+}
+
+pub async fn test_add_team<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+    let manager = repository.find_user_by_username(&"dliwespf".to_owned()).await.unwrap();
+
+    assert_eq!(Some(3), repository.add_team(Team::new(0, "newTeam".to_owned(), manager.clone())).await);
+    assert_eq!("team_created", receiver.try_recv().unwrap().event.name());
+    // The manager is a member of the new team right away
+    let manager = manager.lock().unwrap().clone();
+    let result = repository.add_user_to_team(&"newTeam".to_owned(), 3, manager).await;
+    assert_eq!("User 'dliwespf' is already member of group 'newTeam'", result.unwrap_err());
+}
+
+pub async fn test_add_user_to_team_ok<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+    let manager = repository.get_user(1).await.unwrap();
+
+    assert_eq!(Ok(()), repository.add_user_to_team(&"Babes".to_owned(), 2, manager).await);
+    let message = receiver.try_recv().unwrap();
+    assert_eq!("team_member_added", message.event.name());
+    assert_eq!(vec![1], message.team_ids);
+
+    // Scores of the new member are now published to the team as well
+    repository.score(2, 1).await.unwrap();
+    assert_eq!(vec![1, 2], receiver.try_recv().unwrap().team_ids);
+}
+
+pub async fn test_add_user_to_team_already_member<R: Repository + Sync>(repository: &R) {
+    let manager = repository.get_user(2).await.unwrap();
+    let result = repository.add_user_to_team(&"Church".to_owned(), 3, manager).await;
+    assert_eq!("User 'dliwespf' is already member of group 'Church'", result.unwrap_err());
+}
+
+pub async fn test_add_user_to_team_not_authorized<R: Repository + Sync>(repository: &R) {
+    let not_the_manager = repository.get_user(3).await.unwrap();
+    let result = repository.add_user_to_team(&"Babes".to_owned(), 2, not_the_manager).await;
+    assert_eq!("User 'dliwespf' is not authorized to add users to group 'Babes'", result.unwrap_err());
+}
+
+pub async fn test_add_user_to_team_team_does_not_exist<R: Repository + Sync>(repository: &R) {
+    let manager = repository.get_user(1).await.unwrap();
+    let result = repository.add_user_to_team(&"Nobodies".to_owned(), 2, manager).await;
+    assert_eq!("Team with name 'Nobodies'does not exist", result.unwrap_err());
+}
+
+pub async fn test_add_user_to_team_user_does_not_exist<R: Repository + Sync>(repository: &R) {
+    let manager = repository.get_user(1).await.unwrap();
+    let result = repository.add_user_to_team(&"Babes".to_owned(), 40, manager).await;
+    assert_eq!("User with id 40 does not exist", result.unwrap_err());
+}
+
+pub async fn test_add_user_ok<R: Repository + Sync>(repository: &R) {
+    let admin_session = repository.login(login_request("roterkohl", "Flori1234")).await.unwrap();
+    let mut receiver = repository.event_bus().subscribe();
+
+    let newbie = User::new(0, "newbie".to_owned(), "Newbie".to_owned(), false);
+    let result = repository.add_user(&admin_session, newbie).await;
+    assert_eq!(Status::Ok, result.status());
+
+    let new_user = repository.get_user(result.content.unwrap()).await.unwrap();
+    assert_eq!("newbie", new_user.username);
+    assert_eq!("user_created", receiver.try_recv().unwrap().event.name());
+}
+
+pub async fn test_add_user_not_admin<R: Repository + Sync>(repository: &R) {
+    let user_session = repository.login(login_request("dliwespf", "Franki1234")).await.unwrap();
+
+    let newbie = User::new(0, "newbie".to_owned(), "Newbie".to_owned(), false);
+    let result = repository.add_user(&user_session, newbie).await;
+    assert_eq!(Status::Forbidden, result.status());
+    assert!(repository.find_user_by_username_const(&"newbie".to_owned()).await.is_none());
+}
+
+pub async fn test_add_user_name_clash<R: Repository + Sync>(repository: &R) {
+    let admin_session = repository.login(login_request("roterkohl", "Flori1234")).await.unwrap();
+
+    let clash = User::new(0, "topher".to_owned(), "Another Topher".to_owned(), false);
+    let result = repository.add_user(&admin_session, clash).await;
+    assert_eq!(Status::Conflict, result.status());
+}
+
+pub async fn test_login_ok<R: Repository + Sync>(repository: &R) {
+    let session = repository.login(login_request("roterkohl", "Flori1234")).await.unwrap();
+    assert_eq!(30, session.id.len());
+    assert_eq!(1, session.user.lock().unwrap().id);
+
+    let stored = repository.get_session(&session.id).await.unwrap();
+    assert_eq!(session.id, stored.id);
+    assert_eq!(1, stored.user.lock().unwrap().id);
+}
+
+pub async fn test_login_missing_user<R: Repository + Sync>(repository: &R) {
+    let result = repository.login(login_request("blauerkohl", "Flori1234")).await;
+    assert_eq!("User does not exist", result.err().unwrap());
+}
+
+pub async fn test_login_wrong_password<R: Repository + Sync>(repository: &R) {
+    let result = repository.login(login_request("roterkohl", "Flori5678")).await;
+    assert_eq!("Password mismatch", result.err().unwrap());
+}
+
+pub async fn test_get_session_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert!(repository.get_session(&"NoSuchSession".to_owned()).await.is_none());
+}
+
+pub async fn test_logout_ok<R: Repository + Sync>(repository: &R) {
+    let session = repository.login(login_request("dliwespf", "Franki1234")).await.unwrap();
+    assert_eq!(1, repository.count_sessions(Utc::now() - Duration::hours(1)).await);
+    assert_eq!(0, repository.count_sessions(Utc::now() + Duration::seconds(1)).await);
+
+    assert_eq!(Ok(()), repository.logout(&session.id).await);
+    assert_eq!(0, repository.count_sessions(Utc::now() - Duration::hours(1)).await);
+    assert!(repository.get_session(&session.id).await.is_none());
+    assert_eq!(Err("Session unknown".to_owned()), repository.logout(&session.id).await);
+}
+
+pub async fn test_logout_not_logged_in<R: Repository + Sync>(repository: &R) {
+    assert_eq!(Err("Session unknown".to_owned()), repository.logout(&"NoSuchSession".to_owned()).await);
+}
+
+pub async fn test_check_health<R: Repository + Sync>(repository: &R) {
+    assert!(repository.check_health().await.iter().all(|check| check.status == HealthStatus::Up));
+}
+
+pub async fn test_settings_without_changes<R: Repository + Sync>(repository: &R) {
+    assert!(repository.get_settings().await.is_empty());
+    assert!(repository.get_setting_history(&"scoring_paused".to_owned()).await.is_empty());
+}
+
+pub async fn test_save_setting<R: Repository + Sync>(repository: &R) {
+    let change = |old_value: bool, new_value: bool| SettingChange {
+        key: "scoring_paused".to_owned(),
+        old_value: SettingValue::Bool(old_value),
+        new_value: SettingValue::Bool(new_value),
+        changed_by: "roterkohl".to_owned(),
+        changed_at: Utc::now(),
+    };
+    repository.save_setting(change(false, true)).await.unwrap();
+    repository.save_setting(change(true, false)).await.unwrap();
+
+    assert_eq!(vec![("scoring_paused".to_owned(), SettingValue::Bool(false))], repository.get_settings().await);
+
+    let history = repository.get_setting_history(&"scoring_paused".to_owned()).await;
+    assert_eq!(2, history.len());
+    assert_eq!(SettingValue::Bool(false), history[0].new_value);
+    assert_eq!(SettingValue::Bool(true), history[1].new_value);
+}
+
+pub async fn test_set_password<R: Repository + Sync>(repository: &R) {
+    repository.set_password(3, "N3wPwd".to_owned()).await.unwrap();
+
+    assert!(repository.login(login_request("dliwespf", "N3wPwd")).await.is_ok());
+    assert_eq!("Password mismatch", repository.login(login_request("dliwespf", "Franki1234")).await.err().unwrap());
+}
+
+pub async fn test_set_password_user_does_not_exist<R: Repository + Sync>(repository: &R) {
+    assert_eq!(Err("User with id 40 does not exist".to_owned()), repository.set_password(40, "N3wPwd".to_owned()).await);
+}
+
+pub async fn test_set_user_enabled<R: Repository + Sync>(repository: &R) {
+    let session = repository.login(login_request("dliwespf", "Franki1234")).await.unwrap();
+
+    repository.set_user_enabled(3, false).await.unwrap();
+    assert!(!repository.get_user(3).await.unwrap().enabled);
+    assert!(repository.get_session(&session.id).await.is_none());
+    assert_eq!("User is deactivated", repository.login(login_request("dliwespf", "Franki1234")).await.err().unwrap());
+
+    repository.set_user_enabled(3, true).await.unwrap();
+    assert!(repository.login(login_request("dliwespf", "Franki1234")).await.is_ok());
+}
+
+pub async fn test_save_task_create<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+    let task = repository.save_task(Task { id: 0, name: "Müll rausbringen".to_owned(), points: 20, enabled: true, requires_verification: false }).await.unwrap();
+    assert_eq!(5, task.id);
+    assert_eq!("task_created", receiver.try_recv().unwrap().event.name());
+
+    assert_eq!("Müll rausbringen", repository.get_task(5).await.unwrap().name);
+    assert_eq!(Ok(20), repository.score(3, 5).await);
+}
+
+pub async fn test_save_task_update<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+    repository.save_task(Task { id: 2, name: "Stunden abgeben".to_owned(), points: 35, enabled: true, requires_verification: false }).await.unwrap();
+    assert_eq!("task_updated", receiver.try_recv().unwrap().event.name());
+
+    assert_eq!(4, repository.get_all_tasks().await.len());
+    assert_eq!(Ok(35), repository.score(3, 2).await);
+}
+
+pub async fn test_export_snapshot<R: Repository + Sync>(repository: &R) {
+    let snapshot = repository.export_snapshot().await.unwrap();
+
+    assert_eq!(4, snapshot.users.len());
+    assert_eq!(4, snapshot.tasks.len());
+    let church = snapshot.teams.iter().find(|team| team.name == "Church").unwrap();
+    assert_eq!(vec![1, 2, 3, 4], church.member_ids);
+    let topher = snapshot.users.iter().find(|user| user.username == "topher").unwrap();
+    assert_eq!(5, topher.scores.len());
+    assert!(topher.pwd_hash.is_some());
+}
+
+pub async fn test_import_snapshot<R: Repository + Sync>(repository: &R) {
+    let snapshot = repository.export_snapshot().await.unwrap();
+    let session = repository.login(login_request("topher", "Topheri1234")).await.unwrap();
+    repository.score(4, 4).await.unwrap();
+    repository.create_and_add_user("Winston".to_owned(), "Wilson".to_owned(), "fml".to_owned(), false).await.unwrap();
+
+    repository.import_snapshot(snapshot).await.unwrap();
+
+    assert_eq!(375, repository.get_user(4).await.unwrap().points);
+    assert_eq!(5, repository.get_user(4).await.unwrap().scores.len());
+    assert!(repository.find_user_by_username_const(&"Winston".to_owned()).await.is_none());
+    assert!(repository.get_session(&session.id).await.is_none());
+    // Passwords survive the round trip
+    assert!(repository.login(login_request("topher", "Topheri1234")).await.is_ok());
+}
+
+fn imported_user(username: &str, team: Option<&str>) -> ImportedUser {
+    let mut user = User::new(0, username.to_owned(), username.to_owned(), false);
+    user.set_password("fml".to_owned());
+    ImportedUser { user, team: team.map(str::to_owned) }
+}
+
+pub async fn test_add_in_bulk<R: Repository + Sync>(repository: &R) {
+    let task = Task { id: 0, name: "Fenster putzen".to_owned(), points: 20, enabled: true, requires_verification: false };
+    repository.add_in_bulk(vec![imported_user("Winston", Some("Babes")), imported_user("Hugo", None)], vec![task]).await.unwrap();
+
+    let winston = repository.find_user_by_username_const(&"Winston".to_owned()).await.unwrap();
+    assert_eq!(5, winston.id);
+    assert!(repository.login(login_request("Winston", "fml")).await.is_ok());
+    assert_eq!(6, repository.find_user_by_username_const(&"Hugo".to_owned()).await.unwrap().id);
+    let babes = repository.export_snapshot().await.unwrap().teams.into_iter().find(|team| team.name == "Babes").unwrap();
+    assert_eq!(vec![1, 5], babes.member_ids);
+    assert_eq!("Fenster putzen", repository.get_task(5).await.unwrap().name);
+}
+
+pub async fn test_add_in_bulk_is_all_or_nothing<R: Repository + Sync>(repository: &R) {
+    let task = Task { id: 0, name: "Fenster putzen".to_owned(), points: 20, enabled: true, requires_verification: false };
+    let users = vec![imported_user("Winston", None), imported_user("Hugo", Some("Winners"))];
+    assert!(repository.add_in_bulk(users, vec![task.clone()]).await.is_err());
+    let users = vec![imported_user("Winston", None), imported_user("topher", None)];
+    assert!(repository.add_in_bulk(users, vec![task]).await.is_err());
+
+    assert!(repository.find_user_by_username_const(&"Winston".to_owned()).await.is_none());
+    assert_eq!(4, repository.get_all_users().await.len());
+    assert_eq!(4, repository.get_all_tasks().await.len());
+}
+
+pub async fn test_get_all_teams<R: Repository + Sync>(repository: &R) {
+    let teams = repository.get_all_teams().await;
+    assert_eq!(2, teams.len());
+    let church = teams.iter().find(|team| team.name == "Church").unwrap();
+    assert_eq!(2, church.manager_id);
+    assert_eq!(vec![1, 2, 3, 4], church.member_ids);
+}
+
+pub async fn test_aggregate_scores<R: Repository + Sync>(repository: &R) {
+    let aggregates = repository.aggregate_scores(&ScoreFilter::default()).await.unwrap();
+
+    let keys: Vec<(u32, u32)> = aggregates.iter().map(|aggregate| (aggregate.user_id, aggregate.task_id)).collect();
+    assert_eq!(vec![(1, 1), (1, 3), (1, 4), (2, 1), (2, 3), (2, 4), (4, 4)], keys);
+    assert_eq!(3, aggregates[0].count);
+    assert_eq!(30, aggregates[0].points);
+    assert_eq!("Flori", aggregates[0].display_name);
+    assert_eq!("Kaffee kochen", aggregates[6].task_name);
+    assert_eq!(375, aggregates[6].points);
+}
+
+pub async fn test_aggregate_scores_filtered<R: Repository + Sync>(repository: &R) {
+    let filter = ScoreFilter::parse(None, None, Some(4)).unwrap();
+    let points: Vec<u32> = repository.aggregate_scores(&filter).await.unwrap().iter().map(|aggregate| aggregate.points).collect();
+    assert_eq!(vec![75, 75, 375], points);
+
+    let filter = ScoreFilter::parse(Some("2100-01-01"), None, None).unwrap();
+    assert!(repository.aggregate_scores(&filter).await.unwrap().is_empty());
+}
+
+fn reward(name: &str, cost: u16, stock: Option<u32>) -> Reward {
+    Reward { id: 0, name: name.to_owned(), cost, stock, enabled: true }
+}
+
+pub async fn test_save_reward<R: Repository + Sync>(repository: &R) {
+    let created = repository.save_reward(reward("Kinogutschein", 100, Some(2))).await.unwrap();
+    assert_eq!(1, created.id);
+    assert!(repository.save_reward(reward("Kinogutschein", 50, None)).await.is_err());
+
+    repository.save_reward(Reward { cost: 120, ..created }).await.unwrap();
+    assert_eq!(120, repository.get_reward(1).await.unwrap().cost);
+    assert_eq!(1, repository.get_all_rewards().await.len());
+    assert!(repository.get_reward(2).await.is_none());
+}
+
+pub async fn test_redeem<R: Repository + Sync>(repository: &R) {
+    repository.save_reward(reward("Kinogutschein", 100, Some(2))).await.unwrap();
+    let mut receiver = repository.event_bus().subscribe();
+
+    let redemption = repository.redeem(1, 1).await.unwrap();
+    assert_eq!(RedemptionStatus::Pending, redemption.status);
+    assert_eq!(100, redemption.cost);
+    assert_eq!(Some(1), repository.get_reward(1).await.unwrap().stock);
+    assert_eq!("reward_redeemed", receiver.try_recv().unwrap().event.name());
+
+    assert_eq!(57, Balance::create(1, &repository.get_ledger(Some(1)).await).balance);
+    assert!(repository.redeem(1, 1).await.is_err());
+    assert!(repository.redeem(3, 1).await.is_err());
+    assert!(repository.redeem(1, 2).await.is_err());
+    assert_eq!(1, repository.get_redemptions(Some(1)).await.len());
+    assert!(repository.get_redemptions(Some(3)).await.is_empty());
+    // Lifetime points stay untouched
+    assert_eq!(157, repository.get_user(1).await.unwrap().points);
+}
+
+pub async fn test_redeem_out_of_stock<R: Repository + Sync>(repository: &R) {
+    repository.save_reward(reward("Kinogutschein", 10, Some(1))).await.unwrap();
+    repository.save_reward(Reward { enabled: false, ..reward("Urlaubstag", 10, None) }).await.unwrap();
+
+    repository.redeem(4, 1).await.unwrap();
+    assert_eq!(Err("Reward is out of stock".to_owned()), repository.redeem(4, 1).await.map(|redemption| redemption.id));
+    assert_eq!(Err("Reward is not enabled".to_owned()), repository.redeem(4, 2).await.map(|redemption| redemption.id));
+}
+
+pub async fn test_decide_redemption<R: Repository + Sync>(repository: &R) {
+    repository.save_reward(reward("Kinogutschein", 100, Some(1))).await.unwrap();
+    let rejected = repository.redeem(1, 1).await.unwrap();
+
+    let decided = repository.decide_redemption(rejected.id, false, "brutours.de".to_owned()).await.unwrap();
+    assert_eq!(RedemptionStatus::Rejected, decided.status);
+    assert_eq!(Some("brutours.de".to_owned()), decided.decided_by);
+    assert!(decided.decided_at.is_some());
+    assert!(repository.decide_redemption(rejected.id, true, "brutours.de".to_owned()).await.is_err());
+
+    // Points and stock are given back, so the reward can be redeemed again
+    let refund = repository.get_ledger(Some(1)).await.pop().unwrap();
+    assert_eq!((TransactionKind::Refund, 100), (refund.kind, refund.amount));
+    assert_eq!(Some("brutours.de".to_owned()), refund.created_by);
+    let approved = repository.redeem(1, 1).await.unwrap();
+    repository.decide_redemption(approved.id, true, "topher".to_owned()).await.unwrap();
+    assert_eq!(Some(0), repository.get_reward(1).await.unwrap().stock);
+    let statuses: Vec<RedemptionStatus> = repository.get_redemptions(None).await.iter().map(|redemption| redemption.status).collect();
+    assert_eq!(vec![RedemptionStatus::Rejected, RedemptionStatus::Approved], statuses);
+    assert!(repository.decide_redemption(4711, true, "topher".to_owned()).await.is_err());
+}
+
+pub async fn test_snapshot_keeps_rewards<R: Repository + Sync>(repository: &R) {
+    repository.save_reward(reward("Kinogutschein", 100, None)).await.unwrap();
+    repository.redeem(4, 1).await.unwrap();
+    let snapshot = repository.export_snapshot().await.unwrap();
+    assert_eq!(1, snapshot.rewards.len());
+    assert_eq!(1, snapshot.redemptions.len());
+
+    repository.save_reward(reward("Urlaubstag", 500, None)).await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+    assert_eq!(1, repository.get_all_rewards().await.len());
+    assert_eq!(4, repository.get_redemptions(Some(4)).await[0].user_id);
+}
+
+pub async fn test_score_books_transaction<R: Repository + Sync>(repository: &R) {
+    repository.score(3, 4).await.unwrap();
+
+    let ledger = repository.get_ledger(Some(3)).await;
+    assert_eq!(1, ledger.len());
+    assert_eq!(TransactionKind::Score, ledger[0].kind);
+    assert_eq!(75, ledger[0].amount);
+    assert_eq!(Some("Kaffee kochen".to_owned()), ledger[0].reference);
+    // The demo data consists of 13 scores
+    assert_eq!(14, repository.get_ledger(None).await.len());
+}
+
+pub async fn test_add_transaction<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+
+    let bonus = repository.add_transaction(PointTransaction::new(3, TransactionKind::Bonus, 500, Some("Umzug".to_owned()), Some("topher".to_owned()))).await.unwrap();
+    assert_eq!(14, bonus.id);
+    assert_eq!(500, repository.get_user(3).await.unwrap().points);
+    assert_eq!("points_booked", receiver.try_recv().unwrap().event.name());
+    assert_eq!("leaderboard_change", receiver.try_recv().unwrap().event.name());
+
+    // Lifetime points stop at 0, the balance does not
+    repository.add_transaction(PointTransaction::new(3, TransactionKind::Adjustment, -600, None, None)).await.unwrap();
+    assert_eq!(0, repository.get_user(3).await.unwrap().points);
+    assert_eq!(-100, Balance::create(3, &repository.get_ledger(Some(3)).await).balance);
+    assert!(repository.add_transaction(PointTransaction::new(4711, TransactionKind::Bonus, 5, None, None)).await.is_err());
+}
+
+pub async fn test_import_snapshot_without_ledger<R: Repository + Sync>(repository: &R) {
+    let mut snapshot = repository.export_snapshot().await.unwrap();
+    assert_eq!(13, snapshot.ledger.len());
+    snapshot.version = 1;
+    snapshot.ledger.clear();
+
+    repository.import_snapshot(snapshot).await.unwrap();
+    assert_eq!(13, repository.get_ledger(None).await.len());
+    assert_eq!(375, repository.get_user(4).await.unwrap().points);
+    assert_eq!(157, Balance::create(1, &repository.get_ledger(Some(1)).await).balance);
+}
+
+pub async fn test_start_and_end_season<R: Repository + Sync>(repository: &R) {
+    let season = repository.start_season(Season::new(0, "Herbst".to_owned(), Utc::now(), None)).await.unwrap();
+    assert!(season.is_active());
+    repository.score(3, 4).await.unwrap();
+    repository.score(1, 1).await.unwrap();
+    repository.add_transaction(PointTransaction::new(2, TransactionKind::Bonus, 5, None, None)).await.unwrap();
+    assert_eq!(Some(season.id), repository.get_ledger(Some(3)).await.last().unwrap().season_id);
+
+    // Only what has been earned within the season counts
+    let leaderboard = repository.get_season_leaderboard(season.id).await.unwrap();
+    assert_eq!(vec![3, 1, 2, 4], leaderboard.iter().map(|entry| entry.user_id).collect::<Vec<u32>>());
+    assert_eq!(75, leaderboard[0].points);
+
+    let mut receiver = repository.event_bus().subscribe();
+    let ended = repository.end_season(season.id).await.unwrap();
+    assert!(!ended.is_active());
+    assert_eq!(4, ended.standings.len());
+    assert_eq!("season_ended", receiver.try_recv().unwrap().event.name());
+    assert_eq!("trophy", receiver.try_recv().unwrap().event.name());
+
+    // Scores after the end do not change the archived standings
+    repository.score(4, 4).await.unwrap();
+    assert_eq!(4, repository.get_season_leaderboard(season.id).await.unwrap()[3].user_id);
+    assert_eq!(None, repository.get_ledger(Some(4)).await.last().unwrap().season_id);
+
+    let trophies = repository.get_trophies(None).await;
+    assert_eq!(vec![3, 1, 2], trophies.iter().map(|trophy| trophy.user_id).collect::<Vec<u32>>());
+    assert_eq!("Herbst: 1st place", repository.get_trophies(Some(3)).await[0].name);
+    assert!(repository.end_season(season.id).await.is_err());
+    assert!(repository.end_season(4711).await.is_err());
+    assert!(repository.get_season_leaderboard(4711).await.is_none());
+}
+
+pub async fn test_start_season_conflicts<R: Repository + Sync>(repository: &R) {
+    let season = repository.start_season(Season::new(0, "Herbst".to_owned(), Utc::now(), None)).await.unwrap();
+    assert!(repository.start_season(Season::new(0, "Winter".to_owned(), Utc::now(), None)).await.is_err());
+    repository.end_season(season.id).await.unwrap();
+
+    assert!(repository.start_season(Season::new(0, "Herbst".to_owned(), Utc::now(), None)).await.is_err());
+    let winter = repository.start_season(Season::new(0, "Winter".to_owned(), Utc::now(), None)).await.unwrap();
+    assert_eq!(season.id + 1, winter.id);
+    assert_eq!(2, repository.get_seasons().await.len());
+}
+
+pub async fn test_season_runs_between_start_and_end<R: Repository + Sync>(repository: &R) {
+    let upcoming = repository.start_season(Season::new(0, "Winter".to_owned(), Utc::now() + Duration::days(1), None)).await.unwrap();
+    repository.score(3, 4).await.unwrap();
+    assert_eq!(None, repository.get_ledger(Some(3)).await.last().unwrap().season_id);
+    repository.end_season(upcoming.id).await.unwrap();
+
+    // Past its planned end the season no longer collects points and ends at that time
+    let ends_at = Utc::now() - Duration::hours(1);
+    let season = repository.start_season(Season::new(0, "Herbst".to_owned(), Utc::now() - Duration::days(90), Some(ends_at))).await.unwrap();
+    repository.score(3, 4).await.unwrap();
+    assert_eq!(None, repository.get_ledger(Some(3)).await.last().unwrap().season_id);
+
+    let ended = repository.end_due_seasons(Utc::now()).await;
+    assert_eq!(vec![season.id], ended.iter().map(|season| season.id).collect::<Vec<u32>>());
+    assert_eq!(Some(ends_at), ended[0].end);
+    assert_eq!(Some(ends_at), repository.get_seasons().await[1].ends_at);
+    assert!(repository.end_due_seasons(Utc::now()).await.is_empty());
+}
+
+pub async fn test_snapshot_keeps_seasons<R: Repository + Sync>(repository: &R) {
+    let season = repository.start_season(Season::new(0, "Herbst".to_owned(), Utc::now(), None)).await.unwrap();
+    repository.score(3, 4).await.unwrap();
+    repository.end_season(season.id).await.unwrap();
+
+    let snapshot = repository.export_snapshot().await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+
+    let seasons = repository.get_seasons().await;
+    assert_eq!(1, seasons.len());
+    assert_eq!(75, seasons[0].standings[0].points);
+    assert_eq!(1, repository.get_trophies(Some(3)).await.len());
+    assert_eq!(Some(season.id), repository.get_ledger(Some(3)).await.last().unwrap().season_id);
+}
+
+fn kudos(from_user_id: u32, to_user_id: u32, points: u16) -> Kudos {
+    Kudos::new(from_user_id, KudosRequest { to_user_id, message: "Danke!".to_owned(), points })
+}
+
+const KUDOS_LIMITS: KudosLimits = KudosLimits { weekly_allowance: 30, recipient_cap: 20 };
+
+pub async fn test_give_kudos<R: Repository + Sync>(repository: &R) {
+    let mut receiver = repository.event_bus().subscribe();
+
+    let given = repository.give_kudos(kudos(1, 3, 15), KUDOS_LIMITS).await.unwrap();
+    assert_eq!(1, given.id);
+    assert_eq!(15, repository.get_user(3).await.unwrap().points);
+    assert_eq!(TransactionKind::Kudos, repository.get_ledger(Some(3)).await[0].kind);
+    assert_eq!(Some("Kudos from Flori".to_owned()), repository.get_ledger(Some(3)).await[0].reference);
+    let message = receiver.try_recv().unwrap();
+    assert_eq!("kudos", message.event.name());
+    assert_eq!(vec![1, 2], message.team_ids);
+
+    assert_eq!(1, repository.get_kudos(Some(1)).await.len());
+    assert_eq!(1, repository.get_kudos(Some(3)).await.len());
+    assert!(repository.get_kudos(Some(2)).await.is_empty());
+
+    let snapshot = repository.export_snapshot().await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+    assert_eq!("Danke!", repository.get_kudos(None).await[0].message);
+    assert_eq!(15, repository.get_user(3).await.unwrap().points);
+}
+
+pub async fn test_kudos_limits<R: Repository + Sync>(repository: &R) {
+    repository.give_kudos(kudos(1, 3, 15), KUDOS_LIMITS).await.unwrap();
+    assert!(repository.give_kudos(kudos(1, 3, 10), KUDOS_LIMITS).await.is_err());
+    repository.give_kudos(kudos(1, 2, 15), KUDOS_LIMITS).await.unwrap();
+    assert!(repository.give_kudos(kudos(1, 4, 1), KUDOS_LIMITS).await.is_err());
+
+    // The allowance is per giver
+    repository.give_kudos(kudos(2, 3, 5), KUDOS_LIMITS).await.unwrap();
+    assert!(repository.give_kudos(kudos(2, 4711, 5), KUDOS_LIMITS).await.is_err());
+    assert_eq!(3, repository.get_kudos(None).await.len());
+    assert_eq!(20, repository.get_user(3).await.unwrap().points);
+}
+
+fn task_requiring_verification() -> Task {
+    Task { id: 3, name: "Spülmaschine ausräumen".to_owned(), points: 52, enabled: true, requires_verification: true }
+}
+
+pub async fn test_claim_and_confirm_score<R: Repository + Sync>(repository: &R) {
+    repository.save_task(task_requiring_verification()).await.unwrap();
+    assert!(repository.score(3, 3).await.is_err());
+    assert!(repository.claim_score(3, 1).await.is_err());
+
+    let mut receiver = repository.event_bus().subscribe();
+    let claim = repository.claim_score(3, 3).await.unwrap();
+    assert_eq!(ClaimStatus::Pending, claim.status);
+    assert_eq!(52, claim.points);
+    assert_eq!(0, repository.get_user(3).await.unwrap().points);
+    let message = receiver.try_recv().unwrap();
+    assert_eq!("score_claimed", message.event.name());
+    assert_eq!(vec![2], message.team_ids);
+
+    let confirmed = repository.decide_score_claim(claim.id, true, "brutours.de".to_owned()).await.unwrap();
+    assert_eq!(ClaimStatus::Confirmed, confirmed.status);
+    assert_eq!(Some("brutours.de".to_owned()), confirmed.decided_by);
+    let user = repository.get_user(3).await.unwrap();
+    assert_eq!(52, user.points);
+    assert_eq!(1, user.scores.len());
+    assert_eq!(Some("brutours.de".to_owned()), repository.get_ledger(Some(3)).await[0].created_by);
+    assert!(repository.decide_score_claim(claim.id, false, "brutours.de".to_owned()).await.is_err());
+    assert!(repository.decide_score_claim(4711, true, "brutours.de".to_owned()).await.is_err());
+
+    let snapshot = repository.export_snapshot().await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+    assert!(repository.get_task(3).await.unwrap().requires_verification);
+    assert_eq!(ClaimStatus::Confirmed, repository.get_score_claims(Some(3)).await[0].status);
+    assert_eq!(52, repository.get_user(3).await.unwrap().points);
+}
+
+pub async fn test_reject_and_expire_score_claims<R: Repository + Sync>(repository: &R) {
+    repository.save_task(task_requiring_verification()).await.unwrap();
+    let rejected = repository.claim_score(3, 3).await.unwrap();
+    let expiring = repository.claim_score(3, 3).await.unwrap();
+
+    assert_eq!(ClaimStatus::Rejected, repository.decide_score_claim(rejected.id, false, "brutours.de".to_owned()).await.unwrap().status);
+    assert_eq!(0, repository.get_user(3).await.unwrap().points);
+    assert!(repository.get_ledger(Some(3)).await.is_empty());
+
+    assert!(repository.expire_score_claims(Utc::now() - Duration::hours(1)).await.is_empty());
+    let expired = repository.expire_score_claims(Utc::now() + Duration::seconds(1)).await;
+    assert_eq!(vec![expiring.id], expired.iter().map(|claim| claim.id).collect::<Vec<u32>>());
+    assert_eq!(ClaimStatus::Expired, repository.get_score_claims(None).await[1].status);
+    assert!(repository.decide_score_claim(expiring.id, true, "brutours.de".to_owned()).await.is_err());
+    assert_eq!(0, repository.get_user(3).await.unwrap().points);
+}
+
+// Franki challenges Michi to make more coffee, ending in an hour
+fn coffee_challenge() -> Challenge {
+    Challenge::new("dliwespf".to_owned(), ChallengeRequest { name: "Most coffees".to_owned(), challenger: Participant::User(3), opponent: Participant::User(2),
+        task_ids: vec![4], starts_at: Utc::now() - Duration::days(1), ends_at: Utc::now() + Duration::hours(1), stake: 10, award_trophy: true })
+}
+
+pub async fn test_challenge_lifecycle<R: Repository + Sync>(repository: &R) {
+    let challenge = repository.propose_challenge(coffee_challenge()).await.unwrap();
+    assert_eq!(ChallengeStatus::Proposed, challenge.status);
+    assert!(repository.propose_challenge(coffee_challenge()).await.is_err());
+
+    let mut receiver = repository.event_bus().subscribe();
+    assert_eq!(ChallengeStatus::Accepted, repository.decide_challenge(challenge.id, true, "brutours.de".to_owned()).await.unwrap().status);
+    let message = receiver.try_recv().unwrap();
+    assert_eq!("challenge_decided", message.event.name());
+    assert_eq!(vec![2], message.team_ids);
+    assert!(repository.decide_challenge(challenge.id, false, "brutours.de".to_owned()).await.is_err());
+
+    // Michi made a coffee for the demo data already
+    repository.score(3, 4).await.unwrap();
+    repository.score(3, 4).await.unwrap();
+    assert!(repository.resolve_challenges(Utc::now()).await.is_empty());
+
+    let resolved = repository.resolve_challenges(Utc::now() + Duration::hours(2)).await;
+    assert_eq!(1, resolved.len());
+    assert_eq!(Some(Participant::User(3)), resolved[0].winner);
+    assert_eq!(150, resolved[0].results[0].points);
+    assert_eq!(75, resolved[0].results[1].points);
+    assert_eq!(160, repository.get_user(3).await.unwrap().points);
+    assert_eq!(127, repository.get_user(2).await.unwrap().points);
+    assert_eq!(TransactionKind::Stake, repository.get_ledger(Some(2)).await.last().unwrap().kind);
+    assert_eq!(1, repository.get_trophies(Some(3)).await.len());
+    assert!(repository.resolve_challenges(Utc::now() + Duration::hours(2)).await.is_empty());
+
+    let snapshot = repository.export_snapshot().await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+    let challenges = repository.get_challenges().await;
+    assert_eq!(ChallengeStatus::Resolved, challenges[0].status);
+    assert_eq!(resolved[0].results, challenges[0].results);
+}
+
+pub async fn test_decline_challenge<R: Repository + Sync>(repository: &R) {
+    let challenge = repository.propose_challenge(coffee_challenge()).await.unwrap();
+    assert!(repository.decide_challenge(4711, false, "brutours.de".to_owned()).await.is_err());
+    assert_eq!(ChallengeStatus::Declined, repository.decide_challenge(challenge.id, false, "brutours.de".to_owned()).await.unwrap().status);
+    assert!(repository.decide_challenge(challenge.id, true, "brutours.de".to_owned()).await.is_err());
+
+    assert!(repository.resolve_challenges(Utc::now() + Duration::hours(2)).await.is_empty());
+    assert_eq!(137, repository.get_user(2).await.unwrap().points);
+}
+
+// Watering the flowers daily since yesterday, Topher's turn was due already and Franki's is next
+fn flowers_schedule() -> Schedule {
+    let request = ScheduleRequest { recurrence: "daily".to_owned(), rota: Rota::Users(vec![4, 3]), starts_at: Some(Utc::now() - Duration::days(1)), bonus: 5, malus: 10 };
+    Schedule::new(1, request).unwrap()
+}
+
+pub async fn test_save_and_remove_schedule<R: Repository + Sync>(repository: &R) {
+    assert!(repository.save_schedule(Schedule { task_id: 4711, ..flowers_schedule() }).await.is_err());
+    repository.save_schedule(flowers_schedule()).await.unwrap();
+    repository.save_schedule(Schedule { bonus: 7, ..flowers_schedule() }).await.unwrap();
+    let schedules = repository.get_schedules().await;
+    assert_eq!(1, schedules.len());
+    assert_eq!(7, schedules[0].bonus);
+    assert_eq!(Rota::Users(vec![4, 3]), schedules[0].rota);
+
+    let snapshot = repository.export_snapshot().await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+    assert_eq!(flowers_schedule().recurrence, repository.get_schedules().await[0].recurrence);
+
+    repository.remove_schedule(1).await.unwrap();
+    assert!(repository.get_schedules().await.is_empty());
+    assert!(repository.remove_schedule(1).await.is_err());
+}
+
+pub async fn test_settle_schedules<R: Repository + Sync>(repository: &R) {
+    repository.save_schedule(flowers_schedule()).await.unwrap();
+
+    let settled = repository.settle_schedules(Utc::now()).await;
+    assert_eq!(vec![(4, -10)], settled.iter().map(|transaction| (transaction.user_id, transaction.amount)).collect::<Vec<(u32, i64)>>());
+    assert_eq!(TransactionKind::Revoke, settled[0].kind);
+    assert_eq!(365, repository.get_user(4).await.unwrap().points);
+    assert!(repository.settle_schedules(Utc::now()).await.is_empty());
+
+    repository.score(3, 1).await.unwrap();
+    let settled = repository.settle_schedules(Utc::now() + Duration::days(1)).await;
+    assert_eq!(vec![(3, 5)], settled.iter().map(|transaction| (transaction.user_id, transaction.amount)).collect::<Vec<(u32, i64)>>());
+    assert_eq!(TransactionKind::Bonus, settled[0].kind);
+    assert_eq!(15, repository.get_user(3).await.unwrap().points);
+    assert!(repository.settle_schedules(Utc::now() + Duration::days(1)).await.is_empty());
+    assert_eq!(2, repository.get_schedules().await[0].settled_turns);
+}
+
+fn webhook(url: &str) -> Webhook {
+    Webhook { id: 0, url: url.to_owned(), event_types: vec!["score".to_owned()], enabled: true, secret: "s3cr3t".to_owned() }
+}
+
+fn dead_letter(id: u64) -> WebhookDelivery {
+    WebhookDelivery { id, webhook_id: 1, event_id: id, event_type: "score".to_owned(), status: DeliveryStatus::DeadLetter, attempts: 4,
+        last_response_status: Some(500), last_error: None, created_at: Utc::now(), updated_at: Utc::now() }
+}
+
+pub async fn test_add_and_remove_webhook<R: Repository + Sync>(repository: &R) {
+    assert_eq!(1, repository.add_webhook(webhook("https://example.org/hook")).await.unwrap().id);
+    assert_eq!(2, repository.add_webhook(webhook("https://example.org/other")).await.unwrap().id);
+
+    let snapshot = repository.export_snapshot().await.unwrap();
+    repository.import_snapshot(snapshot).await.unwrap();
+    let webhooks = repository.get_webhooks().await;
+    assert_eq!(2, webhooks.len());
+    assert_eq!("s3cr3t", webhooks[0].secret);
+    assert_eq!(vec!["score".to_owned()], webhooks[0].event_types);
+
+    repository.remove_webhook(1).await.unwrap();
+    assert_eq!(vec![2], repository.get_webhooks().await.iter().map(|w| w.id).collect::<Vec<u32>>());
+    assert!(repository.remove_webhook(1).await.is_err());
+}
+
+pub async fn test_dead_letters_are_bounded<R: Repository + Sync>(repository: &R) {
+    for id in 1..=DEAD_LETTER_LEN as u64 + 2 {
+        repository.add_dead_letter(dead_letter(id)).await.unwrap();
+    }
+
+    let dead_letters = repository.get_dead_letters().await;
+    assert_eq!(DEAD_LETTER_LEN, dead_letters.len());
+    assert_eq!(3, dead_letters[0].id);
+    assert_eq!(Some(500), dead_letters[0].last_response_status);
+    assert_eq!(DEAD_LETTER_LEN as u64 + 2, dead_letters.last().unwrap().id);
+}
@@ -0,0 +1,65 @@
+use futures::join;
+
+use crate::model::{Task, user::Team};
+
+use super::repository::Repository;
+
+pub fn demo_tasks() -> Vec<Task> {
+    vec![
+        Task { id: 1, name: "Blumen gießen".to_owned(), points: 10, enabled: true, requires_verification: false },
+        Task { id: 2, name: "Stunden abgeben".to_owned(), points: 30, enabled: false, requires_verification: false },
+        Task { id: 3, name: "Spülmaschine ausräumen".to_owned(), points: 52, enabled: true, requires_verification: false },
+        Task { id: 4, name: "Kaffee kochen".to_owned(), points: 75, enabled: true, requires_verification: false },
+    ]
+}
+
+// Adds the demo users, teams and scores, the demo tasks have to be present already
+pub async fn add_demo_data<R: Repository + Sync + ?Sized>(repository: &R) {
+    let flori = repository.create_and_add_user("roterkohl".to_owned(), "Flori".to_owned(), "Flori1234".to_owned(), true).await.unwrap();
+    let michi = repository.create_and_add_user("brutours.de".to_owned(), "Michi".to_owned(), "Michi1234".to_owned(), false).await.unwrap();
+    let franki = repository.create_and_add_user("dliwespf".to_owned(), "Franki".to_owned(), "Franki1234".to_owned(), false).await.unwrap();
+    let topheri = repository.create_and_add_user("topher".to_owned(), "Topher".to_owned(), "Topheri1234".to_owned(), true).await.unwrap();
+
+    let team_babes = Team::new(1, "Babes".to_owned(), flori.clone());
+    let mut team_church = Team::new(2, "Church".to_owned(), michi.clone());
+    team_church.add_user(franki.clone(), &michi.lock().unwrap()).unwrap();
+    team_church.add_user(flori.clone(), &michi.lock().unwrap()).unwrap();
+    team_church.add_user(topheri.clone(), &michi.lock().unwrap()).unwrap();
+    repository.add_team(team_babes).await;
+    repository.add_team(team_church).await;
+
+    let flori_id = flori.lock().unwrap().id;
+    let michi_id = michi.lock().unwrap().id;
+    let topheri_id = topheri.lock().unwrap().id;
+
+    join!(
+        repository.score(flori_id, 1),
+        repository.score(flori_id, 1),
+        repository.score(flori_id, 2),
+        repository.score(flori_id, 1),
+        repository.score(flori_id, 4),
+        repository.score(flori_id, 3),
+        repository.score(michi_id, 1),
+        repository.score(michi_id, 2),
+        repository.score(michi_id, 3),
+        repository.score(michi_id, 4),
+
+        repository.score(topheri_id, 4),
+        repository.score(topheri_id, 4),
+        repository.score(topheri_id, 4),
+        repository.score(topheri_id, 4),
+        repository.score(topheri_id, 4)
+    );
+}
+
+// Adds the demo tasks and the rest of the demo data, but only to a repository without users
+pub async fn seed_demo_data<R: Repository + Sync + ?Sized>(repository: &R) -> Result<(), String> {
+    if !repository.get_all_users().await.is_empty() {
+        return Err("The repository already contains users, demo data is only added to an empty one".to_owned());
+    }
+    for task in demo_tasks() {
+        repository.save_task(task).await?;
+    }
+    add_demo_data(repository).await;
+    Ok(())
+}
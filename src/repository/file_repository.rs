@@ -0,0 +1,420 @@
+use std::{fs, path::PathBuf, sync::{Arc, Mutex}};
+
+use chrono::{DateTime, Utc};
+use tracing::{error, info};
+
+use crate::{model::{User, Task, Session, session::LoginRequest, user::Team, health::DependencyHealth, setting::{SettingValue, SettingChange}, snapshot::{RepositorySnapshot, TeamRecord}, bulk_import::ImportedUser, history::ScoreFilter, report::ScoreAggregate, reward::{Reward, Redemption}, ledger::PointTransaction, season::Season, trophy::Trophy, event::LeaderboardEntry, kudos::{Kudos, KudosLimits}, verification::ScoreClaim, challenge::Challenge, schedule::Schedule, webhook::{Webhook, WebhookDelivery}}, resource::http::responder::MessageResponder};
+use crate::event::event_bus::EventBus;
+
+use super::{legacy_repository::LegacyRepository, repository::Repository};
+
+// Keeps everything in memory like the LegacyRepository, but writes a JSON snapshot to disk after every change
+pub struct FileRepository {
+    inner: LegacyRepository,
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl FileRepository {
+
+    // Starts empty if the file does not exist yet, like a new sqlite database
+    pub async fn open(path: &str) -> Result<FileRepository, String> {
+        let path = PathBuf::from(path);
+
+        let inner = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| format!("Unable to read '{}': {}", path.display(), err))?;
+            let snapshot: RepositorySnapshot = serde_json::from_str(&content).map_err(|err| format!("Unable to parse '{}': {}", path.display(), err))?;
+            LegacyRepository::restore(snapshot)?
+        } else {
+            info!(path = %path.display(), "Data file does not exist, starting empty");
+            LegacyRepository::empty()
+        };
+
+        let repository = FileRepository { inner, path, write_lock: Mutex::new(()) };
+        repository.persist()?;
+
+        Ok(repository)
+    }
+
+    // Writes to a temporary file first, so a crash while writing does not leave a truncated data file behind
+    fn persist(&self) -> Result<(), String> {
+        let _guard = self.write_lock.lock().unwrap();
+
+        let content = serde_json::to_string_pretty(&self.inner.snapshot()).map_err(|err| err.to_string())?;
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, content).map_err(|err| format!("Unable to write '{}': {}", temp_path.display(), err))?;
+        fs::rename(&temp_path, &self.path).map_err(|err| format!("Unable to replace '{}': {}", self.path.display(), err))?;
+
+        Ok(())
+    }
+
+    fn persist_logged(&self) {
+        if let Err(err_msg) = self.persist() {
+            error!(error = %err_msg, "Unable to persist data file");
+        }
+    }
+}
+
+#[async_trait]
+impl Repository for FileRepository {
+    async fn get_user<'a>(&'a self, id: u32) -> Option<User> {
+        self.inner.get_user(id).await
+    }
+
+    async fn find_user_by_username<'a>(&'a self, username: &String) -> Option<Arc<Mutex<User>>> {
+        self.inner.find_user_by_username(username).await
+    }
+
+    async fn find_user_by_username_const<'a>(&'a self, username: &String) -> Option<User> {
+        self.inner.find_user_by_username_const(username).await
+    }
+
+    async fn get_all_users<'a>(&'a self) -> Vec<User> {
+        self.inner.get_all_users().await
+    }
+
+    async fn get_task<'a>(&'a self, id: u32) -> Option<Task> {
+        self.inner.get_task(id).await
+    }
+
+    async fn get_all_tasks<'a>(&'a self) -> Vec<Task> {
+        self.inner.get_all_tasks().await
+    }
+
+    async fn get_session<'a>(&'a self, session_id: &String) -> Option<Session> {
+        self.inner.get_session(session_id).await
+    }
+
+    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u64, String> {
+        let result = self.inner.score(user_id, task_id).await;
+        if result.is_ok() {
+            self.persist_logged();
+        }
+        result
+    }
+
+    async fn create_and_add_user<'a>(&'a self, username: String, display_name: String, password: String, is_admin: bool) -> Result<Arc<Mutex<User>>, String> {
+        let result = self.inner.create_and_add_user(username, display_name, password, is_admin).await;
+        if result.is_ok() {
+            self.persist_logged();
+        }
+        result
+    }
+
+    async fn add_team<'a>(&'a self, team: Team) -> Option<u32> {
+        let result = self.inner.add_team(team).await;
+        if result.is_some() {
+            self.persist_logged();
+        }
+        result
+    }
+
+    async fn add_user_to_team<'a>(&'a self, team_name: &String, user_id: u32, manager: User) -> Result<(), String> {
+        let result = self.inner.add_user_to_team(team_name, user_id, manager).await;
+        if result.is_ok() {
+            self.persist_logged();
+        }
+        result
+    }
+
+    async fn add_user<'a>(&'a self, session: &Session, user: User) -> MessageResponder<u32> {
+        let result = self.inner.add_user(session, user).await;
+        if result.content.is_some() {
+            self.persist_logged();
+        }
+        result
+    }
+
+    async fn login<'a>(&'a self, login_request: LoginRequest) -> Result<Session, String> {
+        self.inner.login(login_request).await
+    }
+
+    async fn logout(&self, session_id: &String) -> Result<(), String> {
+        self.inner.logout(session_id).await
+    }
+
+    async fn count_sessions(&self, started_after: DateTime<Utc>) -> usize {
+        self.inner.count_sessions(started_after).await
+    }
+
+    fn event_bus(&self) -> &EventBus {
+        self.inner.event_bus()
+    }
+
+    async fn check_health(&self) -> Vec<DependencyHealth> {
+        match self.persist() {
+            Ok(_) => vec![DependencyHealth::up("data_file", Some(self.path.display().to_string()))],
+            Err(err_msg) => vec![DependencyHealth::down("data_file", err_msg)],
+        }
+    }
+
+    async fn get_settings(&self) -> Vec<(String, SettingValue)> {
+        self.inner.get_settings().await
+    }
+
+    async fn save_setting(&self, change: SettingChange) -> Result<(), String> {
+        self.inner.save_setting(change).await?;
+        self.persist()
+    }
+
+    async fn get_setting_history(&self, key: &String) -> Vec<SettingChange> {
+        self.inner.get_setting_history(key).await
+    }
+
+    async fn set_password(&self, user_id: u32, password: String) -> Result<(), String> {
+        self.inner.set_password(user_id, password).await?;
+        self.persist()
+    }
+
+    async fn set_user_enabled(&self, user_id: u32, enabled: bool) -> Result<(), String> {
+        self.inner.set_user_enabled(user_id, enabled).await?;
+        self.persist()
+    }
+
+    async fn save_task(&self, task: Task) -> Result<Task, String> {
+        let task = self.inner.save_task(task).await?;
+        self.persist()?;
+        Ok(task)
+    }
+
+    async fn export_snapshot(&self) -> Result<RepositorySnapshot, String> {
+        self.inner.export_snapshot().await
+    }
+
+    async fn import_snapshot(&self, snapshot: RepositorySnapshot) -> Result<(), String> {
+        self.inner.import_snapshot(snapshot).await?;
+        self.persist()
+    }
+
+    async fn add_in_bulk(&self, users: Vec<ImportedUser>, tasks: Vec<Task>) -> Result<(), String> {
+        self.inner.add_in_bulk(users, tasks).await?;
+        self.persist()
+    }
+
+    async fn get_all_teams(&self) -> Vec<TeamRecord> {
+        self.inner.get_all_teams().await
+    }
+
+    async fn aggregate_scores(&self, filter: &ScoreFilter) -> Result<Vec<ScoreAggregate>, String> {
+        self.inner.aggregate_scores(filter).await
+    }
+    async fn get_all_rewards(&self) -> Vec<Reward> {
+        self.inner.get_all_rewards().await
+    }
+
+    async fn get_reward(&self, id: u32) -> Option<Reward> {
+        self.inner.get_reward(id).await
+    }
+
+    async fn save_reward(&self, reward: Reward) -> Result<Reward, String> {
+        let reward = self.inner.save_reward(reward).await?;
+        self.persist()?;
+        Ok(reward)
+    }
+
+    async fn redeem(&self, user_id: u32, reward_id: u32) -> Result<Redemption, String> {
+        let redemption = self.inner.redeem(user_id, reward_id).await?;
+        self.persist()?;
+        Ok(redemption)
+    }
+
+    async fn get_redemptions(&self, user_id: Option<u32>) -> Vec<Redemption> {
+        self.inner.get_redemptions(user_id).await
+    }
+
+    async fn decide_redemption(&self, redemption_id: u32, approved: bool, decided_by: String) -> Result<Redemption, String> {
+        let redemption = self.inner.decide_redemption(redemption_id, approved, decided_by).await?;
+        self.persist()?;
+        Ok(redemption)
+    }
+
+    async fn get_ledger(&self, user_id: Option<u32>) -> Vec<PointTransaction> {
+        self.inner.get_ledger(user_id).await
+    }
+
+    async fn add_transaction(&self, transaction: PointTransaction) -> Result<PointTransaction, String> {
+        let transaction = self.inner.add_transaction(transaction).await?;
+        self.persist()?;
+        Ok(transaction)
+    }
+
+    async fn get_seasons(&self) -> Vec<Season> {
+        self.inner.get_seasons().await
+    }
+
+    async fn start_season(&self, season: Season) -> Result<Season, String> {
+        let season = self.inner.start_season(season).await?;
+        self.persist()?;
+        Ok(season)
+    }
+
+    async fn end_season(&self, id: u32) -> Result<Season, String> {
+        let season = self.inner.end_season(id).await?;
+        self.persist()?;
+        Ok(season)
+    }
+
+    async fn end_due_seasons(&self, now: DateTime<Utc>) -> Vec<Season> {
+        let ended = self.inner.end_due_seasons(now).await;
+        if !ended.is_empty() {
+            self.persist_logged();
+        }
+        ended
+    }
+
+    async fn get_season_leaderboard(&self, id: u32) -> Option<Vec<LeaderboardEntry>> {
+        self.inner.get_season_leaderboard(id).await
+    }
+
+    async fn get_trophies(&self, user_id: Option<u32>) -> Vec<Trophy> {
+        self.inner.get_trophies(user_id).await
+    }
+
+    async fn give_kudos(&self, kudos: Kudos, limits: KudosLimits) -> Result<Kudos, String> {
+        let kudos = self.inner.give_kudos(kudos, limits).await?;
+        self.persist()?;
+        Ok(kudos)
+    }
+
+    async fn get_kudos(&self, user_id: Option<u32>) -> Vec<Kudos> {
+        self.inner.get_kudos(user_id).await
+    }
+
+    async fn claim_score(&self, user_id: u32, task_id: u32) -> Result<ScoreClaim, String> {
+        let claim = self.inner.claim_score(user_id, task_id).await?;
+        self.persist()?;
+        Ok(claim)
+    }
+
+    async fn get_score_claims(&self, user_id: Option<u32>) -> Vec<ScoreClaim> {
+        self.inner.get_score_claims(user_id).await
+    }
+
+    async fn decide_score_claim(&self, claim_id: u32, confirmed: bool, decided_by: String) -> Result<ScoreClaim, String> {
+        let claim = self.inner.decide_score_claim(claim_id, confirmed, decided_by).await?;
+        self.persist()?;
+        Ok(claim)
+    }
+
+    async fn expire_score_claims(&self, cutoff: DateTime<Utc>) -> Vec<ScoreClaim> {
+        let expired = self.inner.expire_score_claims(cutoff).await;
+        if !expired.is_empty() {
+            self.persist_logged();
+        }
+        expired
+    }
+
+    async fn propose_challenge(&self, challenge: Challenge) -> Result<Challenge, String> {
+        let challenge = self.inner.propose_challenge(challenge).await?;
+        self.persist()?;
+        Ok(challenge)
+    }
+
+    async fn get_challenges(&self) -> Vec<Challenge> {
+        self.inner.get_challenges().await
+    }
+
+    async fn decide_challenge(&self, challenge_id: u32, accepted: bool, decided_by: String) -> Result<Challenge, String> {
+        let challenge = self.inner.decide_challenge(challenge_id, accepted, decided_by).await?;
+        self.persist()?;
+        Ok(challenge)
+    }
+
+    async fn resolve_challenges(&self, now: DateTime<Utc>) -> Vec<Challenge> {
+        let resolved = self.inner.resolve_challenges(now).await;
+        if !resolved.is_empty() {
+            self.persist_logged();
+        }
+        resolved
+    }
+
+    async fn get_schedules(&self) -> Vec<Schedule> {
+        self.inner.get_schedules().await
+    }
+
+    async fn save_schedule(&self, schedule: Schedule) -> Result<Schedule, String> {
+        let schedule = self.inner.save_schedule(schedule).await?;
+        self.persist()?;
+        Ok(schedule)
+    }
+
+    async fn remove_schedule(&self, task_id: u32) -> Result<(), String> {
+        self.inner.remove_schedule(task_id).await?;
+        self.persist()
+    }
+
+    async fn settle_schedules(&self, now: DateTime<Utc>) -> Vec<PointTransaction> {
+        let settled = self.inner.settle_schedules(now).await;
+        if !settled.is_empty() {
+            self.persist_logged();
+        }
+        settled
+    }
+
+    async fn get_webhooks(&self) -> Vec<Webhook> {
+        self.inner.get_webhooks().await
+    }
+
+    async fn add_webhook(&self, webhook: Webhook) -> Result<Webhook, String> {
+        let webhook = self.inner.add_webhook(webhook).await?;
+        self.persist()?;
+        Ok(webhook)
+    }
+
+    async fn remove_webhook(&self, id: u32) -> Result<(), String> {
+        self.inner.remove_webhook(id).await?;
+        self.persist()
+    }
+
+    async fn get_dead_letters(&self) -> Vec<WebhookDelivery> {
+        self.inner.get_dead_letters().await
+    }
+
+    async fn add_dead_letter(&self, delivery: WebhookDelivery) -> Result<(), String> {
+        self.inner.add_dead_letter(delivery).await?;
+        self.persist()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rocket::futures::executor::block_on;
+
+    use crate::repository::conformance::repository_conformance_tests;
+    use crate::repository::demo_data::seed_demo_data;
+    use crate::repository::repository::Repository;
+
+    use super::FileRepository;
+
+    // Every test writes to a file of its own, starting over with the demo data
+    async fn fixture(test_name: &str) -> FileRepository {
+        let path = env::temp_dir().join(format!("taskscore-{}-{}.json", test_name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let repository = FileRepository::open(path.to_str().unwrap()).await.unwrap();
+        seed_demo_data(&repository).await.unwrap();
+        repository
+    }
+
+    repository_conformance_tests!(fixture);
+
+    #[test]
+    fn test_changes_survive_reopening() {
+        let path = env::temp_dir().join(format!("taskscore-test-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let repository = block_on(FileRepository::open(path)).unwrap();
+        assert!(block_on(repository.get_all_users()).is_empty());
+        block_on(seed_demo_data(&repository)).unwrap();
+        let points = block_on(repository.score(4, 4)).unwrap();
+        drop(repository);
+
+        let reopened = block_on(FileRepository::open(path)).unwrap();
+        assert_eq!(points, block_on(reopened.get_user(4)).unwrap().points);
+        assert_eq!(2, block_on(reopened.get_user(2)).unwrap().id);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,18 +1,32 @@
+use std::{collections::{HashSet, VecDeque}, sync::{Mutex, Arc}};
 
-use std::{sync::{Mutex, Arc}};
-
+use chrono::{DateTime, Utc};
 use rocket::{fairing::Result, http::Status};
-use futures::join;
+use tracing::error;
 
-use crate::{model::{Session, Task, User, session::{LoginRequest}, user::{Team}}, resource::http::responder::MessageResponder};
+use crate::{model::{Session, Task, User, session::{LoginRequest}, user::{Team}, event::{ActivityEvent, LeaderboardEntry}, health::DependencyHealth, setting::{SettingValue, SettingChange}, snapshot::{RepositorySnapshot, UserRecord, TeamRecord, WebhookRecord, SNAPSHOT_VERSION}, bulk_import::ImportedUser, history::ScoreFilter, report::ScoreAggregate, reward::{Reward, Redemption}, ledger::{PointTransaction, TransactionKind, Balance, lifetime_points}, season::{Season, standings}, trophy::Trophy, kudos::{Kudos, KudosLimits}, verification::ScoreClaim, challenge::Challenge, schedule::Schedule, webhook::{Webhook, WebhookDelivery, DEAD_LETTER_LEN}}, resource::http::responder::MessageResponder};
+use crate::event::event_bus::EventBus;
 
-use super::repository::Repository;
+use super::{repository::Repository, demo_data::{demo_tasks, add_demo_data}};
 
 pub struct LegacyRepository {
     users: Arc<Mutex<Vec<Arc<Mutex<User>>>>>,
     sessions: Arc<Mutex<Vec<Arc<Mutex<Session>>>>>,
     tasks: Arc<Mutex<Vec<Task>>>,
     teams: Arc<Mutex<Vec<Arc<Mutex<Team>>>>>,
+    event_bus: EventBus,
+    setting_changes: Arc<Mutex<Vec<SettingChange>>>,
+    rewards: Arc<Mutex<Vec<Reward>>>,
+    redemptions: Arc<Mutex<Vec<Redemption>>>,
+    ledger: Arc<Mutex<Vec<PointTransaction>>>,
+    seasons: Arc<Mutex<Vec<Season>>>,
+    trophies: Arc<Mutex<Vec<Trophy>>>,
+    kudos: Arc<Mutex<Vec<Kudos>>>,
+    score_claims: Arc<Mutex<Vec<ScoreClaim>>>,
+    challenges: Arc<Mutex<Vec<Challenge>>>,
+    schedules: Arc<Mutex<Vec<Schedule>>>,
+    webhooks: Arc<Mutex<Vec<Webhook>>>,
+    dead_letters: Arc<Mutex<VecDeque<WebhookDelivery>>>,
 }
 
 #[async_trait]
@@ -52,23 +66,46 @@ impl Repository for LegacyRepository {
         self.find_session(session_id).and_then(|s| Some(s.lock().unwrap().clone()))
     }
 
-    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u16, String> {
-        let users_guard = self.users.lock().unwrap();
-        let user_opt = users_guard.iter().find(|user| user.lock().unwrap().id == user_id);
-        let user_mutex = user_opt.ok_or("User does not exist")?;
-        let mut user = user_mutex.lock().unwrap();
+    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u64, String> {
+        let (total_points, score_event, leaderboard_before, leaderboard_after) = {
+            let users_guard = self.users.lock().unwrap();
+            let leaderboard_before = LegacyRepository::leaderboard(&users_guard);
 
-        let locked_tasks = self.tasks.lock().unwrap();
-        let task_opt = locked_tasks.iter().find(|task| task.id == task_id);
-        let task = task_opt.ok_or("Task does not exist")?;
+            let user_opt = users_guard.iter().find(|user| user.lock().unwrap().id == user_id);
+            let user_mutex = user_opt.ok_or("User does not exist")?;
+            let mut user = user_mutex.lock().unwrap();
 
-        if !task.enabled {
-            return Err("Task is not enabled".to_owned());
-        }
+            let locked_tasks = self.tasks.lock().unwrap();
+            let task_opt = locked_tasks.iter().find(|task| task.id == task_id);
+            let task = task_opt.ok_or("Task does not exist")?;
+
+            if !task.enabled {
+                return Err("Task is not enabled".to_owned());
+            }
+            if task.requires_verification {
+                return Err("Task requires verification".to_owned());
+            }
+
+            let score = user.score_task(task.clone());
+            let season_id = self.active_season_id();
+            let mut ledger = self.ledger.lock().unwrap();
+            LegacyRepository::book(&mut ledger, PointTransaction { season_id, ..PointTransaction::for_score(user_id, &score) });
+            user.points = lifetime_points(user_id, &ledger);
+
+            let score_event = ActivityEvent::Score { user_id, display_name: user.display_name.clone(), task_id, task_name: task.name.clone(),
+                points: task.points, total_points: user.points };
+            let total_points = user.points;
+            drop(user);
 
-        user.score_task(task.clone());
+            (total_points, score_event, leaderboard_before, LegacyRepository::leaderboard(&users_guard))
+        };
+
+        self.event_bus.publish(self.team_ids_of(user_id), score_event);
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
 
-        Ok(user.points)
+        Ok(total_points)
     }
 
     async fn create_and_add_user<'a>(&'a self, username: String, display_name: String, password: String, is_admin: bool) -> Result<Arc<Mutex<User>>, String> {
@@ -95,7 +132,10 @@ impl Repository for LegacyRepository {
         let user = users_locked.iter().find(|u| u.lock().unwrap().id == user_id).ok_or(format!("User with id {} does not exist", user_id))?;
         let mut team_locked = team.lock().unwrap();
         
-        team_locked.add_user(user.clone(), &manager)
+        team_locked.add_user(user.clone(), &manager)?;
+
+        self.event_bus.publish(vec![team_locked.id], ActivityEvent::TeamMemberAdded { team_id: team_locked.id, name: team_locked.name.clone(), user_id });
+        Ok(())
     }
 
     async fn add_user<'a>(&'a self, session: &Session, user: User) -> MessageResponder<u32> {
@@ -116,6 +156,10 @@ impl Repository for LegacyRepository {
         let user_opt = self.find_user_by_username(&username).await;
         let user = user_opt.ok_or("User does not exist")?;
 
+        if !user.lock().unwrap().enabled {
+            return Err("User is deactivated".to_owned());
+        }
+
         if user.lock().unwrap().verify_password(&login_request.password) {
             let session = Session::new(Arc::clone(&user));
 
@@ -135,294 +179,793 @@ impl Repository for LegacyRepository {
 
         Ok(())
     }
-}
 
-impl LegacyRepository {
+    async fn count_sessions(&self, started_after: DateTime<Utc>) -> usize {
+        self.sessions.lock().unwrap().iter().filter(|session| session.lock().unwrap().started > started_after).count()
+    }
 
-    #[allow(unused)]
-    pub async fn init_repository() -> LegacyRepository {
+    fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    // Everything is kept in memory, so there is nothing that could be unavailable
+    async fn check_health(&self) -> Vec<DependencyHealth> {
+        vec![]
+    }
+
+    // The current value of a setting is the one it has been changed to last
+    async fn get_settings(&self) -> Vec<(String, SettingValue)> {
+        let mut settings: Vec<(String, SettingValue)> = vec![];
+        for change in self.setting_changes.lock().unwrap().iter() {
+            settings.retain(|(key, _)| key != &change.key);
+            settings.push((change.key.clone(), change.new_value.clone()));
+        }
+
+        settings
+    }
+
+    async fn save_setting(&self, change: SettingChange) -> Result<(), String> {
+        self.setting_changes.lock().unwrap().push(change);
+        Ok(())
+    }
+
+    async fn get_setting_history(&self, key: &String) -> Vec<SettingChange> {
+        self.setting_changes.lock().unwrap().iter().rev().filter(|change| &change.key == key).cloned().collect()
+    }
+
+    async fn set_password(&self, user_id: u32, password: String) -> Result<(), String> {
+        let user = self.get_user_unlocked(user_id).ok_or(format!("User with id {} does not exist", user_id))?;
+        user.lock().unwrap().set_password(password);
+        Ok(())
+    }
 
-        let users = vec![];
+    async fn set_user_enabled(&self, user_id: u32, enabled: bool) -> Result<(), String> {
+        let user = self.get_user_unlocked(user_id).ok_or(format!("User with id {} does not exist", user_id))?;
+        user.lock().unwrap().enabled = enabled;
 
-        let sessions = vec![];
+        if !enabled {
+            self.sessions.lock().unwrap().retain(|session| session.lock().unwrap().user.lock().unwrap().id != user_id);
+        }
+        Ok(())
+    }
 
-        let tasks = vec![
-            Task { id: 1, name: "Blumen gießen".to_owned(), points: 10, enabled: true},
-            Task { id: 2, name: "Stunden abgeben".to_owned(), points: 30, enabled: false},
-            Task { id: 3, name: "Spülmaschine ausräumen".to_owned(), points: 52, enabled: true},
-            Task { id: 4, name: "Kaffee kochen".to_owned(), points: 75, enabled: true},
-        ];
+    async fn save_task(&self, mut task: Task) -> Result<Task, String> {
+        let created = {
+            let mut tasks = self.tasks.lock().unwrap();
+            if task.id == 0 {
+                task.id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+            }
 
-        let repository = LegacyRepository {
-            users: Arc::new(Mutex::new(users)),
-            sessions: Arc::new(Mutex::new(sessions)),
-            tasks: Arc::new(Mutex::new(tasks)),
-            teams: Arc::new(Mutex::new(vec![]))
+            match tasks.iter().position(|t| t.id == task.id) {
+                Some(index) => { tasks[index] = task.clone(); false },
+                None => { tasks.push(task.clone()); true }
+            }
         };
+        self.event_bus.publish(vec![], ActivityEvent::task_saved(&task, created));
+        Ok(task)
+    }
+
+    async fn export_snapshot(&self) -> Result<RepositorySnapshot, String> {
+        Ok(self.snapshot())
+    }
+
+    async fn import_snapshot(&self, snapshot: RepositorySnapshot) -> Result<(), String> {
+        let restored = LegacyRepository::restore(snapshot)?;
+
+        *self.users.lock().unwrap() = std::mem::take(&mut *restored.users.lock().unwrap());
+        *self.tasks.lock().unwrap() = std::mem::take(&mut *restored.tasks.lock().unwrap());
+        *self.teams.lock().unwrap() = std::mem::take(&mut *restored.teams.lock().unwrap());
+        *self.setting_changes.lock().unwrap() = std::mem::take(&mut *restored.setting_changes.lock().unwrap());
+        *self.rewards.lock().unwrap() = std::mem::take(&mut *restored.rewards.lock().unwrap());
+        *self.redemptions.lock().unwrap() = std::mem::take(&mut *restored.redemptions.lock().unwrap());
+        *self.ledger.lock().unwrap() = std::mem::take(&mut *restored.ledger.lock().unwrap());
+        *self.seasons.lock().unwrap() = std::mem::take(&mut *restored.seasons.lock().unwrap());
+        *self.trophies.lock().unwrap() = std::mem::take(&mut *restored.trophies.lock().unwrap());
+        *self.kudos.lock().unwrap() = std::mem::take(&mut *restored.kudos.lock().unwrap());
+        *self.score_claims.lock().unwrap() = std::mem::take(&mut *restored.score_claims.lock().unwrap());
+        *self.challenges.lock().unwrap() = std::mem::take(&mut *restored.challenges.lock().unwrap());
+        *self.schedules.lock().unwrap() = std::mem::take(&mut *restored.schedules.lock().unwrap());
+        *self.webhooks.lock().unwrap() = std::mem::take(&mut *restored.webhooks.lock().unwrap());
+        *self.dead_letters.lock().unwrap() = std::mem::take(&mut *restored.dead_letters.lock().unwrap());
+        self.sessions.lock().unwrap().clear();
+
+        Ok(())
+    }
 
-        let flori = repository.create_and_add_user("roterkohl".to_owned(), "Flori".to_owned(), "Flori1234".to_owned(), true).await.unwrap();
-        let michi = repository.create_and_add_user("brutours.de".to_owned(), "Michi".to_owned(), "Michi1234".to_owned(), false).await.unwrap();
-        let franki = repository.create_and_add_user("dliwespf".to_owned(), "Franki".to_owned(), "Franki1234".to_owned(), false).await.unwrap();
-        let topheri = repository.create_and_add_user("topher".to_owned(), "Topher".to_owned(), "Topheri1234".to_owned(), true).await.unwrap();
-
-        let mut team_babes = Team::new(1, "Babes".to_owned(), flori.clone());
-        let mut team_church = Team::new(2, "Church".to_owned(), michi.clone());
-        team_church.add_user(franki.clone(), &michi.lock().unwrap());
-        team_church.add_user(flori.clone(), &michi.lock().unwrap());
-        team_church.add_user(topheri.clone(), &michi.lock().unwrap());
-
-        let flori_id = flori.lock().unwrap().id;
-        let michi_id = michi.lock().unwrap().id;
-        let franki_id = franki.lock().unwrap().id;
-        let topheri_id = topheri.lock().unwrap().id;
-
-        join!(
-            repository.score(flori_id, 1),
-            repository.score(flori_id, 1),
-            repository.score(flori_id, 2),
-            repository.score(flori_id, 1),
-            repository.score(flori_id, 4),
-            repository.score(flori_id, 3),
-            repository.score(michi_id, 1),
-            repository.score(michi_id, 2),
-            repository.score(michi_id, 3),
-            repository.score(michi_id, 4),
-
-            repository.score(topheri_id, 4),
-            repository.score(topheri_id, 4),
-            repository.score(topheri_id, 4),
-            repository.score(topheri_id, 4),
-            repository.score(topheri_id, 4)
-        );
+    async fn add_in_bulk(&self, users: Vec<ImportedUser>, tasks: Vec<Task>) -> Result<(), String> {
+        let teams_locked = self.teams.lock().unwrap();
+        let mut users_locked = self.users.lock().unwrap();
+        let mut tasks_locked = self.tasks.lock().unwrap();
+
+        // Everything is checked before the first change, so a failure leaves the repository untouched
+        let mut usernames: HashSet<String> = users_locked.iter().map(|user| user.lock().unwrap().username.clone()).collect();
+        for ImportedUser { user, team } in users.iter() {
+            if !usernames.insert(user.username.clone()) {
+                return Err(format!("Username '{}' is not available", user.username));
+            }
+            if let Some(team_name) = team.as_ref().filter(|name| !teams_locked.iter().any(|t| t.lock().unwrap().name == **name)) {
+                return Err(format!("Team with name '{}'does not exist", team_name));
+            }
+        }
 
-        repository
+        let mut events = vec![];
+        for ImportedUser { mut user, team } in users {
+            user.id = users_locked.iter().map(|u| u.lock().unwrap().id).max().unwrap_or(0) + 1;
+            events.push((vec![], ActivityEvent::UserCreated { user_id: user.id, username: user.username.clone(), display_name: user.display_name.clone() }));
+            let user_id = user.id;
+            let new_user = Arc::new(Mutex::new(user));
+            users_locked.push(new_user.clone());
+
+            if let Some(team) = team.and_then(|name| teams_locked.iter().find(|t| t.lock().unwrap().name == name)) {
+                let mut team_locked = team.lock().unwrap();
+                team_locked.member_ids.insert(user_id);
+                team_locked.members.push(new_user);
+                events.push((vec![team_locked.id], ActivityEvent::TeamMemberAdded { team_id: team_locked.id, name: team_locked.name.clone(), user_id }));
+            }
+        }
+        for mut task in tasks {
+            task.id = tasks_locked.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+            events.push((vec![], ActivityEvent::task_saved(&task, true)));
+            tasks_locked.push(task);
+        }
+
+        events.into_iter().for_each(|(team_ids, event)| self.event_bus.publish(team_ids, event));
+        Ok(())
+    }
+    async fn get_all_teams(&self) -> Vec<TeamRecord> {
+        self.teams.lock().unwrap().iter().map(|team| TeamRecord::from(&*team.lock().unwrap())).collect()
     }
 
-    fn get_user_unlocked<'a>(&'a self, id: u32) -> Option<Arc<Mutex<User>>> {
+    async fn aggregate_scores(&self, filter: &ScoreFilter) -> Result<Vec<ScoreAggregate>, String> {
+        let mut users: Vec<User> = self.users.lock().unwrap().iter().map(|user| user.lock().unwrap().clone()).collect();
+        users.sort_by_key(|user| user.id);
+        Ok(users.iter().flat_map(|user| ScoreAggregate::of_user(user.id, &user.display_name, &user.scores, filter)).collect())
+    }
+    async fn get_all_rewards(&self) -> Vec<Reward> {
+        self.rewards.lock().unwrap().clone()
+    }
 
-        let users_guard = self.users.lock().unwrap();
-        users_guard.iter().find(|user| user.lock().unwrap().id == id).and_then(|user| Some(user.clone()))
+    async fn get_reward(&self, id: u32) -> Option<Reward> {
+        self.rewards.lock().unwrap().iter().find(|reward| reward.id == id).cloned()
+    }
+
+    async fn save_reward(&self, mut reward: Reward) -> Result<Reward, String> {
+        let mut rewards = self.rewards.lock().unwrap();
+        if rewards.iter().any(|r| r.name == reward.name && r.id != reward.id) {
+            return Err(format!("Reward '{}' already exists", reward.name));
+        }
+        if reward.id == 0 {
+            reward.id = rewards.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        }
 
+        match rewards.iter().position(|r| r.id == reward.id) {
+            Some(index) => rewards[index] = reward.clone(),
+            None => rewards.push(reward.clone())
+        }
+        Ok(reward)
     }
-    
-    fn find_session<'a>(&'a self, session_id: &String) -> Option<Arc<Mutex<Session>>> {
-        self.sessions.lock().unwrap().iter().find(|session| session.lock().unwrap().id.eq(session_id))
-            .and_then(|f| Some(f.clone()))
+
+    async fn redeem(&self, user_id: u32, reward_id: u32) -> Result<Redemption, String> {
+        let (redemption, display_name) = {
+            let user = self.get_user_unlocked(user_id).ok_or(format!("User with id {} does not exist", user_id))?;
+            let user = user.lock().unwrap();
+            let mut rewards = self.rewards.lock().unwrap();
+            let mut redemptions = self.redemptions.lock().unwrap();
+            let mut ledger = self.ledger.lock().unwrap();
+
+            let reward = rewards.iter_mut().find(|reward| reward.id == reward_id).ok_or(format!("Reward with id {} does not exist", reward_id))?;
+            reward.check_redeemable(Balance::create(user_id, &ledger).balance)?;
+            reward.stock = reward.stock.map(|stock| stock - 1);
+
+            let redemption = Redemption::new(redemptions.iter().map(|r| r.id).max().unwrap_or(0) + 1, reward, user_id);
+            redemptions.push(redemption.clone());
+            LegacyRepository::book(&mut ledger, PointTransaction::new(user_id, TransactionKind::Redeem, -(redemption.cost as i64), Some(redemption.reward_name.clone()), None));
+            (redemption, user.display_name.clone())
+        };
+
+        self.event_bus.publish(self.team_ids_of(user_id), ActivityEvent::RewardRedeemed { redemption_id: redemption.id, user_id, display_name,
+            reward_id, reward_name: redemption.reward_name.clone(), cost: redemption.cost });
+        Ok(redemption)
     }
 
-    fn add_user_private<'a>(&'a self, mut user: User) -> Result<Arc<Mutex<User>>, String> {
-        let mut users_vec = self.users.lock().unwrap();
+    async fn get_redemptions(&self, user_id: Option<u32>) -> Vec<Redemption> {
+        self.redemptions.lock().unwrap().iter().filter(|redemption| user_id.map_or(true, |user_id| redemption.user_id == user_id)).cloned().collect()
+    }
 
-        if users_vec.iter().any(|u| u.lock().unwrap().username.eq(&user.username)) {
-            return Err("Username is not available".to_owned());
+    async fn decide_redemption(&self, redemption_id: u32, approved: bool, decided_by: String) -> Result<Redemption, String> {
+        let redemption = {
+            let mut rewards = self.rewards.lock().unwrap();
+            let mut redemptions = self.redemptions.lock().unwrap();
+            let redemption = redemptions.iter_mut().find(|r| r.id == redemption_id).ok_or(format!("Redemption with id {} does not exist", redemption_id))?;
+            redemption.decide(approved, decided_by.clone())?;
+
+            // A rejected redemption gives back the points and the item
+            if !approved {
+                if let Some(reward) = rewards.iter_mut().find(|reward| reward.id == redemption.reward_id) {
+                    reward.stock = reward.stock.map(|stock| stock + 1);
+                }
+                let refund = PointTransaction::new(redemption.user_id, TransactionKind::Refund, redemption.cost as i64, Some(redemption.reward_name.clone()), Some(decided_by));
+                LegacyRepository::book(&mut self.ledger.lock().unwrap(), refund);
+            }
+            redemption.clone()
+        };
+
+        self.event_bus.publish(self.team_ids_of(redemption.user_id), ActivityEvent::RedemptionDecided { redemption_id, user_id: redemption.user_id,
+            reward_name: redemption.reward_name.clone(), status: redemption.status });
+        Ok(redemption)
+    }
+
+    async fn get_ledger(&self, user_id: Option<u32>) -> Vec<PointTransaction> {
+        self.ledger.lock().unwrap().iter().filter(|transaction| user_id.map_or(true, |user_id| transaction.user_id == user_id)).cloned().collect()
+    }
+
+    async fn add_transaction(&self, mut transaction: PointTransaction) -> Result<PointTransaction, String> {
+        let (transaction, total_points, leaderboard_before, leaderboard_after) = {
+            let users_guard = self.users.lock().unwrap();
+            let leaderboard_before = LegacyRepository::leaderboard(&users_guard);
+
+            let user_mutex = users_guard.iter().find(|user| user.lock().unwrap().id == transaction.user_id)
+                .ok_or(format!("User with id {} does not exist", transaction.user_id))?;
+            let mut user = user_mutex.lock().unwrap();
+            if transaction.kind.is_earned() {
+                transaction.season_id = self.active_season_id();
+            }
+            let mut ledger = self.ledger.lock().unwrap();
+            let transaction = LegacyRepository::book(&mut ledger, transaction);
+            user.points = lifetime_points(user.id, &ledger);
+            let total_points = user.points;
+            drop(user);
+
+            (transaction, total_points, leaderboard_before, LegacyRepository::leaderboard(&users_guard))
+        };
+
+        self.event_bus.publish(self.team_ids_of(transaction.user_id), ActivityEvent::PointsBooked { transaction_id: transaction.id, user_id: transaction.user_id,
+            kind: transaction.kind, amount: transaction.amount, total_points });
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
         }
+        Ok(transaction)
+    }
 
-        let new_id = users_vec.iter().map(|u| u.lock().unwrap().id).max().unwrap_or(0) + 1;
-        user.id = new_id;
-        let new_user = Arc::new(Mutex::new(user));
-        users_vec.push(new_user.clone());
+    async fn get_seasons(&self) -> Vec<Season> {
+        self.seasons.lock().unwrap().clone()
+    }
 
-        Ok(new_user)
+    async fn start_season(&self, season: Season) -> Result<Season, String> {
+        let season = {
+            let mut seasons = self.seasons.lock().unwrap();
+            if let Some(running) = seasons.iter().find(|season| season.is_active()) {
+                return Err(format!("Season '{}' is still running", running.name));
+            }
+            if seasons.iter().any(|existing| existing.name == season.name) {
+                return Err(format!("Season '{}' already exists", season.name));
+            }
+
+            let season = Season { id: seasons.iter().map(|s| s.id).max().unwrap_or(0) + 1, ..season };
+            seasons.push(season.clone());
+            season
+        };
+
+        self.event_bus.publish(vec![], ActivityEvent::SeasonStarted { season_id: season.id, name: season.name.clone() });
+        Ok(season)
+    }
+
+    async fn end_season(&self, id: u32) -> Result<Season, String> {
+        let (season, awarded) = {
+            // Holding the users keeps scores from being booked while the standings are archived
+            let users_guard = self.users.lock().unwrap();
+            let users: Vec<(u32, String)> = users_guard.iter().map(|user| user.lock().unwrap()).map(|user| (user.id, user.display_name.clone())).collect();
+            let mut seasons = self.seasons.lock().unwrap();
+            let season = seasons.iter_mut().find(|season| season.id == id).ok_or(format!("Season with id {} does not exist", id))?;
+            season.finish(standings(id, &users, &self.ledger.lock().unwrap()), Utc::now())?;
+
+            let mut trophies = self.trophies.lock().unwrap();
+            let mut awarded = vec![];
+            for mut trophy in season.trophies() {
+                trophy.id = trophies.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                trophies.push(trophy.clone());
+                awarded.push(trophy);
+            }
+            (season.clone(), awarded)
+        };
+
+        self.event_bus.publish(vec![], ActivityEvent::SeasonEnded { season_id: id, name: season.name.clone(), standings: season.standings.clone() });
+        awarded.iter().for_each(|trophy| self.event_bus.publish(self.team_ids_of(trophy.user_id), season.trophy_awarded(trophy)));
+        Ok(season)
     }
 
-    fn add_team_private<'a>(&'a self, mut team: Team) -> Result<Arc<Mutex<Team>>, String> {
-        let mut teams_vec = self.teams.lock().unwrap();
+    async fn end_due_seasons(&self, now: DateTime<Utc>) -> Vec<Season> {
+        let due: Vec<u32> = self.get_seasons().await.into_iter().filter(|season| season.is_due(now)).map(|season| season.id).collect();
+        let mut ended = vec![];
+        for id in due {
+            match self.end_season(id).await {
+                Ok(season) => ended.push(season),
+                Err(err_msg) => error!(season_id = id, error = %err_msg, "Unable to end a season past its planned end"),
+            }
+        }
+        ended
+    }
 
-        let new_id = teams_vec.iter().map(|u| u.lock().unwrap().id).max().unwrap_or(0) + 1;
-        team.id = new_id;
-        let new_team = Arc::new(Mutex::new(team));
-        teams_vec.push(new_team.clone());
+    async fn get_season_leaderboard(&self, id: u32) -> Option<Vec<LeaderboardEntry>> {
+        let users: Vec<(u32, String)> = self.users.lock().unwrap().iter().map(|user| user.lock().unwrap()).map(|user| (user.id, user.display_name.clone())).collect();
+        let season = self.seasons.lock().unwrap().iter().find(|season| season.id == id).cloned()?;
+        if !season.is_active() {
+            return Some(season.standings);
+        }
+        Some(standings(id, &users, &self.ledger.lock().unwrap()))
+    }
 
-        Ok(new_team)
+    async fn get_trophies(&self, user_id: Option<u32>) -> Vec<Trophy> {
+        self.trophies.lock().unwrap().iter().filter(|trophy| user_id.map_or(true, |user_id| trophy.user_id == user_id)).cloned().collect()
     }
-}
 
-#[cfg(test)]
-mod tests {
+    async fn give_kudos(&self, mut kudos: Kudos, limits: KudosLimits) -> Result<Kudos, String> {
+        let (kudos_event, leaderboard_before, leaderboard_after) = {
+            let users_guard = self.users.lock().unwrap();
+            let leaderboard_before = LegacyRepository::leaderboard(&users_guard);
+            let find_user = |id: u32| users_guard.iter().find(|user| user.lock().unwrap().id == id).cloned().ok_or(format!("User with id {} does not exist", id));
+            let from_display_name = find_user(kudos.from_user_id)?.lock().unwrap().display_name.clone();
+            let recipient_mutex = find_user(kudos.to_user_id)?;
+            let mut recipient = recipient_mutex.lock().unwrap();
 
-    use rocket::futures::executor::block_on;
+            {
+                let mut all_kudos = self.kudos.lock().unwrap();
+                limits.check(&kudos, &all_kudos)?;
+                kudos.id = all_kudos.iter().map(|k| k.id).max().unwrap_or(0) + 1;
+                all_kudos.push(kudos.clone());
+            }
 
-    use crate::model::{Session, session::LoginRequest, User, user::Team};
-    use crate::repository::repository::Repository;
+            let season_id = self.active_season_id();
+            let mut ledger = self.ledger.lock().unwrap();
+            LegacyRepository::book(&mut ledger, PointTransaction { season_id, ..kudos.transaction(&from_display_name) });
+            recipient.points = lifetime_points(kudos.to_user_id, &ledger);
+            drop(ledger);
 
-    use super::LegacyRepository;
+            let kudos_event = kudos.event(from_display_name, recipient.display_name.clone());
+            drop(recipient);
+            (kudos_event, leaderboard_before, LegacyRepository::leaderboard(&users_guard))
+        };
 
-    lazy_static! {
-        static ref REPOSITORY: LegacyRepository = block_on(LegacyRepository::init_repository());
-        static ref ADMIN_SESSION: Session = block_on(REPOSITORY.login(LoginRequest{username: "roterkohl".to_owned(), password: Some("Flori1234".to_owned())})).unwrap();
-        static ref USER_SESSION: Session = block_on(REPOSITORY.login(LoginRequest{username: "dliwespf".to_owned(), password: Some("Franki1234".to_owned())})).unwrap();
+        let mut team_ids = self.team_ids_of(kudos.from_user_id);
+        team_ids.extend(self.team_ids_of(kudos.to_user_id));
+        team_ids.sort_unstable();
+        team_ids.dedup();
+        self.event_bus.publish(team_ids, kudos_event);
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
+        Ok(kudos)
     }
-    
-    #[test]
-    fn test_get_user_exists() {
-        let exists_opt = block_on(REPOSITORY.get_user(1));
-        assert!(exists_opt.is_some());
-        assert_eq!(1, exists_opt.unwrap().id);
+
+    async fn get_kudos(&self, user_id: Option<u32>) -> Vec<Kudos> {
+        self.kudos.lock().unwrap().iter()
+            .filter(|kudos| user_id.map_or(true, |user_id| kudos.from_user_id == user_id || kudos.to_user_id == user_id))
+            .cloned()
+            .collect()
     }
 
-    #[test]
-    fn test_get_user_does_not_exist() {
-        let does_not_exist_opt = block_on(REPOSITORY.get_user(10));
-        assert!(does_not_exist_opt.is_none());
+    async fn claim_score(&self, user_id: u32, task_id: u32) -> Result<ScoreClaim, String> {
+        let (claim, display_name) = {
+            let user = self.get_user_unlocked(user_id).ok_or(format!("User with id {} does not exist", user_id))?;
+            let user = user.lock().unwrap();
+            let tasks = self.tasks.lock().unwrap();
+            let task = tasks.iter().find(|task| task.id == task_id).ok_or("Task does not exist")?;
+            if !task.enabled {
+                return Err("Task is not enabled".to_owned());
+            }
+            if !task.requires_verification {
+                return Err("Task does not require verification".to_owned());
+            }
+
+            let mut claims = self.score_claims.lock().unwrap();
+            let claim = ScoreClaim::new(claims.iter().map(|c| c.id).max().unwrap_or(0) + 1, task, user_id);
+            claims.push(claim.clone());
+            (claim, user.display_name.clone())
+        };
+
+        self.event_bus.publish(self.team_ids_of(user_id), ActivityEvent::ScoreClaimed { claim_id: claim.id, user_id, display_name, task_id,
+            task_name: claim.task_name.clone(), points: claim.points });
+        Ok(claim)
+    }
+
+    async fn get_score_claims(&self, user_id: Option<u32>) -> Vec<ScoreClaim> {
+        self.score_claims.lock().unwrap().iter().filter(|claim| user_id.map_or(true, |user_id| claim.user_id == user_id)).cloned().collect()
+    }
+
+    async fn decide_score_claim(&self, claim_id: u32, confirmed: bool, decided_by: String) -> Result<ScoreClaim, String> {
+        let (claim, score_event, leaderboard_before, leaderboard_after) = {
+            let users_guard = self.users.lock().unwrap();
+            let leaderboard_before = LegacyRepository::leaderboard(&users_guard);
+            let user_id = self.score_claims.lock().unwrap().iter().find(|c| c.id == claim_id).map(|c| c.user_id)
+                .ok_or(format!("Claim with id {} does not exist", claim_id))?;
+            let user_mutex = users_guard.iter().find(|user| user.lock().unwrap().id == user_id).cloned().ok_or(format!("User with id {} does not exist", user_id))?;
+            let mut user = user_mutex.lock().unwrap();
+            let tasks = self.tasks.lock().unwrap();
+            let mut claims = self.score_claims.lock().unwrap();
+            let claim = claims.iter_mut().find(|c| c.id == claim_id).ok_or(format!("Claim with id {} does not exist", claim_id))?;
+            let task = tasks.iter().find(|task| task.id == claim.task_id).cloned().ok_or(format!("Task with id {} does not exist", claim.task_id))?;
+            claim.decide(confirmed, decided_by.clone())?;
+
+            // Only a confirmed claim becomes a score
+            let mut score_event = None;
+            if confirmed {
+                let score = claim.score(task);
+                user.scores.push(score.clone());
+                let season_id = self.active_season_id();
+                let mut ledger = self.ledger.lock().unwrap();
+                LegacyRepository::book(&mut ledger, PointTransaction { season_id, created_by: Some(decided_by), ..PointTransaction::for_score(user_id, &score) });
+                user.points = lifetime_points(user_id, &ledger);
+                score_event = Some(ActivityEvent::Score { user_id, display_name: user.display_name.clone(), task_id: claim.task_id,
+                    task_name: claim.task_name.clone(), points: claim.points, total_points: user.points });
+            }
+            let claim = claim.clone();
+            drop(claims);
+            drop(tasks);
+            drop(user);
+            (claim, score_event, leaderboard_before, LegacyRepository::leaderboard(&users_guard))
+        };
+
+        let team_ids = self.team_ids_of(claim.user_id);
+        self.event_bus.publish(team_ids.clone(), ActivityEvent::ScoreClaimDecided { claim_id, user_id: claim.user_id,
+            task_name: claim.task_name.clone(), status: claim.status });
+        if let Some(score_event) = score_event {
+            self.event_bus.publish(team_ids, score_event);
+        }
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
+        Ok(claim)
     }
 
-    #[test]
-    fn test_find_user_by_username_exists() {
-        let result = block_on(REPOSITORY.find_user_by_username(&"topher".to_owned()));
-        assert!(result.is_some());
-        let user_result = result.unwrap();
-        let user = user_result.lock().unwrap();
-        assert_eq!(4, user.id);
+    async fn expire_score_claims(&self, cutoff: DateTime<Utc>) -> Vec<ScoreClaim> {
+        let expired: Vec<ScoreClaim> = self.score_claims.lock().unwrap().iter_mut()
+            .filter_map(|claim| claim.expire(cutoff).then(|| claim.clone()))
+            .collect();
+
+        for claim in expired.iter() {
+            self.event_bus.publish(self.team_ids_of(claim.user_id), ActivityEvent::ScoreClaimDecided { claim_id: claim.id, user_id: claim.user_id,
+                task_name: claim.task_name.clone(), status: claim.status });
+        }
+        expired
     }
 
-    #[test]
-    fn test_find_user_by_username_does_not_exist() {
-        let result = block_on(REPOSITORY.find_user_by_username(&"eduardLaser".to_owned()));
-        assert!(result.is_none());
+    async fn propose_challenge(&self, mut challenge: Challenge) -> Result<Challenge, String> {
+        {
+            let mut challenges = self.challenges.lock().unwrap();
+            if challenges.iter().any(|c| c.name == challenge.name) {
+                return Err(format!("Challenge '{}' already exists", challenge.name));
+            }
+            challenge.id = challenges.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+            challenges.push(challenge.clone());
+        }
+
+        let teams = self.get_all_teams().await;
+        self.event_bus.publish(challenge.team_ids(&teams), challenge.event());
+        Ok(challenge)
     }
 
-    #[test]
-    fn test_get_all_users() {
-        let result = block_on(block_on(LegacyRepository::init_repository()).get_all_users());
-        assert_eq!(4, result.len());
+    async fn get_challenges(&self) -> Vec<Challenge> {
+        self.challenges.lock().unwrap().clone()
     }
 
-    #[test]
-    fn test_get_task_exists() {
-        let exists_opt = block_on(REPOSITORY.get_task(1));
-        assert!(exists_opt.is_some());
-        assert_eq!(1, exists_opt.unwrap().id);
+    async fn decide_challenge(&self, challenge_id: u32, accepted: bool, decided_by: String) -> Result<Challenge, String> {
+        let challenge = {
+            let mut challenges = self.challenges.lock().unwrap();
+            let challenge = challenges.iter_mut().find(|c| c.id == challenge_id).ok_or(format!("Challenge with id {} does not exist", challenge_id))?;
+            challenge.decide(accepted, decided_by, Utc::now())?;
+            challenge.clone()
+        };
+
+        let teams = self.get_all_teams().await;
+        self.event_bus.publish(challenge.team_ids(&teams), challenge.event());
+        Ok(challenge)
     }
 
-    #[test]
-    fn test_get_task_does_not_exist() {
-        let does_not_exist_opt = block_on(REPOSITORY.get_task(4711));
-        assert!(does_not_exist_opt.is_none());
+    async fn resolve_challenges(&self, now: DateTime<Utc>) -> Vec<Challenge> {
+        if !self.challenges.lock().unwrap().iter().any(|challenge| challenge.is_due(now)) {
+            return vec![];
+        }
+        let users = self.get_all_users().await;
+        let teams = self.get_all_teams().await;
+
+        let mut resolved = vec![];
+        let mut stakes = vec![];
+        for challenge in self.challenges.lock().unwrap().iter_mut().filter(|challenge| challenge.is_due(now)) {
+            stakes.extend(challenge.resolve(&users, &teams));
+            resolved.push(challenge.clone());
+        }
+        for stake in stakes {
+            if let Err(err_msg) = self.add_transaction(stake).await {
+                error!(error = %err_msg, "Unable to book the stake of a challenge");
+            }
+        }
+
+        for challenge in resolved.iter() {
+            self.event_bus.publish(challenge.team_ids(&teams), challenge.event());
+            for mut trophy in challenge.trophies(&teams) {
+                {
+                    let mut trophies = self.trophies.lock().unwrap();
+                    trophy.id = trophies.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                    trophies.push(trophy.clone());
+                }
+                let display_name = users.iter().find(|user| user.id == trophy.user_id).map(|user| user.display_name.clone()).unwrap_or_default();
+                self.event_bus.publish(self.team_ids_of(trophy.user_id), ActivityEvent::Trophy { user_id: trophy.user_id, display_name, trophy: trophy.name });
+            }
+        }
+        resolved
     }
 
-    #[test]
-    fn test_get_all_tasks() {
-        let result = block_on(block_on(LegacyRepository::init_repository()).get_all_tasks());
-        assert_eq!(4, result.len());
+    async fn get_schedules(&self) -> Vec<Schedule> {
+        self.schedules.lock().unwrap().clone()
     }
 
-    #[test]
-    fn test_find_session_exists() {
-        let result = REPOSITORY.find_session(&ADMIN_SESSION.id);
-        assert!(result.is_some());
-        assert_eq!(ADMIN_SESSION.id, result.unwrap().lock().unwrap().id);
+    async fn save_schedule(&self, schedule: Schedule) -> Result<Schedule, String> {
+        if !self.tasks.lock().unwrap().iter().any(|task| task.id == schedule.task_id) {
+            return Err(format!("Task with id {} does not exist", schedule.task_id));
+        }
+        let mut schedules = self.schedules.lock().unwrap();
+        schedules.retain(|s| s.task_id != schedule.task_id);
+        schedules.push(schedule.clone());
+        Ok(schedule)
+    }
+
+    async fn remove_schedule(&self, task_id: u32) -> Result<(), String> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let count = schedules.len();
+        schedules.retain(|s| s.task_id != task_id);
+        if schedules.len() == count {
+            return Err(format!("Task with id {} has no schedule", task_id));
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_get_session_does_not_exist() {
-        let result = REPOSITORY.find_session(&"&ADMIN_SESSION.id".to_owned());
-        assert!(result.is_none());
+    async fn settle_schedules(&self, now: DateTime<Utc>) -> Vec<PointTransaction> {
+        let users = self.get_all_users().await;
+        let teams = self.get_all_teams().await;
+        let tasks = self.tasks.lock().unwrap().clone();
+
+        let mut settled = vec![];
+        for schedule in self.schedules.lock().unwrap().iter_mut() {
+            if let Some(task) = tasks.iter().find(|task| task.id == schedule.task_id) {
+                settled.extend(schedule.settle(task, &users, &teams, now));
+            }
+        }
+
+        let mut booked = vec![];
+        for transaction in settled {
+            match self.add_transaction(transaction).await {
+                Ok(transaction) => booked.push(transaction),
+                Err(err_msg) => error!(error = %err_msg, "Unable to book the bonus or malus of a schedule"),
+            }
+        }
+        booked
     }
 
-    #[test]
-    fn test_score_ok() {
-        let result = block_on(REPOSITORY.score(4, 4));
-        assert!(result.is_ok());
-        assert_eq!(450, result.unwrap());
+    async fn get_webhooks(&self) -> Vec<Webhook> {
+        self.webhooks.lock().unwrap().clone()
     }
 
-    #[test]
-    fn test_score_user_does_not_exist() {
-        let result = block_on(REPOSITORY.score(40, 4));
-        assert!(result.is_err());
-        assert_eq!("User does not exist", result.unwrap_err());
+    async fn add_webhook(&self, mut webhook: Webhook) -> Result<Webhook, String> {
+        let mut webhooks = self.webhooks.lock().unwrap();
+        webhook.id = webhooks.iter().map(|w| w.id).max().unwrap_or(0) + 1;
+        webhooks.push(webhook.clone());
+        Ok(webhook)
     }
 
-    #[test]
-    fn test_score_task_does_not_exist() {
-        let result = block_on(REPOSITORY.score(4, 40));
-        assert!(result.is_err());
-        assert_eq!("Task does not exist", result.unwrap_err());
+    async fn remove_webhook(&self, id: u32) -> Result<(), String> {
+        let mut webhooks = self.webhooks.lock().unwrap();
+        let index = webhooks.iter().position(|w| w.id == id).ok_or(format!("Webhook with id {} does not exist", id))?;
+        webhooks.remove(index);
+        Ok(())
     }
 
-    #[test]
-    fn test_score_task_is_disabled() {
-        let result = block_on(REPOSITORY.score(4, 2));
-        assert!(result.is_err());
-        assert_eq!("Task is not enabled", result.unwrap_err());
+    async fn get_dead_letters(&self) -> Vec<WebhookDelivery> {
+        self.dead_letters.lock().unwrap().iter().cloned().collect()
     }
 
-    #[test]
-    fn test_create_and_add_user_ok() {
-        let result = block_on(REPOSITORY.create_and_add_user("Winston".to_owned(), "Wilson".to_owned(), "fml".to_owned(), false));
-        assert!(result.is_ok());
-        let new_user_id = result.unwrap().lock().unwrap().id;
-        let new_user_result = block_on(REPOSITORY.get_user(new_user_id));
-        assert!(new_user_result.is_some());
+    async fn add_dead_letter(&self, delivery: WebhookDelivery) -> Result<(), String> {
+        let mut dead_letters = self.dead_letters.lock().unwrap();
+        if dead_letters.len() >= DEAD_LETTER_LEN {
+            dead_letters.pop_front();
+        }
+        dead_letters.push_back(delivery);
+        Ok(())
     }
+}
 
-    #[test]
-    fn test_create_and_add_user_name_clash() {
-        let result = block_on(REPOSITORY.create_and_add_user("dliwespf".to_owned(), "Wilson".to_owned(), "fml".to_owned(), false));
-        let err = result.err();
-        
-        assert!(err.is_some());
-        assert_eq!("Username is not available", err.unwrap());
+impl LegacyRepository {
+
+    pub fn empty() -> LegacyRepository {
+        LegacyRepository {
+            users: Arc::new(Mutex::new(vec![])),
+            sessions: Arc::new(Mutex::new(vec![])),
+            tasks: Arc::new(Mutex::new(vec![])),
+            teams: Arc::new(Mutex::new(vec![])),
+            event_bus: EventBus::new(),
+            setting_changes: Arc::new(Mutex::new(vec![])),
+            rewards: Arc::new(Mutex::new(vec![])),
+            redemptions: Arc::new(Mutex::new(vec![])),
+            ledger: Arc::new(Mutex::new(vec![])),
+            seasons: Arc::new(Mutex::new(vec![])),
+            trophies: Arc::new(Mutex::new(vec![])),
+            kudos: Arc::new(Mutex::new(vec![])),
+            score_claims: Arc::new(Mutex::new(vec![])),
+            challenges: Arc::new(Mutex::new(vec![])),
+            schedules: Arc::new(Mutex::new(vec![])),
+            webhooks: Arc::new(Mutex::new(vec![])),
+            dead_letters: Arc::new(Mutex::new(VecDeque::new())),
+        }
     }
 
-    #[test]
-    fn test_add_team() {
-        let repository = block_on(LegacyRepository::init_repository());
-        let _new_id = repository.add_team(Team::new(0, "newTeam".to_owned(), repository.get_user_unlocked(3).unwrap().clone()));
-        //let team = repository.get_
+    pub fn restore(snapshot: RepositorySnapshot) -> Result<LegacyRepository, String> {
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(format!("Snapshot version {} is newer than the supported version {}", snapshot.version, SNAPSHOT_VERSION));
+        }
+
+        let ledger = snapshot.ledger_or_derived();
+        let users: Vec<Arc<Mutex<User>>> = snapshot.users.into_iter().map(|record| {
+            let mut user = User::from(record);
+            user.points = lifetime_points(user.id, &ledger);
+            Arc::new(Mutex::new(user))
+        }).collect();
+        let find_user = |id: u32| users.iter().find(|user| user.lock().unwrap().id == id).cloned();
+
+        let mut teams = vec![];
+        for record in snapshot.teams {
+            let mut members = vec![];
+            for member_id in record.member_ids.iter() {
+                members.push(find_user(*member_id).ok_or(format!("Member {} of team '{}' does not exist", member_id, record.name))?);
+            }
+            let team = Team { id: record.id, name: record.name, manager_id: record.manager_id, members, member_ids: record.member_ids.into_iter().collect() };
+            teams.push(Arc::new(Mutex::new(team)));
+        }
+
+        let repository = LegacyRepository::empty();
+        *repository.users.lock().unwrap() = users;
+        *repository.tasks.lock().unwrap() = snapshot.tasks;
+        *repository.teams.lock().unwrap() = teams;
+        *repository.setting_changes.lock().unwrap() = snapshot.setting_changes;
+        *repository.rewards.lock().unwrap() = snapshot.rewards;
+        *repository.redemptions.lock().unwrap() = snapshot.redemptions;
+        *repository.ledger.lock().unwrap() = ledger;
+        *repository.seasons.lock().unwrap() = snapshot.seasons;
+        *repository.trophies.lock().unwrap() = snapshot.trophies;
+        *repository.kudos.lock().unwrap() = snapshot.kudos;
+        *repository.score_claims.lock().unwrap() = snapshot.score_claims;
+        *repository.challenges.lock().unwrap() = snapshot.challenges;
+        *repository.schedules.lock().unwrap() = snapshot.schedules;
+        *repository.webhooks.lock().unwrap() = snapshot.webhooks.into_iter().map(Webhook::from).collect();
+        *repository.dead_letters.lock().unwrap() = snapshot.dead_letters.into_iter().collect();
+
+        Ok(repository)
+    }
+
+    pub fn snapshot(&self) -> RepositorySnapshot {
+        RepositorySnapshot {
+            version: SNAPSHOT_VERSION,
+            users: self.users.lock().unwrap().iter().map(|user| UserRecord::from(&*user.lock().unwrap())).collect(),
+            tasks: self.tasks.lock().unwrap().clone(),
+            teams: self.teams.lock().unwrap().iter().map(|team| TeamRecord::from(&*team.lock().unwrap())).collect(),
+            setting_changes: self.setting_changes.lock().unwrap().clone(),
+            rewards: self.rewards.lock().unwrap().clone(),
+            redemptions: self.redemptions.lock().unwrap().clone(),
+            ledger: self.ledger.lock().unwrap().clone(),
+            seasons: self.seasons.lock().unwrap().clone(),
+            trophies: self.trophies.lock().unwrap().clone(),
+            kudos: self.kudos.lock().unwrap().clone(),
+            score_claims: self.score_claims.lock().unwrap().clone(),
+            challenges: self.challenges.lock().unwrap().clone(),
+            schedules: self.schedules.lock().unwrap().clone(),
+            webhooks: self.webhooks.lock().unwrap().iter().map(WebhookRecord::from).collect(),
+            dead_letters: self.dead_letters.lock().unwrap().iter().cloned().collect(),
+        }
     }
 
-    #[test]
-    fn test_add_user_to_team() {
-        //REPOSITORY.add_user_to_team(team_name, user_id, manager)
+    // Creates a repository filled with demo data
+    pub async fn init_repository() -> LegacyRepository {
+        let repository = LegacyRepository::empty();
+        *repository.tasks.lock().unwrap() = demo_tasks();
+        add_demo_data(&repository).await;
+
+        repository
     }
 
-    #[test]
-    fn test_add_user() {
-        let newbie = User::new(0, "newbie".to_owned(), "Newbie".to_owned(), false);
-        let result = block_on(REPOSITORY.add_user(&ADMIN_SESSION, newbie)).content;
-        let new_user_id = result.unwrap();
-        assert!(result.is_some());
-        let new_user_result = block_on(REPOSITORY.get_user(new_user_id));
-        assert!(new_user_result.is_some());
+    fn get_user_unlocked<'a>(&'a self, id: u32) -> Option<Arc<Mutex<User>>> {
+
+        let users_guard = self.users.lock().unwrap();
+        users_guard.iter().find(|user| user.lock().unwrap().id == id).and_then(|user| Some(user.clone()))
+
+    }
+    
+    fn active_season_id(&self) -> Option<u32> {
+        let now = Utc::now();
+        self.seasons.lock().unwrap().iter().find(|season| season.is_running(now)).map(|season| season.id)
     }
 
-    #[test]
-    fn test_login_ok() {
-        let login_request = LoginRequest { username: "roterkohl".to_owned(), password: Some("Flori1234".to_owned()) };
-        let result = block_on(REPOSITORY.login(login_request));
-        assert!(result.is_ok());
-        assert_eq!(30, result.unwrap().id.len());
+    fn team_ids_of(&self, user_id: u32) -> Vec<u32> {
+        self.teams.lock().unwrap().iter()
+            .map(|team| team.lock().unwrap())
+            .filter(|team| team.member_ids.contains(&user_id))
+            .map(|team| team.id)
+            .collect()
     }
 
-    #[test]
-    fn test_login_missing_user() {
-        let login_request = LoginRequest { username: "blauerkohl".to_owned(), password: Some("Flori1234".to_owned()) };
-        let result = block_on(REPOSITORY.login(login_request));
-        assert!(result.is_err());
-        let err = result.err();
-        assert_eq!("User does not exist", err.unwrap());
+    fn leaderboard(users: &[Arc<Mutex<User>>]) -> Vec<LeaderboardEntry> {
+        let mut users: Vec<User> = users.iter().map(|user| user.lock().unwrap().clone()).collect();
+        users.sort_by(|a, b| b.points.cmp(&a.points).then(a.id.cmp(&b.id)));
+
+        users.iter().enumerate()
+            .map(|(index, user)| LeaderboardEntry { rank: index as u32 + 1, user_id: user.id, display_name: user.display_name.clone(), points: user.points })
+            .collect()
     }
 
-    #[test]
-    fn test_login_wrong_password() {
-        let login_request = LoginRequest { username: "roterkohl".to_owned(), password: Some("Flori5678".to_owned()) };
-        let result = block_on(REPOSITORY.login(login_request));
-        assert!(result.is_err());
-        let err = result.err();
-        assert_eq!("Password mismatch", err.unwrap());
+    // Appends the transaction with the next id, the ledger is never changed otherwise
+    fn book(ledger: &mut Vec<PointTransaction>, mut transaction: PointTransaction) -> PointTransaction {
+        transaction.id = ledger.last().map_or(0, |last| last.id) + 1;
+        ledger.push(transaction.clone());
+        transaction
     }
 
-    #[test]
-    fn test_logout_ok() {
-        let result = block_on(REPOSITORY.logout(&USER_SESSION.id));
-        assert!(result.is_ok())
+    fn find_session<'a>(&'a self, session_id: &String) -> Option<Arc<Mutex<Session>>> {
+        self.sessions.lock().unwrap().iter().find(|session| session.lock().unwrap().id.eq(session_id))
+            .and_then(|f| Some(f.clone()))
+    }
+
+    fn add_user_private<'a>(&'a self, mut user: User) -> Result<Arc<Mutex<User>>, String> {
+        let mut users_vec = self.users.lock().unwrap();
+
+        if users_vec.iter().any(|u| u.lock().unwrap().username.eq(&user.username)) {
+            return Err("Username is not available".to_owned());
+        }
+
+        let new_id = users_vec.iter().map(|u| u.lock().unwrap().id).max().unwrap_or(0) + 1;
+        user.id = new_id;
+        let user_created = ActivityEvent::UserCreated { user_id: new_id, username: user.username.clone(), display_name: user.display_name.clone() };
+        let new_user = Arc::new(Mutex::new(user));
+        users_vec.push(new_user.clone());
+
+        self.event_bus.publish(vec![], user_created);
+
+        Ok(new_user)
+    }
+
+    fn add_team_private<'a>(&'a self, mut team: Team) -> Result<Arc<Mutex<Team>>, String> {
+        let mut teams_vec = self.teams.lock().unwrap();
+
+        let new_id = teams_vec.iter().map(|u| u.lock().unwrap().id).max().unwrap_or(0) + 1;
+        team.id = new_id;
+        let team_created = ActivityEvent::TeamCreated { team_id: new_id, name: team.name.clone() };
+        let new_team = Arc::new(Mutex::new(team));
+        teams_vec.push(new_team.clone());
+
+        self.event_bus.publish(vec![new_id], team_created);
+
+        Ok(new_team)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::futures::executor::block_on;
+
+    use crate::repository::conformance::repository_conformance_tests;
+    use crate::repository::repository::Repository;
+
+    use super::LegacyRepository;
+
+    async fn fixture(_test_name: &str) -> LegacyRepository {
+        LegacyRepository::init_repository().await
     }
 
+    repository_conformance_tests!(fixture);
+
     #[test]
-    fn test_logout_not_logged_in() {
-        let result = block_on(REPOSITORY.logout(&"&USER_SESSION.id".to_owned()));
-        assert!(result.is_err());
-        assert_eq!("Session unknown", result.unwrap_err())
+    fn test_restore_snapshot() {
+        let repository = block_on(LegacyRepository::init_repository());
+        block_on(repository.score(3, 4)).unwrap();
+
+        let restored = LegacyRepository::restore(repository.snapshot()).unwrap();
+        assert_eq!(75, block_on(restored.get_user(3)).unwrap().points);
+        assert_eq!(vec![1, 2], restored.team_ids_of(1));
     }
-}
\ No newline at end of file
+}
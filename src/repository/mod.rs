@@ -1,3 +1,11 @@
 pub mod repository;
 pub mod legacy_repository;
-pub mod neo4j_repsitory;
\ No newline at end of file
+pub mod neo4j_repsitory;
+pub mod file_repository;
+pub mod sqlite_repository;
+pub mod demo_data;
+pub mod backup;
+pub mod bulk_import;
+
+#[cfg(test)]
+pub mod conformance;
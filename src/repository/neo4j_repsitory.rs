@@ -1,14 +1,25 @@
 
-use std::{env, iter::FromIterator, convert::TryFrom};
+use std::{iter::FromIterator, convert::TryFrom, time::Instant};
 
-use bolt_client::{Client, bolt_proto::{version::{V4_3, V4_2}, Message, message::{Success, Record}, value::Node}, Metadata, Params};
-use dotenv::dotenv;
+use chrono::{DateTime, Utc};
+use bolt_client::{Client, bolt_proto::{version::{V4_3, V4_2}, Message, message::{Success, Record}, value::Node, Value}, Metadata, Params};
 use rocket::{tokio::{net::TcpStream, io::BufStream}, futures::lock::Mutex, http::Status};
 use tokio_util::compat::*;
-use crate::{model::{User}, resource::http::responder::MessageResponder};
+use crate::{model::{User, health::DependencyHealth, setting::{SettingValue, SettingChange}, snapshot::WebhookRecord, webhook::{Webhook, WebhookDelivery, DEAD_LETTER_LEN}}, resource::http::responder::MessageResponder};
+use crate::config::app_config::AppConfig;
+use crate::event::event_bus::EventBus;
+use crate::metrics::metrics::Metrics;
+use tracing::{debug, error, info_span, Instrument};
 
 use super::{legacy_repository::{LegacyRepository}, repository::Repository};
 
+// Schema changes, applied in order on connect. Each applied version is recorded as a (:Migration) node
+const MIGRATIONS: [(i64, &str); 3] = [
+    (1, "CREATE CONSTRAINT person_username IF NOT EXISTS FOR (p:Person) REQUIRE p.username IS UNIQUE"),
+    (2, "CREATE CONSTRAINT task_id IF NOT EXISTS FOR (t:Task) REQUIRE t.id IS UNIQUE"),
+    (3, "CREATE CONSTRAINT team_name IF NOT EXISTS FOR (t:Team) REQUIRE t.name IS UNIQUE"),
+];
+
 pub struct Neo4JRepository {
     client: Mutex<Client<Compat<BufStream<TcpStream>>>>,
     legacy_repo: LegacyRepository,  // TODO: Replace me
@@ -17,9 +28,10 @@ pub struct Neo4JRepository {
 type ConnectionError = String;
 impl Neo4JRepository {
 
-    pub async fn connect() -> Result<Neo4JRepository, ConnectionError> {
-        dotenv().ok();
-        let db_addr = env::var("TS_DATABASE_ADDRESS").or(Err("Database address not configured".to_owned()))?;
+    pub async fn connect(config: &AppConfig) -> Result<Neo4JRepository, ConnectionError> {
+        let db_addr = config.database_address.as_ref().ok_or("Database address not configured".to_owned())?;
+        let principal = config.database_principal.as_deref().unwrap_or("");
+        let credentials = config.database_password.as_ref().map_or("", |password| password.expose());
 
         let stream = TcpStream::connect(db_addr).await.or(Err("unable to create TCP connection to database".to_owned()))?;
         let stream = BufStream::new(stream).compat();
@@ -35,52 +47,121 @@ impl Neo4JRepository {
             Metadata::from_iter(vec![
                 ("user_agent", "my-client-name/1.0"),
                 ("scheme", "basic"),
-                ("principal", &env::var("TS_DATABASE_PRINCIPAL").unwrap()),
-                ("credentials", &env::var("TS_DATABASE_PASSWORD").unwrap()),
+                ("principal", principal),
+                ("credentials", credentials),
             ])).await.or(Err("Error sending authentication info to database".to_owned()))?;
 
         Success::try_from(response).or(Err("DB responded with error on login".to_owned()))?;
 
+        // A failed migration does not prevent the start, the readiness check reports it instead
+        if let Err(err_msg) = Neo4JRepository::migrate(&mut client).await {
+            error!(error = %err_msg, "Unable to apply database migrations");
+        }
+
         Ok(Neo4JRepository { client: Mutex::new(client), legacy_repo: LegacyRepository::init_repository().await })
     }
 
+    async fn migrate(client: &mut Client<Compat<BufStream<TcpStream>>>) -> Result<(), String> {
+        let applied_version = Neo4JRepository::get_schema_version(client).await?;
+
+        for (version, statement) in MIGRATIONS.iter().filter(|(version, _)| *version > applied_version) {
+            Neo4JRepository::execute_in_db(client, "migration", statement, Params::from_iter(Vec::<(&str, i64)>::new())).await?;
+            Neo4JRepository::execute_in_db(client, "record_migration", "MERGE (:Migration {version: $version});", Params::from_iter(vec![("version", *version)])).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_schema_version(client: &mut Client<Compat<BufStream<TcpStream>>>) -> Result<i64, String> {
+        let statement = "MATCH (m:Migration) RETURN max(m.version);";
+        let records = Neo4JRepository::match_in_db(client, "get_schema_version", statement, Params::from_iter(Vec::<(&str, i64)>::new())).await
+            .ok_or("Unable to read schema version from database".to_owned())?;
+
+        match records[0].fields().get(0) {
+            Some(Value::Integer(version)) => Ok(*version),
+            _ => Ok(0)
+        }
+    }
+
+    // Runs a statement whose result is of no interest, failing if the database does not respond with success
+    async fn execute_in_db(client: &mut Client<Compat<BufStream<TcpStream>>>, statement_name: &str, statement: &str, params: Params) -> Result<(), String> {
+        let start = Instant::now();
+        let result = Neo4JRepository::execute_in_db_untimed(client, statement, params)
+            .instrument(info_span!("cypher", statement = statement_name)).await;
+        Neo4JRepository::observe_statement(statement_name, start, &result);
+
+        result
+    }
+
+    async fn execute_in_db_untimed(client: &mut Client<Compat<BufStream<TcpStream>>>, statement: &str, params: Params) -> Result<(), String> {
+        let run_response = client.run(statement, Some(params), None).await.map_err(|err| err.to_string())?;
+        if Success::try_from(run_response).is_err() {
+            let _ = client.reset().await;
+            return Err(format!("DB responded with error on '{}'", statement));
+        }
+
+        let (_records, pull_response) = client.pull(Some(Metadata::from_iter(vec![("n", -1)]))).await.map_err(|err| err.to_string())?;
+        Success::try_from(pull_response).or(Err(format!("DB responded with error pulling results of '{}'", statement)))?;
+
+        Ok(())
+    }
+
     async fn discard(client: &mut Client<Compat<BufStream<TcpStream>>>) {
         let discard_result = client.discard(Some(Metadata::from_iter(vec![("n", -1)]))).await;
 
         if discard_result.is_err() {
             let err_msg = discard_result.unwrap_err();
-            println!("{}", err_msg);
+            error!(error = %err_msg, "Unable to discard remaining records");
         }
     }
 
-    async fn match_in_db(client: &mut Client<Compat<BufStream<TcpStream>>>, statement: &str, params: Params) -> Option<Vec<Record>> {
+    // Parameters are never logged, as they may contain password hashes
+    fn observe_statement<T>(statement_name: &str, start: Instant, result: &Result<T, String>) {
+        let duration = start.elapsed();
+        Metrics::global().observe_db_query(statement_name, duration, result.is_ok());
 
-        let run_result = client.run(statement, Some(params), None).await;
+        match result {
+            Ok(_) => debug!(statement = statement_name, duration_ms = duration.as_millis() as u64, "Cypher statement executed"),
+            Err(err_msg) => error!(statement = statement_name, duration_ms = duration.as_millis() as u64, error = %err_msg, "Cypher statement failed"),
+        }
+    }
 
-        if run_result.is_err() {
-            let err_msg = run_result.unwrap_err();
-            println!("{}", err_msg);
-            return None;
+    // Runs a statement and pulls its first record, measuring the time it takes under the given statement name
+    async fn match_in_db(client: &mut Client<Compat<BufStream<TcpStream>>>, statement_name: &str, statement: &str, params: Params) -> Option<Vec<Record>> {
+        let start = Instant::now();
+        let result = Neo4JRepository::query_in_db(client, statement, params, 1)
+            .instrument(info_span!("cypher", statement = statement_name)).await;
+        Neo4JRepository::observe_statement(statement_name, start, &result);
+
+        match result {
+            Ok(records) if records.is_empty() => None,
+            Ok(records) => Some(records),
+            Err(_) => None
         }
+    }
 
-        let metadata = Some(Metadata::from_iter(vec![("n", 1)]));
+    // Runs a statement and pulls all of its records
+    async fn fetch_all_in_db(client: &mut Client<Compat<BufStream<TcpStream>>>, statement_name: &str, statement: &str, params: Params) -> Result<Vec<Record>, String> {
+        let start = Instant::now();
+        let result = Neo4JRepository::query_in_db(client, statement, params, -1)
+            .instrument(info_span!("cypher", statement = statement_name)).await;
+        Neo4JRepository::observe_statement(statement_name, start, &result);
 
-        let pull_result = client.pull(metadata).await;
-        if pull_result.is_err() {
-            let err_msg = pull_result.unwrap_err();
-            println!("{}", err_msg);
-            return None;
-        }
+        result
+    }
 
-        let (records, _response) = pull_result.unwrap();
+    // Pulls at most n records (all for -1), discarding the rest
+    async fn query_in_db(client: &mut Client<Compat<BufStream<TcpStream>>>, statement: &str, params: Params, n: i64) -> Result<Vec<Record>, String> {
+        client.run(statement, Some(params), None).await.map_err(|err| err.to_string())?;
 
-        if records.len() == 0 {
-            return None;
-        }
+        let metadata = Some(Metadata::from_iter(vec![("n", n)]));
+        let (records, _response) = client.pull(metadata).await.map_err(|err| err.to_string())?;
 
-        Neo4JRepository::discard(client).await;
+        if !records.is_empty() && n >= 0 {
+            Neo4JRepository::discard(client).await;
+        }
 
-        Some(records)
+        Ok(records)
     }
 }
 
@@ -101,7 +182,7 @@ impl Repository for Neo4JRepository {
         let statement = "MATCH (p:Person {username: $username}) RETURN p;";
         let params = Params::from_iter(vec![("username", username.clone())]);
 
-        let records = Neo4JRepository::match_in_db(&mut client, statement, params).await?;
+        let records = Neo4JRepository::match_in_db(&mut client, "find_user_by_username", statement, params).await?;
 
         let node = Node::try_from(records[0].fields()[0].clone()).unwrap();
 
@@ -126,7 +207,7 @@ impl Repository for Neo4JRepository {
         self.legacy_repo.get_session(session_id).await
     }
 
-    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u16, String> {
+    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u64, String> {
         self.legacy_repo.score(user_id, task_id).await
     }
 
@@ -142,42 +223,29 @@ impl Repository for Neo4JRepository {
         self.legacy_repo.add_user_to_team(team_name, user_id, manager).await
     }
 
-    async fn add_user<'a>(&'a self, _session: &crate::model::Session, user: crate::model::User) -> MessageResponder<u32> {
-        // Todo: Check if admin session
-        // It is needed tochange the Mutex within it to the rocket version - and this is toom much work for me right now :)
+    async fn add_user<'a>(&'a self, session: &crate::model::Session, user: crate::model::User) -> MessageResponder<u32> {
+        // Logins and scores go to the legacy repository, so the user is added there first. It checks the session,
+        // assigns the id and publishes the user_created event with it
+        let created = self.legacy_repo.add_user(session, user.clone()).await;
+        let id = match created.content {
+            Some(id) if created.status() == Status::Ok => id,
+            _ => return created
+        };
 
         let mut client = self.client.lock().await;
 
-        let statement = "CREATE (:Person {username: $username, display_name: $display_name, password: $pwd_hash_components, is_admin: $is_admin });";
+        let statement = "CREATE (:Person {id: $id, username: $username, display_name: $display_name, password: $pwd_hash_components, is_admin: $is_admin });";
         let params = Params::from_iter(vec![
-            ("username", user.username),
-            ("display_name", user.display_name),
-            ("pwd_hash_components", user.pwd_hash_components.unwrap_or("".to_owned())),
-            ("is_admin", format!("{}", user.is_admin))]);
-
-        let run_result = client.run(statement, Some(params), None).await;
-        
-        let result;
-        if run_result.is_err() {
-            let err_msg = run_result.unwrap_err();
-            println!("{}", err_msg);
-            result = MessageResponder::create_with_message(Status::InternalServerError, "Error running create on db (run)".to_owned());
-        } else {
-
-            let metadata = Some(Metadata::from_iter(vec![("n", 1)]));
-            let pull_result = client.pull(metadata).await;
-            if pull_result.is_err() {
-                let err_msg = run_result.unwrap_err();
-                println!("{}", err_msg);
-                result = MessageResponder::create_with_message(Status::InternalServerError, "Error running create on db (run)".to_owned());
-            } else {
-                result = MessageResponder::create_ok(0);
-            }
+            ("id", Value::from(id as i64)),
+            ("username", Value::from(user.username)),
+            ("display_name", Value::from(user.display_name)),
+            ("pwd_hash_components", Value::from(user.pwd_hash_components.unwrap_or("".to_owned()))),
+            ("is_admin", Value::from(format!("{}", user.is_admin)))]);
+
+        match Neo4JRepository::execute_in_db(&mut client, "add_user", statement, params).await {
+            Ok(_) => MessageResponder::create_ok(id),
+            Err(_) => MessageResponder::create_with_message(Status::InternalServerError, "Error running create on db".to_owned())
         }
-
-        Neo4JRepository::discard(&mut client).await;
-
-        result
     }
 
     async fn login<'a>(&'a self, login_request: crate::model::session::LoginRequest) -> Result<crate::model::Session, String> {
@@ -187,4 +255,351 @@ impl Repository for Neo4JRepository {
     async fn logout(&self, session_id: &String) -> Result<(), String> {
         self.legacy_repo.logout(session_id).await
     }
-}
\ No newline at end of file
+
+    async fn count_sessions(&self, started_after: DateTime<Utc>) -> usize {
+        self.legacy_repo.count_sessions(started_after).await
+    }
+
+    fn event_bus(&self) -> &EventBus {
+        self.legacy_repo.event_bus()
+    }
+
+    async fn check_health(&self) -> Vec<DependencyHealth> {
+        let mut client = self.client.lock().await;
+
+        let ping = Neo4JRepository::match_in_db(&mut client, "ping", "RETURN 1;", Params::from_iter(Vec::<(&str, i64)>::new())).await;
+        if ping.is_none() {
+            let _ = client.reset().await;
+            return vec![
+                DependencyHealth::down("neo4j", "Database does not respond via bolt".to_owned()),
+                DependencyHealth::down("migrations", "Schema version unknown".to_owned()),
+            ];
+        }
+
+        let latest_version = MIGRATIONS.iter().map(|(version, _)| *version).max().unwrap_or(0);
+        let migrations = match Neo4JRepository::get_schema_version(&mut client).await {
+            Ok(version) if version >= latest_version => DependencyHealth::up("migrations", Some(format!("Schema version {}", version))),
+            Ok(version) => DependencyHealth::down("migrations", format!("Schema version {} of {} applied", version, latest_version)),
+            Err(err_msg) => DependencyHealth::down("migrations", err_msg),
+        };
+
+        vec![DependencyHealth::up("neo4j", None), migrations]
+    }
+
+    async fn get_settings(&self) -> Vec<(String, SettingValue)> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MATCH (s:Setting) RETURN s.key, s.value;";
+        let records = Neo4JRepository::fetch_all_in_db(&mut client, "get_settings", statement, Params::from_iter(Vec::<(&str, i64)>::new())).await.unwrap_or(vec![]);
+
+        records.iter().filter_map(|record| match (record.fields().get(0), record.fields().get(1)) {
+            (Some(Value::String(key)), Some(Value::String(value))) => serde_json::from_str::<SettingValue>(value).ok().map(|value| (key.clone(), value)),
+            _ => None
+        }).collect()
+    }
+
+    async fn save_setting(&self, change: SettingChange) -> Result<(), String> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MERGE (s:Setting {key: $key}) SET s.value = $new_value \
+            CREATE (s)<-[:CHANGED]-(:SettingChange {old_value: $old_value, new_value: $new_value, changed_by: $changed_by, changed_at: $changed_at});";
+        let params = Params::from_iter(vec![
+            ("key", change.key),
+            ("old_value", serde_json::to_string(&change.old_value).map_err(|err| err.to_string())?),
+            ("new_value", serde_json::to_string(&change.new_value).map_err(|err| err.to_string())?),
+            ("changed_by", change.changed_by),
+            ("changed_at", change.changed_at.to_rfc3339())]);
+
+        Neo4JRepository::execute_in_db(&mut client, "save_setting", statement, params).await
+    }
+
+    async fn get_setting_history(&self, key: &String) -> Vec<SettingChange> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MATCH (:Setting {key: $key})<-[:CHANGED]-(c:SettingChange) \
+            RETURN c.old_value, c.new_value, c.changed_by, c.changed_at ORDER BY c.changed_at DESC;";
+        let params = Params::from_iter(vec![("key", key.clone())]);
+        let records = Neo4JRepository::fetch_all_in_db(&mut client, "get_setting_history", statement, params).await.unwrap_or(vec![]);
+
+        records.iter().filter_map(|record| match record.fields() {
+            [Value::String(old_value), Value::String(new_value), Value::String(changed_by), Value::String(changed_at)] => Some(SettingChange {
+                key: key.clone(),
+                old_value: serde_json::from_str(old_value).ok()?,
+                new_value: serde_json::from_str(new_value).ok()?,
+                changed_by: changed_by.clone(),
+                changed_at: DateTime::parse_from_rfc3339(changed_at).ok()?.with_timezone(&Utc),
+            }),
+            _ => None
+        }).collect()
+    }
+
+    async fn set_password(&self, user_id: u32, password: String) -> Result<(), String> {
+        self.legacy_repo.set_password(user_id, password).await
+    }
+
+    async fn set_user_enabled(&self, user_id: u32, enabled: bool) -> Result<(), String> {
+        self.legacy_repo.set_user_enabled(user_id, enabled).await
+    }
+
+    async fn save_task(&self, task: crate::model::Task) -> Result<crate::model::Task, String> {
+        self.legacy_repo.save_task(task).await
+    }
+
+    async fn export_snapshot(&self) -> Result<crate::model::snapshot::RepositorySnapshot, String> {
+        self.legacy_repo.export_snapshot().await
+    }
+
+    async fn import_snapshot(&self, snapshot: crate::model::snapshot::RepositorySnapshot) -> Result<(), String> {
+        self.legacy_repo.import_snapshot(snapshot).await
+    }
+
+    async fn add_in_bulk(&self, users: Vec<crate::model::bulk_import::ImportedUser>, tasks: Vec<crate::model::Task>) -> Result<(), String> {
+        self.legacy_repo.add_in_bulk(users, tasks).await
+    }
+
+    async fn get_all_teams(&self) -> Vec<crate::model::snapshot::TeamRecord> {
+        self.legacy_repo.get_all_teams().await
+    }
+
+    async fn aggregate_scores(&self, filter: &crate::model::history::ScoreFilter) -> Result<Vec<crate::model::report::ScoreAggregate>, String> {
+        self.legacy_repo.aggregate_scores(filter).await
+    }
+    async fn get_all_rewards(&self) -> Vec<crate::model::reward::Reward> {
+        self.legacy_repo.get_all_rewards().await
+    }
+
+    async fn get_reward(&self, id: u32) -> Option<crate::model::reward::Reward> {
+        self.legacy_repo.get_reward(id).await
+    }
+
+    async fn save_reward(&self, reward: crate::model::reward::Reward) -> Result<crate::model::reward::Reward, String> {
+        self.legacy_repo.save_reward(reward).await
+    }
+
+    async fn redeem(&self, user_id: u32, reward_id: u32) -> Result<crate::model::reward::Redemption, String> {
+        self.legacy_repo.redeem(user_id, reward_id).await
+    }
+
+    async fn get_redemptions(&self, user_id: Option<u32>) -> Vec<crate::model::reward::Redemption> {
+        self.legacy_repo.get_redemptions(user_id).await
+    }
+
+    async fn decide_redemption(&self, redemption_id: u32, approved: bool, decided_by: String) -> Result<crate::model::reward::Redemption, String> {
+        self.legacy_repo.decide_redemption(redemption_id, approved, decided_by).await
+    }
+
+    async fn get_ledger(&self, user_id: Option<u32>) -> Vec<crate::model::ledger::PointTransaction> {
+        self.legacy_repo.get_ledger(user_id).await
+    }
+
+    async fn add_transaction(&self, transaction: crate::model::ledger::PointTransaction) -> Result<crate::model::ledger::PointTransaction, String> {
+        self.legacy_repo.add_transaction(transaction).await
+    }
+
+    async fn get_seasons(&self) -> Vec<crate::model::season::Season> {
+        self.legacy_repo.get_seasons().await
+    }
+
+    async fn start_season(&self, season: crate::model::season::Season) -> Result<crate::model::season::Season, String> {
+        self.legacy_repo.start_season(season).await
+    }
+
+    async fn end_season(&self, id: u32) -> Result<crate::model::season::Season, String> {
+        self.legacy_repo.end_season(id).await
+    }
+
+    async fn end_due_seasons(&self, now: DateTime<Utc>) -> Vec<crate::model::season::Season> {
+        self.legacy_repo.end_due_seasons(now).await
+    }
+
+    async fn get_season_leaderboard(&self, id: u32) -> Option<Vec<crate::model::event::LeaderboardEntry>> {
+        self.legacy_repo.get_season_leaderboard(id).await
+    }
+
+    async fn get_trophies(&self, user_id: Option<u32>) -> Vec<crate::model::trophy::Trophy> {
+        self.legacy_repo.get_trophies(user_id).await
+    }
+
+    // Kept as (:Person)-[:KUDOS]->(:Person) in the graph as well, the points go to the ledger like everything else.
+    // The relationship is written first and removed again if the points cannot be booked, so both stay in line.
+    async fn give_kudos(&self, kudos: crate::model::kudos::Kudos, limits: crate::model::kudos::KudosLimits) -> Result<crate::model::kudos::Kudos, String> {
+        let (from, to) = match (self.legacy_repo.get_user(kudos.from_user_id).await, self.legacy_repo.get_user(kudos.to_user_id).await) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return self.legacy_repo.give_kudos(kudos, limits).await
+        };
+
+        let mut client = self.client.lock().await;
+        let statement = "MATCH (from:Person {username: $from_username}) \
+            MATCH (to:Person {username: $to_username}) \
+            CREATE (from)-[kudos:KUDOS {message: $message, points: $points, at: $at}]->(to) \
+            RETURN kudos.at;";
+        let params = Params::from_iter(vec![
+            ("from_username", Value::from(from.username.clone())),
+            ("to_username", Value::from(to.username.clone())),
+            ("message", Value::from(kudos.message.clone())),
+            ("points", Value::from(kudos.points as i64)),
+            ("at", Value::from(kudos.at.to_rfc3339()))]);
+        if Neo4JRepository::match_in_db(&mut client, "give_kudos", statement, params).await.is_none() {
+            return Err(format!("Unable to add the kudos from {} to {} to the graph", from.username, to.username));
+        }
+
+        match self.legacy_repo.give_kudos(kudos.clone(), limits).await {
+            Ok(kudos) => Ok(kudos),
+            Err(err) => {
+                let statement = "MATCH (:Person {username: $from_username})-[kudos:KUDOS {at: $at}]->(:Person {username: $to_username}) DELETE kudos;";
+                let params = Params::from_iter(vec![
+                    ("from_username", Value::from(from.username)),
+                    ("to_username", Value::from(to.username)),
+                    ("at", Value::from(kudos.at.to_rfc3339()))]);
+                if let Err(err_msg) = Neo4JRepository::execute_in_db(&mut client, "remove_kudos", statement, params).await {
+                    error!(error = %err_msg, "Unable to remove kudos from the graph that could not be booked");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn get_kudos(&self, user_id: Option<u32>) -> Vec<crate::model::kudos::Kudos> {
+        self.legacy_repo.get_kudos(user_id).await
+    }
+
+    async fn claim_score(&self, user_id: u32, task_id: u32) -> Result<crate::model::verification::ScoreClaim, String> {
+        self.legacy_repo.claim_score(user_id, task_id).await
+    }
+
+    async fn get_score_claims(&self, user_id: Option<u32>) -> Vec<crate::model::verification::ScoreClaim> {
+        self.legacy_repo.get_score_claims(user_id).await
+    }
+
+    async fn decide_score_claim(&self, claim_id: u32, confirmed: bool, decided_by: String) -> Result<crate::model::verification::ScoreClaim, String> {
+        self.legacy_repo.decide_score_claim(claim_id, confirmed, decided_by).await
+    }
+
+    async fn expire_score_claims(&self, cutoff: DateTime<Utc>) -> Vec<crate::model::verification::ScoreClaim> {
+        self.legacy_repo.expire_score_claims(cutoff).await
+    }
+
+    async fn propose_challenge(&self, challenge: crate::model::challenge::Challenge) -> Result<crate::model::challenge::Challenge, String> {
+        self.legacy_repo.propose_challenge(challenge).await
+    }
+
+    async fn get_challenges(&self) -> Vec<crate::model::challenge::Challenge> {
+        self.legacy_repo.get_challenges().await
+    }
+
+    async fn decide_challenge(&self, challenge_id: u32, accepted: bool, decided_by: String) -> Result<crate::model::challenge::Challenge, String> {
+        self.legacy_repo.decide_challenge(challenge_id, accepted, decided_by).await
+    }
+
+    async fn resolve_challenges(&self, now: DateTime<Utc>) -> Vec<crate::model::challenge::Challenge> {
+        self.legacy_repo.resolve_challenges(now).await
+    }
+
+    async fn get_schedules(&self) -> Vec<crate::model::schedule::Schedule> {
+        self.legacy_repo.get_schedules().await
+    }
+
+    async fn save_schedule(&self, schedule: crate::model::schedule::Schedule) -> Result<crate::model::schedule::Schedule, String> {
+        self.legacy_repo.save_schedule(schedule).await
+    }
+
+    async fn remove_schedule(&self, task_id: u32) -> Result<(), String> {
+        self.legacy_repo.remove_schedule(task_id).await
+    }
+
+    async fn settle_schedules(&self, now: DateTime<Utc>) -> Vec<crate::model::ledger::PointTransaction> {
+        self.legacy_repo.settle_schedules(now).await
+    }
+
+    // Webhooks and dead letters are kept in the graph like the settings, as JSON including the secret
+    async fn get_webhooks(&self) -> Vec<Webhook> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MATCH (w:Webhook) RETURN w.record ORDER BY w.id;";
+        let records = Neo4JRepository::fetch_all_in_db(&mut client, "get_webhooks", statement, Params::from_iter(Vec::<(&str, i64)>::new())).await.unwrap_or(vec![]);
+
+        records.iter().filter_map(|record| match record.fields().get(0) {
+            Some(Value::String(record)) => serde_json::from_str::<WebhookRecord>(record).ok().map(Webhook::from),
+            _ => None
+        }).collect()
+    }
+
+    async fn add_webhook(&self, mut webhook: Webhook) -> Result<Webhook, String> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MATCH (w:Webhook) RETURN max(w.id);";
+        let records = Neo4JRepository::fetch_all_in_db(&mut client, "max_webhook_id", statement, Params::from_iter(Vec::<(&str, i64)>::new())).await?;
+        webhook.id = match records.first().and_then(|record| record.fields().get(0)) {
+            Some(Value::Integer(max_id)) => *max_id as u32 + 1,
+            _ => 1
+        };
+
+        let statement = "CREATE (:Webhook {id: $id, record: $record});";
+        let params = Params::from_iter(vec![
+            ("id", Value::from(webhook.id as i64)),
+            ("record", Value::from(serde_json::to_string(&WebhookRecord::from(&webhook)).map_err(|err| err.to_string())?))]);
+        Neo4JRepository::execute_in_db(&mut client, "add_webhook", statement, params).await?;
+        Ok(webhook)
+    }
+
+    async fn remove_webhook(&self, id: u32) -> Result<(), String> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MATCH (w:Webhook {id: $id}) DELETE w RETURN $id;";
+        let params = Params::from_iter(vec![("id", id as i64)]);
+        match Neo4JRepository::fetch_all_in_db(&mut client, "remove_webhook", statement, params).await? {
+            records if records.is_empty() => Err(format!("Webhook with id {} does not exist", id)),
+            _ => Ok(())
+        }
+    }
+
+    async fn get_dead_letters(&self) -> Vec<WebhookDelivery> {
+        let mut client = self.client.lock().await;
+
+        let statement = "MATCH (d:DeadLetter) RETURN d.record ORDER BY d.seq;";
+        let records = Neo4JRepository::fetch_all_in_db(&mut client, "get_dead_letters", statement, Params::from_iter(Vec::<(&str, i64)>::new())).await.unwrap_or(vec![]);
+
+        records.iter().filter_map(|record| match record.fields().get(0) {
+            Some(Value::String(record)) => serde_json::from_str::<WebhookDelivery>(record).ok(),
+            _ => None
+        }).collect()
+    }
+
+    async fn add_dead_letter(&self, delivery: WebhookDelivery) -> Result<(), String> {
+        let mut client = self.client.lock().await;
+
+        // Numbered instead of timestamped, several dead letters can be stored within the same millisecond
+        let statement = "OPTIONAL MATCH (last:DeadLetter) WITH coalesce(max(last.seq), 0) + 1 AS seq CREATE (:DeadLetter {seq: seq, record: $record});";
+        let params = Params::from_iter(vec![("record", serde_json::to_string(&delivery).map_err(|err| err.to_string())?)]);
+        Neo4JRepository::execute_in_db(&mut client, "add_dead_letter", statement, params).await?;
+
+        let statement = "MATCH (d:DeadLetter) WITH d ORDER BY d.seq DESC SKIP $keep DELETE d;";
+        let params = Params::from_iter(vec![("keep", DEAD_LETTER_LEN as i64)]);
+        Neo4JRepository::execute_in_db(&mut client, "trim_dead_letters", statement, params).await
+    }
+}
+#[cfg(test)]
+mod tests {
+    use bolt_client::Params;
+    use rocket::figment::{Figment, providers::Env};
+
+    use crate::config::app_config::AppConfig;
+    use crate::repository::conformance::repository_conformance_tests;
+
+    use super::Neo4JRepository;
+
+    // Uses the database given by TS_TEST_DATABASE_ADDRESS, TS_TEST_DATABASE_PRINCIPAL and TS_TEST_DATABASE_PASSWORD,
+    // everything but the migrations is deleted before each test
+    async fn fixture(_test_name: &str) -> Neo4JRepository {
+        let config = AppConfig::load(&Figment::new().merge(Env::prefixed("TS_TEST_"))).unwrap();
+        let repository = Neo4JRepository::connect(&config).await.unwrap();
+
+        let mut client = repository.client.lock().await;
+        Neo4JRepository::execute_in_db(&mut client, "clear", "MATCH (n) WHERE NOT n:Migration DETACH DELETE n;", Params::from_iter(Vec::<(&str, i64)>::new())).await.unwrap();
+        drop(client);
+
+        repository
+    }
+
+    repository_conformance_tests!(fixture, #[ignore = "requires a Neo4j test database, run with --ignored --test-threads=1"]);
+}
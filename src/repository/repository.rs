@@ -1,6 +1,15 @@
 use std::sync::{Arc, Mutex};
 
-use crate::{model::{User, Task, Session, session::LoginRequest, user::Team}, resource::http::responder::MessageResponder};
+use chrono::{DateTime, Utc};
+
+use crate::{model::{User, Task, Session, session::LoginRequest, user::Team, health::DependencyHealth, setting::{SettingValue, SettingChange}, snapshot::{RepositorySnapshot, TeamRecord}, bulk_import::ImportedUser, history::ScoreFilter, report::ScoreAggregate, reward::{Reward, Redemption}, ledger::PointTransaction, season::Season, trophy::Trophy, event::LeaderboardEntry, kudos::{Kudos, KudosLimits}, verification::ScoreClaim, challenge::Challenge, schedule::Schedule, webhook::{Webhook, WebhookDelivery}}, resource::http::responder::MessageResponder};
+use crate::config::app_config::{AppConfig, RepositoryKind};
+use crate::event::event_bus::EventBus;
+
+use super::{file_repository::FileRepository, legacy_repository::LegacyRepository, neo4j_repsitory::Neo4JRepository, sqlite_repository::SqliteRepository};
+
+// The repository as managed by Rocket, resources must not depend on a specific implementation
+pub type DynRepository = Arc<dyn Repository + Send + Sync>;
 
 #[async_trait]
 pub trait Repository {
@@ -12,13 +21,98 @@ pub trait Repository {
     async fn get_task<'a>(&'a self, id: u32) -> Option<Task>;
     async fn get_all_tasks<'a>(&'a self) -> Vec<Task>;
     async fn get_session<'a>(&'a self, session_id: &String) -> Option<Session>;
-    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u16, String>;
+    // Books the task's points in the ledger, returning the user's new lifetime points
+    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u64, String>;
     async fn create_and_add_user<'a>(&'a self, username: String, display_name: String, password: String, is_admin: bool) -> Result<Arc<Mutex<User>>, String>;
     async fn add_team<'a>(&'a self, team: Team) -> Option<u32>;
     async fn add_user_to_team<'a>(&'a self, team_name: &String, user_id: u32, manager: User) -> Result<(), String>;
     async fn add_user<'a>(&'a self, session: &Session, user: User) -> MessageResponder<u32>;
     async fn login<'a>(&'a self, login_request: LoginRequest) -> Result<Session, String>;
     async fn logout(&self, session_id: &String) -> Result<(), String>;
+    // Sessions started before the cutoff have expired, even if they are only removed on their next use
+    async fn count_sessions(&self, started_after: DateTime<Utc>) -> usize;
+    fn event_bus(&self) -> &EventBus;
+    async fn check_health(&self) -> Vec<DependencyHealth>;
+    async fn get_settings(&self) -> Vec<(String, SettingValue)>;
+    async fn save_setting(&self, change: SettingChange) -> Result<(), String>;
+    async fn get_setting_history(&self, key: &String) -> Vec<SettingChange>;
+    async fn set_password(&self, user_id: u32, password: String) -> Result<(), String>;
+    // Disabling a user ends all of their sessions
+    async fn set_user_enabled(&self, user_id: u32, enabled: bool) -> Result<(), String>;
+    // Creates the task if its id is 0 or unknown, otherwise replaces the existing one
+    async fn save_task(&self, task: Task) -> Result<Task, String>;
+    async fn export_snapshot(&self) -> Result<RepositorySnapshot, String>;
+    // Replaces everything held by the repository, sessions included
+    async fn import_snapshot(&self, snapshot: RepositorySnapshot) -> Result<(), String>;
+    // Adds all users (joining their teams) and tasks, or none of them if one cannot be added
+    async fn add_in_bulk(&self, users: Vec<ImportedUser>, tasks: Vec<Task>) -> Result<(), String>;
+    async fn get_all_teams(&self) -> Vec<TeamRecord>;
+    // Sums up the matching scores per user and task, ordered by user and task
+    async fn aggregate_scores(&self, filter: &ScoreFilter) -> Result<Vec<ScoreAggregate>, String>;
+    async fn get_all_rewards(&self) -> Vec<Reward>;
+    async fn get_reward(&self, id: u32) -> Option<Reward>;
+    // Creates the reward if its id is 0 or unknown, otherwise replaces the existing one
+    async fn save_reward(&self, reward: Reward) -> Result<Reward, String>;
+    // Checks balance and stock, takes one off the stock and books the cost in the ledger in one step
+    async fn redeem(&self, user_id: u32, reward_id: u32) -> Result<Redemption, String>;
+    async fn get_redemptions(&self, user_id: Option<u32>) -> Vec<Redemption>;
+    // Only pending redemptions can be decided, rejecting one refunds the points and restocks the reward
+    async fn decide_redemption(&self, redemption_id: u32, approved: bool, decided_by: String) -> Result<Redemption, String>;
+    // The transactions of the given user in the order they were booked, everyone's without a user
+    async fn get_ledger(&self, user_id: Option<u32>) -> Vec<PointTransaction>;
+    // Appends the transaction to the ledger with the next id, lifetime points and balance follow from it
+    async fn add_transaction(&self, transaction: PointTransaction) -> Result<PointTransaction, String>;
+    async fn get_seasons(&self) -> Vec<Season>;
+    // Earned points are booked for the running season, only one season can be open at a time
+    async fn start_season(&self, season: Season) -> Result<Season, String>;
+    // Archives the final standings and awards trophies to the best three
+    async fn end_season(&self, id: u32) -> Result<Season, String>;
+    // Ends the open seasons whose planned end has passed, returns the ended ones
+    async fn end_due_seasons(&self, now: DateTime<Utc>) -> Vec<Season>;
+    // Live for the running season, the archived standings for ended ones
+    async fn get_season_leaderboard(&self, id: u32) -> Option<Vec<LeaderboardEntry>>;
+    async fn get_trophies(&self, user_id: Option<u32>) -> Vec<Trophy>;
+    // Checks the limits against what the giver has given before and books the points for the recipient in one step
+    async fn give_kudos(&self, kudos: Kudos, limits: KudosLimits) -> Result<Kudos, String>;
+    // Given or received by the user, everyone's without a user
+    async fn get_kudos(&self, user_id: Option<u32>) -> Vec<Kudos>;
+    // Tasks that require verification are claimed instead of scored, nothing is booked until the claim is confirmed
+    async fn claim_score(&self, user_id: u32, task_id: u32) -> Result<ScoreClaim, String>;
+    async fn get_score_claims(&self, user_id: Option<u32>) -> Vec<ScoreClaim>;
+    // Confirming a pending claim adds the score and books its points, rejecting it only records the decision
+    async fn decide_score_claim(&self, claim_id: u32, confirmed: bool, decided_by: String) -> Result<ScoreClaim, String>;
+    // Marks the claims still pending from before the cutoff as expired and returns them
+    async fn expire_score_claims(&self, cutoff: DateTime<Utc>) -> Vec<ScoreClaim>;
+    // Participants and tasks have been checked by the caller
+    async fn propose_challenge(&self, challenge: Challenge) -> Result<Challenge, String>;
+    async fn get_challenges(&self) -> Vec<Challenge>;
+    async fn decide_challenge(&self, challenge_id: u32, accepted: bool, decided_by: String) -> Result<Challenge, String>;
+    // Resolves the accepted challenges that have ended by then, booking their stakes and trophies
+    async fn resolve_challenges(&self, now: DateTime<Utc>) -> Vec<Challenge>;
+    async fn get_schedules(&self) -> Vec<Schedule>;
+    // Replaces the schedule of the task if it has one already
+    async fn save_schedule(&self, schedule: Schedule) -> Result<Schedule, String>;
+    async fn remove_schedule(&self, task_id: u32) -> Result<(), String>;
+    // Books bonus and malus for the assignments due by then and returns the transactions
+    async fn settle_schedules(&self, now: DateTime<Utc>) -> Vec<PointTransaction>;
+    async fn get_webhooks(&self) -> Vec<Webhook>;
+    // Assigns the next free id to the webhook
+    async fn add_webhook(&self, webhook: Webhook) -> Result<Webhook, String>;
+    async fn remove_webhook(&self, id: u32) -> Result<(), String>;
+    // Oldest first
+    async fn get_dead_letters(&self) -> Vec<WebhookDelivery>;
+    // Drops the oldest dead letters beyond DEAD_LETTER_LEN
+    async fn add_dead_letter(&self, delivery: WebhookDelivery) -> Result<(), String>;
 }
 
 pub trait SizedRepository: Repository + Sized {}
+
+
+pub async fn create_repository(config: &AppConfig) -> Result<DynRepository, String> {
+    match config.repository {
+        RepositoryKind::Neo4j => Ok(Arc::new(Neo4JRepository::connect(config).await?)),
+        RepositoryKind::Memory => Ok(Arc::new(LegacyRepository::init_repository().await)),
+        RepositoryKind::File => Ok(Arc::new(FileRepository::open(&config.data_file).await?)),
+        RepositoryKind::Sqlite => Ok(Arc::new(SqliteRepository::open(&config.sqlite_file).await?)),
+    }
+}
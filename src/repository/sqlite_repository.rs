@@ -0,0 +1,1708 @@
+use std::{collections::HashSet, sync::{Arc, Mutex}, time::Instant};
+
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use rocket::tokio::task::block_in_place;
+use rusqlite::{Connection, OptionalExtension, Row, ToSql, params, types::Type};
+use tracing::{debug, error, info, info_span};
+
+use crate::{model::{User, Task, Score, Session, session::LoginRequest, user::Team, event::{ActivityEvent, LeaderboardEntry}, health::DependencyHealth, setting::{SettingValue, SettingChange}, snapshot::{RepositorySnapshot, UserRecord, TeamRecord, WebhookRecord, SNAPSHOT_VERSION}, bulk_import::ImportedUser, history::ScoreFilter, report::ScoreAggregate, reward::{Reward, Redemption, RedemptionStatus}, ledger::{PointTransaction, TransactionKind, Balance}, season::{Season, standings}, trophy::Trophy, kudos::{Kudos, KudosLimits}, verification::{ScoreClaim, ClaimStatus}, challenge::{Challenge, ChallengeStatus}, schedule::{Schedule, Recurrence}, webhook::{Webhook, WebhookDelivery, DeliveryStatus, DEAD_LETTER_LEN}}, resource::http::responder::MessageResponder};
+use crate::event::event_bus::EventBus;
+use crate::model::user::hash_password;
+use crate::metrics::metrics::Metrics;
+
+use super::{repository::Repository, demo_data::{demo_tasks, add_demo_data}};
+
+// Applied in order on startup, every version is recorded in the migrations table once it has been applied
+const MIGRATIONS: [(i64, &str); 13] = [
+    (1, "CREATE TABLE users (
+            id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL UNIQUE,
+            display_name TEXT NOT NULL,
+            is_admin INTEGER NOT NULL,
+            points INTEGER NOT NULL DEFAULT 0,
+            pwd_hash TEXT
+        );
+        CREATE TABLE tasks (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            enabled INTEGER NOT NULL
+        );
+        CREATE TABLE scores (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            task_id INTEGER NOT NULL REFERENCES tasks(id),
+            points INTEGER NOT NULL,
+            scored_at TEXT NOT NULL
+        );
+        CREATE INDEX scores_user_id ON scores(user_id);
+        CREATE TABLE sessions (
+            id TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            started TEXT NOT NULL,
+            refreshed TEXT NOT NULL
+        );
+        CREATE TABLE teams (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            manager_id INTEGER NOT NULL REFERENCES users(id)
+        );
+        CREATE TABLE team_members (
+            team_id INTEGER NOT NULL REFERENCES teams(id),
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            PRIMARY KEY (team_id, user_id)
+        );"),
+    (2, "CREATE TABLE setting_changes (
+            id INTEGER PRIMARY KEY,
+            key TEXT NOT NULL,
+            old_value TEXT NOT NULL,
+            new_value TEXT NOT NULL,
+            changed_by TEXT NOT NULL,
+            changed_at TEXT NOT NULL
+        );"),
+    (3, "ALTER TABLE users ADD COLUMN enabled INTEGER NOT NULL DEFAULT 1;"),
+    (4, "CREATE TABLE rewards (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            cost INTEGER NOT NULL,
+            stock INTEGER,
+            enabled INTEGER NOT NULL
+        );
+        CREATE TABLE redemptions (
+            id INTEGER PRIMARY KEY,
+            reward_id INTEGER NOT NULL REFERENCES rewards(id),
+            reward_name TEXT NOT NULL,
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            cost INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            requested_at TEXT NOT NULL,
+            decided_by TEXT,
+            decided_at TEXT
+        );"),
+    // The points of a user are derived from the ledger from now on, it starts with everything that happened so far
+    (5, "CREATE TABLE point_transactions (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            kind TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            reference TEXT,
+            created_by TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX point_transactions_user_id ON point_transactions(user_id);
+        INSERT INTO point_transactions (user_id, kind, amount, reference, created_by, created_at)
+            SELECT user_id, kind, amount, reference, created_by, created_at FROM (
+                SELECT s.id AS position, s.user_id, 'score' AS kind, s.points AS amount, t.name AS reference, NULL AS created_by, s.scored_at AS created_at
+                    FROM scores s JOIN tasks t ON t.id = s.task_id
+                UNION ALL SELECT id, user_id, 'redeem', -cost, reward_name, NULL, requested_at FROM redemptions
+                UNION ALL SELECT id, user_id, 'refund', cost, reward_name, decided_by, decided_at FROM redemptions WHERE status = 'rejected'
+            ) ORDER BY created_at, position;
+        INSERT INTO point_transactions (user_id, kind, amount, reference, created_at)
+            SELECT id, 'adjustment', points - scored, 'Points without a score', strftime('%Y-%m-%d %H:%M:%S+00:00', 'now') FROM (
+                SELECT u.id, u.points, COALESCE((SELECT SUM(s.points) FROM scores s WHERE s.user_id = u.id), 0) AS scored FROM users u
+            ) WHERE points <> scored;
+        ALTER TABLE users DROP COLUMN points;
+        CREATE VIEW user_points AS
+            SELECT u.id AS user_id, MAX(0, COALESCE(SUM(p.amount), 0)) AS points FROM users u
+            LEFT JOIN point_transactions p ON p.user_id = u.id AND p.kind NOT IN ('redeem', 'refund')
+            GROUP BY u.id;"),
+    (6, "CREATE TABLE seasons (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            started_at TEXT NOT NULL,
+            ended_at TEXT
+        );
+        CREATE TABLE season_standings (
+            season_id INTEGER NOT NULL REFERENCES seasons(id),
+            rank INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            display_name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            PRIMARY KEY (season_id, user_id)
+        );
+        CREATE TABLE trophies (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            name TEXT NOT NULL,
+            season_id INTEGER REFERENCES seasons(id),
+            awarded_at TEXT NOT NULL
+        );
+        ALTER TABLE point_transactions ADD COLUMN season_id INTEGER REFERENCES seasons(id);"),
+    (7, "CREATE TABLE kudos (
+            id INTEGER PRIMARY KEY,
+            from_user_id INTEGER NOT NULL REFERENCES users(id),
+            to_user_id INTEGER NOT NULL REFERENCES users(id),
+            message TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            given_at TEXT NOT NULL
+        );
+        CREATE INDEX kudos_from_user_id ON kudos(from_user_id);"),
+    (8, "ALTER TABLE tasks ADD COLUMN requires_verification INTEGER NOT NULL DEFAULT 0;
+        CREATE TABLE score_claims (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            task_id INTEGER NOT NULL REFERENCES tasks(id),
+            task_name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            claimed_at TEXT NOT NULL,
+            decided_by TEXT,
+            decided_at TEXT
+        );"),
+    // Participants, tasks and results are kept as JSON, they are only ever read as a whole
+    (9, "CREATE TABLE challenges (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            challenger TEXT NOT NULL,
+            opponent TEXT NOT NULL,
+            task_ids TEXT NOT NULL,
+            starts_at TEXT NOT NULL,
+            ends_at TEXT NOT NULL,
+            stake INTEGER NOT NULL,
+            award_trophy INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            proposed_by TEXT NOT NULL,
+            decided_by TEXT,
+            results TEXT NOT NULL,
+            winner TEXT NOT NULL
+        );"),
+    // The rota is kept as JSON, the recurrence as the rule it was given as
+    (10, "CREATE TABLE schedules (
+            task_id INTEGER PRIMARY KEY REFERENCES tasks(id),
+            recurrence TEXT NOT NULL,
+            rota TEXT NOT NULL,
+            starts_at TEXT NOT NULL,
+            bonus INTEGER NOT NULL,
+            malus INTEGER NOT NULL,
+            settled_until TEXT NOT NULL
+        );"),
+    (11, "ALTER TABLE seasons ADD COLUMN ends_at TEXT;"),
+    // The rotas of schedules settled before restart with the first member
+    (12, "ALTER TABLE schedules ADD COLUMN settled_turns INTEGER NOT NULL DEFAULT 0;"),
+    // Delivery ids start over with every restart, so dead letters are kept in the order they arrived instead
+    (13, "CREATE TABLE webhooks (
+            id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL,
+            event_types TEXT NOT NULL,
+            enabled INTEGER NOT NULL,
+            secret TEXT NOT NULL
+        );
+        CREATE TABLE webhook_dead_letters (
+            seq INTEGER PRIMARY KEY,
+            delivery_id INTEGER NOT NULL,
+            webhook_id INTEGER NOT NULL,
+            event_id INTEGER NOT NULL,
+            event_type TEXT NOT NULL,
+            attempts INTEGER NOT NULL,
+            last_response_status INTEGER,
+            last_error TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );"),
+];
+
+const TASK_COLUMNS: &str = "id, name, points, enabled, requires_verification";
+const USER_COLUMNS: &str = "id, username, display_name, is_admin, (SELECT points FROM user_points WHERE user_id = users.id), pwd_hash, enabled";
+const REWARD_COLUMNS: &str = "id, name, cost, stock, enabled";
+const REDEMPTION_COLUMNS: &str = "id, reward_id, reward_name, user_id, cost, status, requested_at, decided_by, decided_at";
+const TRANSACTION_COLUMNS: &str = "id, user_id, kind, amount, reference, created_by, created_at, season_id";
+const TROPHY_COLUMNS: &str = "id, user_id, name, season_id, awarded_at";
+const KUDOS_COLUMNS: &str = "id, from_user_id, to_user_id, message, points, given_at";
+const CHALLENGE_COLUMNS: &str = "id, name, challenger, opponent, task_ids, starts_at, ends_at, stake, award_trophy, status, proposed_by, decided_by, results, winner";
+const SCHEDULE_COLUMNS: &str = "task_id, recurrence, rota, starts_at, bonus, malus, settled_until, settled_turns";
+const WEBHOOK_COLUMNS: &str = "id, url, event_types, enabled, secret";
+const DEAD_LETTER_COLUMNS: &str = "delivery_id, webhook_id, event_id, event_type, attempts, last_response_status, last_error, created_at, updated_at";
+const CLAIM_COLUMNS: &str = "id, user_id, task_id, task_name, points, status, claimed_at, decided_by, decided_at";
+
+pub struct SqliteRepository {
+    connection: Mutex<Connection>,
+    event_bus: EventBus,
+}
+
+impl SqliteRepository {
+
+    // A new database starts empty, the demo data (with its well-known passwords) is only added by 'taskscore-admin seed'
+    pub async fn open(path: &str) -> Result<SqliteRepository, String> {
+        let connection = Connection::open(path).map_err(|err| format!("Unable to open '{}': {}", path, err))?;
+        let repository = SqliteRepository::create(connection)?;
+
+        let is_new = repository.run("count_users", |connection| connection.query_row("SELECT COUNT(*) FROM users", [], |row| row.get::<_, i64>(0)))? == 0;
+        if is_new {
+            info!(path, "Database has no users yet, add them with taskscore-admin");
+        }
+
+        Ok(repository)
+    }
+
+    // Creates an in-memory database filled with demo data
+    pub async fn init_repository() -> SqliteRepository {
+        let repository = SqliteRepository::create(Connection::open_in_memory().unwrap()).unwrap();
+        repository.add_demo_data().await.unwrap();
+
+        repository
+    }
+
+    fn create(connection: Connection) -> Result<SqliteRepository, String> {
+        connection.execute_batch("PRAGMA foreign_keys = ON;").map_err(|err| err.to_string())?;
+
+        let repository = SqliteRepository { connection: Mutex::new(connection), event_bus: EventBus::new() };
+        repository.migrate()?;
+
+        Ok(repository)
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        self.run("create_migrations", |connection| connection.execute_batch("CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL);"))?;
+        let applied_version = self.get_schema_version()?;
+
+        for (version, statement) in MIGRATIONS.iter().filter(|(version, _)| *version > applied_version) {
+            info!(version, "Applying database migration");
+            self.run("migration", |connection| {
+                let transaction = connection.transaction()?;
+                transaction.execute_batch(statement)?;
+                transaction.execute("INSERT INTO migrations (version, applied_at) VALUES (?1, ?2)", params![version, Utc::now()])?;
+                transaction.commit()
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn get_schema_version(&self) -> Result<i64, String> {
+        self.run("get_schema_version", |connection| connection.query_row("SELECT COALESCE(MAX(version), 0) FROM migrations", [], |row| row.get(0)))
+    }
+
+    async fn add_demo_data(&self) -> Result<(), String> {
+        self.run("add_demo_tasks", |connection| {
+            for task in demo_tasks() {
+                SqliteRepository::insert_task(connection, &task)?;
+            }
+            Ok(())
+        })?;
+        add_demo_data(self).await;
+
+        Ok(())
+    }
+
+    // Runs the given statements on the connection, measuring the time it takes under the given statement name.
+    // The statements block, so the executor hands its other tasks to another thread in the meantime
+    fn run<T>(&self, statement_name: &str, statements: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+        let _span = info_span!("sql", statement = statement_name).entered();
+        let start = Instant::now();
+        let result = block_in_place(|| statements(&mut self.connection.lock().unwrap())).map_err(|err| err.to_string());
+
+        // Parameters are never logged, as they may contain password hashes
+        let duration = start.elapsed();
+        Metrics::global().observe_db_query(statement_name, duration, result.is_ok());
+        match &result {
+            Ok(_) => debug!(statement = statement_name, duration_ms = duration.as_millis() as u64, "SQL statement executed"),
+            Err(err_msg) => error!(statement = statement_name, duration_ms = duration.as_millis() as u64, error = %err_msg, "SQL statement failed"),
+        }
+
+        result
+    }
+
+    fn find_user(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Option<User>> {
+        let statement = format!("SELECT {} FROM users WHERE {}", USER_COLUMNS, condition);
+        let user = connection.query_row(&statement, [value], SqliteRepository::user_from_row).optional()?;
+
+        match user {
+            Some(mut user) => {
+                user.scores = SqliteRepository::find_scores(connection, user.id)?;
+                Ok(Some(user))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn find_all_users(connection: &Connection) -> rusqlite::Result<Vec<User>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM users ORDER BY id", USER_COLUMNS))?;
+        let users: Vec<User> = statement.query_map([], SqliteRepository::user_from_row)?.collect::<rusqlite::Result<_>>()?;
+
+        let mut users_with_scores = vec![];
+        for mut user in users {
+            user.scores = SqliteRepository::find_scores(connection, user.id)?;
+            users_with_scores.push(user);
+        }
+        Ok(users_with_scores)
+    }
+
+    fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+        Ok(User {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            display_name: row.get(2)?,
+            is_admin: row.get(3)?,
+            points: row.get(4)?,
+            enabled: row.get(6)?,
+            scores: vec![],
+            pwd_hash_components: row.get(5)?,
+        })
+    }
+
+    fn find_scores(connection: &Connection, user_id: u32) -> rusqlite::Result<Vec<Score>> {
+        let mut statement = connection.prepare(
+            "SELECT t.id, t.name, t.points, t.enabled, t.requires_verification, s.points, s.scored_at FROM scores s JOIN tasks t ON t.id = s.task_id WHERE s.user_id = ?1 ORDER BY s.id")?;
+        let scores = statement.query_map([user_id], |row| Ok(Score {
+            task: SqliteRepository::task_from_row(row)?,
+            points: row.get(5)?,
+            scored_at: row.get(6)?,
+        }))?;
+
+        scores.collect()
+    }
+
+    fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+        Ok(Task { id: row.get(0)?, name: row.get(1)?, points: row.get(2)?, enabled: row.get(3)?, requires_verification: row.get(4)? })
+    }
+
+    fn find_task(connection: &Connection, id: u32) -> rusqlite::Result<Option<Task>> {
+        connection.query_row(&format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS), [id], SqliteRepository::task_from_row).optional()
+    }
+
+    fn find_all_tasks(connection: &Connection) -> rusqlite::Result<Vec<Task>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM tasks ORDER BY id", TASK_COLUMNS))?;
+        let tasks = statement.query_map([], SqliteRepository::task_from_row)?;
+        tasks.collect()
+    }
+
+    fn insert_task(connection: &Connection, task: &Task) -> rusqlite::Result<usize> {
+        connection.execute("INSERT INTO tasks (id, name, points, enabled, requires_verification) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![task.id, task.name, task.points, task.enabled, task.requires_verification])
+    }
+
+    fn find_all_teams(connection: &Connection) -> rusqlite::Result<Vec<TeamRecord>> {
+        let mut statement = connection.prepare("SELECT id, name, manager_id FROM teams ORDER BY id")?;
+        let teams: Vec<(u32, String, u32)> = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?.collect::<rusqlite::Result<_>>()?;
+
+        let mut records = vec![];
+        for (id, name, manager_id) in teams {
+            records.push(TeamRecord { id, name, manager_id, member_ids: SqliteRepository::find_team_member_ids(connection, id)? });
+        }
+        Ok(records)
+    }
+
+    fn find_all_setting_changes(connection: &Connection) -> rusqlite::Result<Vec<SettingChange>> {
+        let mut statement = connection.prepare("SELECT key, old_value, new_value, changed_by, changed_at FROM setting_changes ORDER BY id")?;
+        let changes = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?, row.get::<_, DateTime<Utc>>(4)?)))?;
+
+        let mut setting_changes = vec![];
+        for change in changes {
+            let (key, old_value, new_value, changed_by, changed_at) = change?;
+            let to_value = |value: &String| serde_json::from_str::<SettingValue>(value).map_err(|err| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(err)));
+            setting_changes.push(SettingChange { key, old_value: to_value(&old_value)?, new_value: to_value(&new_value)?, changed_by, changed_at });
+        }
+        Ok(setting_changes)
+    }
+
+    fn reward_from_row(row: &Row) -> rusqlite::Result<Reward> {
+        Ok(Reward { id: row.get(0)?, name: row.get(1)?, cost: row.get(2)?, stock: row.get(3)?, enabled: row.get(4)? })
+    }
+
+    // Matches everything with the condition '?1 IS NULL' and None as value
+    fn find_rewards(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<Reward>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM rewards WHERE {} ORDER BY id", REWARD_COLUMNS, condition))?;
+        let rewards = statement.query_map([value], SqliteRepository::reward_from_row)?;
+        rewards.collect()
+    }
+
+    fn redemption_from_row(row: &Row) -> rusqlite::Result<Redemption> {
+        let status: String = row.get(5)?;
+        let status = RedemptionStatus::parse(&status)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, format!("'{}' is no redemption status", status).into()))?;
+
+        Ok(Redemption { id: row.get(0)?, reward_id: row.get(1)?, reward_name: row.get(2)?, user_id: row.get(3)?, cost: row.get(4)?,
+            status, requested_at: row.get(6)?, decided_by: row.get(7)?, decided_at: row.get(8)? })
+    }
+
+    fn insert_reward(connection: &Connection, reward: &Reward) -> rusqlite::Result<usize> {
+        connection.execute("INSERT INTO rewards (id, name, cost, stock, enabled) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![reward.id, reward.name, reward.cost, reward.stock, reward.enabled])
+    }
+
+    fn insert_redemption(connection: &Connection, redemption: &Redemption) -> rusqlite::Result<usize> {
+        connection.execute("INSERT INTO redemptions (id, reward_id, reward_name, user_id, cost, status, requested_at, decided_by, decided_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![redemption.id, redemption.reward_id, redemption.reward_name, redemption.user_id, redemption.cost, redemption.status.as_str(),
+                redemption.requested_at, redemption.decided_by, redemption.decided_at])
+    }
+
+    fn find_redemptions(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<Redemption>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM redemptions WHERE {} ORDER BY id", REDEMPTION_COLUMNS, condition))?;
+        let redemptions = statement.query_map([value], SqliteRepository::redemption_from_row)?;
+        redemptions.collect()
+    }
+
+    fn claim_from_row(row: &Row) -> rusqlite::Result<ScoreClaim> {
+        let status: String = row.get(5)?;
+        let status = ClaimStatus::parse(&status)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, format!("'{}' is no claim status", status).into()))?;
+
+        Ok(ScoreClaim { id: row.get(0)?, user_id: row.get(1)?, task_id: row.get(2)?, task_name: row.get(3)?, points: row.get(4)?,
+            status, claimed_at: row.get(6)?, decided_by: row.get(7)?, decided_at: row.get(8)? })
+    }
+
+    // Claims without an id get the next one
+    fn insert_claim(connection: &Connection, claim: &ScoreClaim) -> rusqlite::Result<u32> {
+        let id = Some(claim.id).filter(|id| *id != 0);
+        connection.execute("INSERT INTO score_claims (id, user_id, task_id, task_name, points, status, claimed_at, decided_by, decided_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![id, claim.user_id, claim.task_id, claim.task_name, claim.points, claim.status.as_str(), claim.claimed_at, claim.decided_by, claim.decided_at])?;
+        Ok(connection.last_insert_rowid() as u32)
+    }
+
+    fn update_claim(connection: &Connection, claim: &ScoreClaim) -> rusqlite::Result<usize> {
+        connection.execute("UPDATE score_claims SET status = ?1, decided_by = ?2, decided_at = ?3 WHERE id = ?4",
+            params![claim.status.as_str(), claim.decided_by, claim.decided_at, claim.id])
+    }
+
+    fn json_from_row<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+        let value: String = row.get(index)?;
+        serde_json::from_str(&value).map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+    }
+
+    fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+        serde_json::to_string(value).map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+    }
+
+    fn challenge_from_row(row: &Row) -> rusqlite::Result<Challenge> {
+        let status: String = row.get(9)?;
+        let status = ChallengeStatus::parse(&status)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(9, Type::Text, format!("'{}' is no challenge status", status).into()))?;
+
+        Ok(Challenge { id: row.get(0)?, name: row.get(1)?, challenger: SqliteRepository::json_from_row(row, 2)?, opponent: SqliteRepository::json_from_row(row, 3)?,
+            task_ids: SqliteRepository::json_from_row(row, 4)?, starts_at: row.get(5)?, ends_at: row.get(6)?, stake: row.get(7)?, award_trophy: row.get(8)?,
+            status, proposed_by: row.get(10)?, decided_by: row.get(11)?, results: SqliteRepository::json_from_row(row, 12)?, winner: SqliteRepository::json_from_row(row, 13)? })
+    }
+
+    // Challenges without an id get the next one
+    fn insert_challenge(connection: &Connection, challenge: &Challenge) -> rusqlite::Result<u32> {
+        let id = Some(challenge.id).filter(|id| *id != 0);
+        connection.execute(&format!("INSERT INTO challenges ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)", CHALLENGE_COLUMNS),
+            params![id, challenge.name, SqliteRepository::to_json(&challenge.challenger)?, SqliteRepository::to_json(&challenge.opponent)?,
+                SqliteRepository::to_json(&challenge.task_ids)?, challenge.starts_at, challenge.ends_at, challenge.stake, challenge.award_trophy,
+                challenge.status.as_str(), challenge.proposed_by, challenge.decided_by, SqliteRepository::to_json(&challenge.results)?, SqliteRepository::to_json(&challenge.winner)?])?;
+        Ok(connection.last_insert_rowid() as u32)
+    }
+
+    fn update_challenge(connection: &Connection, challenge: &Challenge) -> rusqlite::Result<usize> {
+        connection.execute("UPDATE challenges SET status = ?1, decided_by = ?2, results = ?3, winner = ?4 WHERE id = ?5",
+            params![challenge.status.as_str(), challenge.decided_by, SqliteRepository::to_json(&challenge.results)?, SqliteRepository::to_json(&challenge.winner)?, challenge.id])
+    }
+
+    fn find_challenges(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<Challenge>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM challenges WHERE {} ORDER BY id", CHALLENGE_COLUMNS, condition))?;
+        let challenges = statement.query_map([value], SqliteRepository::challenge_from_row)?;
+        challenges.collect()
+    }
+
+    fn schedule_from_row(row: &Row) -> rusqlite::Result<Schedule> {
+        let rule: String = row.get(1)?;
+        let recurrence = Recurrence::parse(&rule).map_err(|err_msg| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, err_msg.into()))?;
+        Ok(Schedule { task_id: row.get(0)?, recurrence, rota: SqliteRepository::json_from_row(row, 2)?, starts_at: row.get(3)?, bonus: row.get(4)?, malus: row.get(5)?,
+            settled_until: row.get(6)?, settled_turns: row.get(7)? })
+    }
+
+    fn insert_schedule(connection: &Connection, schedule: &Schedule) -> rusqlite::Result<usize> {
+        connection.execute(&format!("INSERT OR REPLACE INTO schedules ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", SCHEDULE_COLUMNS),
+            params![schedule.task_id, String::from(schedule.recurrence.clone()), SqliteRepository::to_json(&schedule.rota)?, schedule.starts_at, schedule.bonus,
+                schedule.malus, schedule.settled_until, schedule.settled_turns])
+    }
+
+    fn find_all_webhooks(connection: &Connection) -> rusqlite::Result<Vec<Webhook>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM webhooks ORDER BY id", WEBHOOK_COLUMNS))?;
+        let webhooks = statement.query_map([], |row| Ok(Webhook { id: row.get(0)?, url: row.get(1)?, event_types: SqliteRepository::json_from_row(row, 2)?, enabled: row.get(3)?, secret: row.get(4)? }))?;
+        webhooks.collect()
+    }
+
+    // Webhooks without an id get the next one
+    fn insert_webhook(connection: &Connection, webhook: &Webhook) -> rusqlite::Result<u32> {
+        let id = Some(webhook.id).filter(|id| *id != 0);
+        connection.execute(&format!("INSERT INTO webhooks ({}) VALUES (?1, ?2, ?3, ?4, ?5)", WEBHOOK_COLUMNS),
+            params![id, webhook.url, SqliteRepository::to_json(&webhook.event_types)?, webhook.enabled, webhook.secret])?;
+        Ok(connection.last_insert_rowid() as u32)
+    }
+
+    fn find_all_dead_letters(connection: &Connection) -> rusqlite::Result<Vec<WebhookDelivery>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM webhook_dead_letters ORDER BY seq", DEAD_LETTER_COLUMNS))?;
+        let dead_letters = statement.query_map([], |row| Ok(WebhookDelivery { id: row.get(0)?, webhook_id: row.get(1)?, event_id: row.get(2)?, event_type: row.get(3)?,
+            status: DeliveryStatus::DeadLetter, attempts: row.get(4)?, last_response_status: row.get(5)?, last_error: row.get(6)?, created_at: row.get(7)?, updated_at: row.get(8)? }))?;
+        dead_letters.collect()
+    }
+
+    fn insert_dead_letter(connection: &Connection, delivery: &WebhookDelivery) -> rusqlite::Result<usize> {
+        connection.execute(&format!("INSERT INTO webhook_dead_letters ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)", DEAD_LETTER_COLUMNS),
+            params![delivery.id, delivery.webhook_id, delivery.event_id, delivery.event_type, delivery.attempts, delivery.last_response_status, delivery.last_error,
+                delivery.created_at, delivery.updated_at])
+    }
+
+    fn find_all_schedules(connection: &Connection) -> rusqlite::Result<Vec<Schedule>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM schedules ORDER BY task_id", SCHEDULE_COLUMNS))?;
+        let schedules = statement.query_map([], SqliteRepository::schedule_from_row)?;
+        schedules.collect()
+    }
+
+    fn find_claims(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<ScoreClaim>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM score_claims WHERE {} ORDER BY id", CLAIM_COLUMNS, condition))?;
+        let claims = statement.query_map([value], SqliteRepository::claim_from_row)?;
+        claims.collect()
+    }
+
+    fn transaction_from_row(row: &Row) -> rusqlite::Result<PointTransaction> {
+        let kind: String = row.get(2)?;
+        let kind = TransactionKind::parse(&kind)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, format!("'{}' is no transaction kind", kind).into()))?;
+
+        Ok(PointTransaction { id: row.get(0)?, user_id: row.get(1)?, kind, amount: row.get(3)?, reference: row.get(4)?, created_by: row.get(5)?, created_at: row.get(6)?, season_id: row.get(7)? })
+    }
+
+    // Transactions without an id get the next one
+    fn insert_transaction(connection: &Connection, transaction: &PointTransaction) -> rusqlite::Result<u64> {
+        let id = Some(transaction.id).filter(|id| *id != 0);
+        connection.execute("INSERT INTO point_transactions (id, user_id, kind, amount, reference, created_by, created_at, season_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![id, transaction.user_id, transaction.kind.as_str(), transaction.amount, transaction.reference, transaction.created_by, transaction.created_at, transaction.season_id])?;
+        Ok(connection.last_insert_rowid() as u64)
+    }
+
+    fn find_transactions(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<PointTransaction>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM point_transactions WHERE {} ORDER BY id", TRANSACTION_COLUMNS, condition))?;
+        let transactions = statement.query_map([value], SqliteRepository::transaction_from_row)?;
+        transactions.collect()
+    }
+
+    fn lifetime_points(connection: &Connection, user_id: u32) -> rusqlite::Result<u64> {
+        connection.query_row("SELECT points FROM user_points WHERE user_id = ?1", [user_id], |row| row.get(0))
+    }
+
+    fn active_season_id(connection: &Connection) -> rusqlite::Result<Option<u32>> {
+        let now = Utc::now();
+        Ok(SqliteRepository::find_seasons(connection, "ended_at IS NULL AND ?1 IS NULL", &None::<u32>)?.into_iter().find(|season| season.is_running(now)).map(|season| season.id))
+    }
+
+    fn find_seasons(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<Season>> {
+        let mut statement = connection.prepare(&format!("SELECT id, name, started_at, ended_at, ends_at FROM seasons WHERE {} ORDER BY id", condition))?;
+        let seasons = statement.query_map([value], |row| Ok(Season { id: row.get(0)?, name: row.get(1)?, start: row.get(2)?, end: row.get(3)?, ends_at: row.get(4)?, standings: vec![] }))?;
+        let mut seasons = seasons.collect::<rusqlite::Result<Vec<Season>>>()?;
+
+        let mut statement = connection.prepare("SELECT rank, user_id, display_name, points FROM season_standings WHERE season_id = ?1 ORDER BY rank")?;
+        for season in seasons.iter_mut() {
+            let standings = statement.query_map([season.id], |row| Ok(LeaderboardEntry { rank: row.get(0)?, user_id: row.get(1)?, display_name: row.get(2)?, points: row.get(3)? }))?;
+            season.standings = standings.collect::<rusqlite::Result<Vec<LeaderboardEntry>>>()?;
+        }
+        Ok(seasons)
+    }
+
+    fn insert_season(connection: &Connection, season: &Season) -> rusqlite::Result<()> {
+        connection.execute("INSERT INTO seasons (id, name, started_at, ended_at, ends_at) VALUES (?1, ?2, ?3, ?4, ?5)", params![season.id, season.name, season.start, season.end, season.ends_at])?;
+        SqliteRepository::insert_standings(connection, season)
+    }
+
+    fn insert_standings(connection: &Connection, season: &Season) -> rusqlite::Result<()> {
+        for entry in season.standings.iter() {
+            connection.execute("INSERT INTO season_standings (season_id, rank, user_id, display_name, points) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![season.id, entry.rank, entry.user_id, entry.display_name, entry.points])?;
+        }
+        Ok(())
+    }
+
+    fn season_standings(connection: &Connection, season_id: u32) -> rusqlite::Result<Vec<LeaderboardEntry>> {
+        let mut statement = connection.prepare("SELECT id, display_name FROM users ORDER BY id")?;
+        let users = statement.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?.collect::<rusqlite::Result<Vec<(u32, String)>>>()?;
+        let ledger = SqliteRepository::find_transactions(connection, "season_id = ?1", &season_id)?;
+        Ok(standings(season_id, &users, &ledger))
+    }
+
+    fn find_trophies(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<Trophy>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM trophies WHERE {} ORDER BY id", TROPHY_COLUMNS, condition))?;
+        let trophies = statement.query_map([value], |row| Ok(Trophy { id: row.get(0)?, user_id: row.get(1)?, name: row.get(2)?, season_id: row.get(3)?, awarded_at: row.get(4)? }))?;
+        trophies.collect()
+    }
+
+    // Trophies without an id get the next one
+    fn insert_trophy(connection: &Connection, trophy: &Trophy) -> rusqlite::Result<u32> {
+        let id = Some(trophy.id).filter(|id| *id != 0);
+        connection.execute("INSERT INTO trophies (id, user_id, name, season_id, awarded_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, trophy.user_id, trophy.name, trophy.season_id, trophy.awarded_at])?;
+        Ok(connection.last_insert_rowid() as u32)
+    }
+
+    fn find_kudos(connection: &Connection, condition: &str, value: &dyn ToSql) -> rusqlite::Result<Vec<Kudos>> {
+        let mut statement = connection.prepare(&format!("SELECT {} FROM kudos WHERE {} ORDER BY id", KUDOS_COLUMNS, condition))?;
+        let kudos = statement.query_map([value], |row| Ok(Kudos { id: row.get(0)?, from_user_id: row.get(1)?, to_user_id: row.get(2)?, message: row.get(3)?, points: row.get(4)?, at: row.get(5)? }))?;
+        kudos.collect()
+    }
+
+    // Kudos without an id get the next one
+    fn insert_kudos(connection: &Connection, kudos: &Kudos) -> rusqlite::Result<u32> {
+        let id = Some(kudos.id).filter(|id| *id != 0);
+        connection.execute("INSERT INTO kudos (id, from_user_id, to_user_id, message, points, given_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, kudos.from_user_id, kudos.to_user_id, kudos.message, kudos.points, kudos.at])?;
+        Ok(connection.last_insert_rowid() as u32)
+    }
+
+    fn find_team(connection: &Connection, name: &String) -> rusqlite::Result<Option<Team>> {
+        let team = connection.query_row("SELECT id, name, manager_id FROM teams WHERE name = ?1", [name],
+            |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?, row.get::<_, u32>(2)?))).optional()?;
+        let (id, name, manager_id) = match team {
+            Some(team) => team,
+            None => return Ok(None)
+        };
+
+        let mut members = vec![];
+        for member_id in SqliteRepository::find_team_member_ids(connection, id)? {
+            if let Some(member) = SqliteRepository::find_user(connection, "id = ?1", &member_id)? {
+                members.push(Arc::new(Mutex::new(member)));
+            }
+        }
+        let member_ids: HashSet<u32> = members.iter().map(|member| member.lock().unwrap().id).collect();
+
+        Ok(Some(Team { id, name, manager_id, members, member_ids }))
+    }
+
+    fn find_team_member_ids(connection: &Connection, team_id: u32) -> rusqlite::Result<Vec<u32>> {
+        let mut statement = connection.prepare("SELECT user_id FROM team_members WHERE team_id = ?1 ORDER BY user_id")?;
+        let member_ids = statement.query_map([team_id], |row| row.get(0))?;
+        member_ids.collect()
+    }
+
+    fn team_ids_of(connection: &Connection, user_id: u32) -> rusqlite::Result<Vec<u32>> {
+        let mut statement = connection.prepare("SELECT team_id FROM team_members WHERE user_id = ?1 ORDER BY team_id")?;
+        let team_ids = statement.query_map([user_id], |row| row.get(0))?;
+        team_ids.collect()
+    }
+
+    fn leaderboard(connection: &Connection) -> rusqlite::Result<Vec<LeaderboardEntry>> {
+        let mut statement = connection.prepare("SELECT u.id, u.display_name, p.points FROM users u JOIN user_points p ON p.user_id = u.id ORDER BY p.points DESC, u.id ASC")?;
+        let entries = statement.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?, row.get::<_, u64>(2)?)))?;
+
+        entries.enumerate()
+            .map(|(index, entry)| entry.map(|(user_id, display_name, points)| LeaderboardEntry { rank: index as u32 + 1, user_id, display_name, points }))
+            .collect()
+    }
+
+    fn add_user_private(&self, user: User) -> Result<User, String> {
+        let new_user = self.run("add_user", |connection| {
+            if SqliteRepository::find_user(connection, "username = ?1", &user.username)?.is_some() {
+                return Ok(None);
+            }
+
+            connection.execute("INSERT INTO users (username, display_name, is_admin, pwd_hash, enabled) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![user.username, user.display_name, user.is_admin, user.pwd_hash_components, user.enabled])?;
+            SqliteRepository::find_user(connection, "id = ?1", &connection.last_insert_rowid())
+        })?.ok_or("Username is not available".to_owned())?;
+
+        self.event_bus.publish(vec![], ActivityEvent::UserCreated { user_id: new_user.id, username: new_user.username.clone(), display_name: new_user.display_name.clone() });
+
+        Ok(new_user)
+    }
+}
+
+#[async_trait]
+impl Repository for SqliteRepository {
+
+    async fn get_user<'a>(&'a self, id: u32) -> Option<User> {
+        self.run("get_user", |connection| SqliteRepository::find_user(connection, "id = ?1", &id)).ok().flatten()
+    }
+
+    async fn find_user_by_username<'a>(&'a self, username: &String) -> Option<Arc<Mutex<User>>> {
+        self.find_user_by_username_const(username).await.map(|user| Arc::new(Mutex::new(user)))
+    }
+
+    async fn find_user_by_username_const<'a>(&'a self, username: &String) -> Option<User> {
+        self.run("find_user_by_username", |connection| SqliteRepository::find_user(connection, "username = ?1", username)).ok().flatten()
+    }
+
+    async fn get_all_users<'a>(&'a self) -> Vec<User> {
+        self.run("get_all_users", |connection| SqliteRepository::find_all_users(connection)).unwrap_or(vec![])
+    }
+
+    async fn get_task<'a>(&'a self, id: u32) -> Option<Task> {
+        self.run("get_task", |connection| SqliteRepository::find_task(connection, id)).ok().flatten()
+    }
+
+    async fn get_all_tasks<'a>(&'a self) -> Vec<Task> {
+        self.run("get_all_tasks", |connection| SqliteRepository::find_all_tasks(connection)).unwrap_or(vec![])
+    }
+
+    async fn get_session<'a>(&'a self, session_id: &String) -> Option<Session> {
+        self.run("get_session", |connection| {
+            let session = connection.query_row("SELECT user_id, started, refreshed FROM sessions WHERE id = ?1", [session_id],
+                |row| Ok((row.get::<_, u32>(0)?, row.get::<_, DateTime<Utc>>(1)?, row.get::<_, DateTime<Utc>>(2)?))).optional()?;
+            let (user_id, started, refreshed) = match session {
+                Some(session) => session,
+                None => return Ok(None)
+            };
+
+            let user = SqliteRepository::find_user(connection, "id = ?1", &user_id)?;
+            Ok(user.map(|user| Session { id: session_id.clone(), user: Arc::new(Mutex::new(user)), started, refreshed }))
+        }).ok().flatten()
+    }
+
+    async fn score<'a>(&'a self, user_id: u32, task_id: u32) -> Result<u64, String> {
+        let scored = self.run("score", |connection| {
+            let transaction = connection.transaction()?;
+
+            let user = match SqliteRepository::find_user(&transaction, "id = ?1", &user_id)? {
+                Some(user) => user,
+                None => return Ok(Err("User does not exist"))
+            };
+            let task = match SqliteRepository::find_task(&transaction, task_id)? {
+                Some(task) => task,
+                None => return Ok(Err("Task does not exist"))
+            };
+            if !task.enabled {
+                return Ok(Err("Task is not enabled"));
+            }
+            if task.requires_verification {
+                return Ok(Err("Task requires verification"));
+            }
+
+            let leaderboard_before = SqliteRepository::leaderboard(&transaction)?;
+            let score = Score::new(task.clone());
+            transaction.execute("INSERT INTO scores (user_id, task_id, points, scored_at) VALUES (?1, ?2, ?3, ?4)", params![user_id, task_id, score.points, score.scored_at])?;
+            let season_id = SqliteRepository::active_season_id(&transaction)?;
+            SqliteRepository::insert_transaction(&transaction, &PointTransaction { season_id, ..PointTransaction::for_score(user_id, &score) })?;
+            let total_points = SqliteRepository::lifetime_points(&transaction, user_id)?;
+            let leaderboard_after = SqliteRepository::leaderboard(&transaction)?;
+            let team_ids = SqliteRepository::team_ids_of(&transaction, user_id)?;
+            transaction.commit()?;
+
+            let score_event = ActivityEvent::Score { user_id, display_name: user.display_name, task_id, task_name: task.name, points: task.points, total_points };
+            Ok(Ok((total_points, team_ids, score_event, leaderboard_before, leaderboard_after)))
+        })?;
+        let (total_points, team_ids, score_event, leaderboard_before, leaderboard_after) = scored?;
+
+        self.event_bus.publish(team_ids, score_event);
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
+
+        Ok(total_points)
+    }
+
+    async fn create_and_add_user<'a>(&'a self, username: String, display_name: String, password: String, is_admin: bool) -> Result<Arc<Mutex<User>>, String> {
+        let mut user = User::new(0, username, display_name, is_admin);
+        user.set_password(password);
+
+        self.add_user_private(user).map(|user| Arc::new(Mutex::new(user)))
+    }
+
+    async fn add_team<'a>(&'a self, team: Team) -> Option<u32> {
+        let team_id = self.run("add_team", |connection| {
+            let transaction = connection.transaction()?;
+            transaction.execute("INSERT INTO teams (name, manager_id) VALUES (?1, ?2)", params![team.name, team.manager_id])?;
+            let team_id = transaction.last_insert_rowid() as u32;
+            for member_id in team.member_ids.iter() {
+                transaction.execute("INSERT INTO team_members (team_id, user_id) VALUES (?1, ?2)", params![team_id, member_id])?;
+            }
+            transaction.commit()?;
+
+            Ok(team_id)
+        }).ok()?;
+
+        self.event_bus.publish(vec![team_id], ActivityEvent::TeamCreated { team_id, name: team.name });
+        Some(team_id)
+    }
+
+    async fn add_user_to_team<'a>(&'a self, team_name: &String, user_id: u32, manager: User) -> Result<(), String> {
+        let team_id = self.run("add_user_to_team", |connection| {
+            let mut team = match SqliteRepository::find_team(connection, team_name)? {
+                Some(team) => team,
+                None => return Ok(Err(format!("Team with name '{}'does not exist", team_name)))
+            };
+            let user = match SqliteRepository::find_user(connection, "id = ?1", &user_id)? {
+                Some(user) => user,
+                None => return Ok(Err(format!("User with id {} does not exist", user_id)))
+            };
+
+            if let Err(err_msg) = team.add_user(Arc::new(Mutex::new(user)), &manager) {
+                return Ok(Err(err_msg));
+            }
+            connection.execute("INSERT INTO team_members (team_id, user_id) VALUES (?1, ?2)", params![team.id, user_id])?;
+
+            Ok(Ok(team.id))
+        })??;
+
+        self.event_bus.publish(vec![team_id], ActivityEvent::TeamMemberAdded { team_id, name: team_name.clone(), user_id });
+        Ok(())
+    }
+
+    async fn add_user<'a>(&'a self, session: &Session, user: User) -> MessageResponder<u32> {
+        if !session.user.lock().unwrap().is_admin {
+            return MessageResponder::create_with_message(Status::Forbidden, "You are not an admin".to_owned());
+        }
+
+        match self.add_user_private(user) {
+            Ok(user) => MessageResponder::create_ok(user.id),
+            Err(text) => MessageResponder::create_with_message(Status::Conflict, text)
+        }
+    }
+
+    async fn login<'a>(&'a self, login_request: LoginRequest) -> Result<Session, String> {
+        let user = self.find_user_by_username_const(&login_request.username).await.ok_or("User does not exist")?;
+        if !user.enabled {
+            return Err("User is deactivated".to_owned());
+        }
+        if !user.verify_password(&login_request.password) {
+            return Err("Password mismatch".to_owned());
+        }
+
+        let session = Session::new(Arc::new(Mutex::new(user)));
+        let user_id = session.user.lock().unwrap().id;
+        self.run("login", |connection| connection.execute("INSERT INTO sessions (id, user_id, started, refreshed) VALUES (?1, ?2, ?3, ?4)",
+            params![session.id, user_id, session.started, session.refreshed]))?;
+
+        Ok(session)
+    }
+
+    async fn logout(&self, session_id: &String) -> Result<(), String> {
+        let deleted = self.run("logout", |connection| connection.execute("DELETE FROM sessions WHERE id = ?1", [session_id]))?;
+        if deleted == 0 {
+            return Err("Session unknown".to_owned());
+        }
+
+        Ok(())
+    }
+
+    async fn count_sessions(&self, started_after: DateTime<Utc>) -> usize {
+        self.run("count_sessions", |connection| connection.query_row("SELECT COUNT(*) FROM sessions WHERE started > ?1", [started_after], |row| row.get::<_, usize>(0)))
+            .unwrap_or(0)
+    }
+
+    fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    async fn check_health(&self) -> Vec<DependencyHealth> {
+        if let Err(err_msg) = self.run("health_check", |connection| connection.query_row("SELECT 1", [], |row| row.get::<_, i64>(0))) {
+            return vec![DependencyHealth::down("sqlite", err_msg)];
+        }
+
+        let latest_version = MIGRATIONS.iter().map(|(version, _)| *version).max().unwrap_or(0);
+        let migrations = match self.get_schema_version() {
+            Ok(version) if version >= latest_version => DependencyHealth::up("migrations", Some(format!("Schema version {}", version))),
+            Ok(version) => DependencyHealth::down("migrations", format!("Schema version {} of {} applied", version, latest_version)),
+            Err(err_msg) => DependencyHealth::down("migrations", err_msg),
+        };
+
+        vec![DependencyHealth::up("sqlite", None), migrations]
+    }
+
+    // The current value of a setting is the one it has been changed to last
+    async fn get_settings(&self) -> Vec<(String, SettingValue)> {
+        let values = self.run("get_settings", |connection| {
+            let mut statement = connection.prepare("SELECT key, new_value FROM setting_changes WHERE id IN (SELECT MAX(id) FROM setting_changes GROUP BY key)")?;
+            let values = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            values.collect::<rusqlite::Result<Vec<_>>>()
+        }).unwrap_or(vec![]);
+
+        values.into_iter()
+            .filter_map(|(key, value)| serde_json::from_str::<SettingValue>(&value).ok().map(|value| (key, value)))
+            .collect()
+    }
+
+    async fn save_setting(&self, change: SettingChange) -> Result<(), String> {
+        let old_value = serde_json::to_string(&change.old_value).map_err(|err| err.to_string())?;
+        let new_value = serde_json::to_string(&change.new_value).map_err(|err| err.to_string())?;
+
+        self.run("save_setting", |connection| connection.execute(
+            "INSERT INTO setting_changes (key, old_value, new_value, changed_by, changed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![change.key, old_value, new_value, change.changed_by, change.changed_at]))?;
+
+        Ok(())
+    }
+
+    async fn get_setting_history(&self, key: &String) -> Vec<SettingChange> {
+        let changes = self.run("get_setting_history", |connection| {
+            let mut statement = connection.prepare("SELECT old_value, new_value, changed_by, changed_at FROM setting_changes WHERE key = ?1 ORDER BY id DESC")?;
+            let changes = statement.query_map([key], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, DateTime<Utc>>(3)?)))?;
+            changes.collect::<rusqlite::Result<Vec<_>>>()
+        }).unwrap_or(vec![]);
+
+        changes.into_iter()
+            .filter_map(|(old_value, new_value, changed_by, changed_at)| Some(SettingChange {
+                key: key.clone(),
+                old_value: serde_json::from_str(&old_value).ok()?,
+                new_value: serde_json::from_str(&new_value).ok()?,
+                changed_by,
+                changed_at,
+            }))
+            .collect()
+    }
+
+    async fn set_password(&self, user_id: u32, password: String) -> Result<(), String> {
+        let pwd_hash = hash_password(password);
+        let updated = self.run("set_password", |connection| connection.execute("UPDATE users SET pwd_hash = ?1 WHERE id = ?2", params![pwd_hash, user_id]))?;
+        if updated == 0 {
+            return Err(format!("User with id {} does not exist", user_id));
+        }
+        Ok(())
+    }
+
+    async fn set_user_enabled(&self, user_id: u32, enabled: bool) -> Result<(), String> {
+        let updated = self.run("set_user_enabled", |connection| {
+            let transaction = connection.transaction()?;
+            let updated = transaction.execute("UPDATE users SET enabled = ?1 WHERE id = ?2", params![enabled, user_id])?;
+            if !enabled {
+                transaction.execute("DELETE FROM sessions WHERE user_id = ?1", [user_id])?;
+            }
+            transaction.commit()?;
+            Ok(updated)
+        })?;
+
+        if updated == 0 {
+            return Err(format!("User with id {} does not exist", user_id));
+        }
+        Ok(())
+    }
+
+    async fn save_task(&self, mut task: Task) -> Result<Task, String> {
+        let (id, created) = self.run("save_task", |connection| {
+            if task.id == 0 {
+                connection.execute("INSERT INTO tasks (name, points, enabled, requires_verification) VALUES (?1, ?2, ?3, ?4)",
+                    params![task.name, task.points, task.enabled, task.requires_verification])?;
+                return Ok((connection.last_insert_rowid() as u32, true));
+            }
+
+            let created = SqliteRepository::find_task(connection, task.id)?.is_none();
+            connection.execute("INSERT INTO tasks (id, name, points, enabled, requires_verification) VALUES (?1, ?2, ?3, ?4, ?5) \
+                ON CONFLICT (id) DO UPDATE SET name = excluded.name, points = excluded.points, enabled = excluded.enabled, requires_verification = excluded.requires_verification",
+                params![task.id, task.name, task.points, task.enabled, task.requires_verification])?;
+            Ok((task.id, created))
+        })?;
+
+        task.id = id;
+        self.event_bus.publish(vec![], ActivityEvent::task_saved(&task, created));
+        Ok(task)
+    }
+
+    async fn export_snapshot(&self) -> Result<RepositorySnapshot, String> {
+        self.run("export_snapshot", |connection| Ok(RepositorySnapshot {
+            version: SNAPSHOT_VERSION,
+            users: SqliteRepository::find_all_users(connection)?.iter().map(UserRecord::from).collect(),
+            tasks: SqliteRepository::find_all_tasks(connection)?,
+            teams: SqliteRepository::find_all_teams(connection)?,
+            setting_changes: SqliteRepository::find_all_setting_changes(connection)?,
+            rewards: SqliteRepository::find_rewards(connection, "?1 IS NULL", &None::<u32>)?,
+            redemptions: SqliteRepository::find_redemptions(connection, "?1 IS NULL", &None::<u32>)?,
+            ledger: SqliteRepository::find_transactions(connection, "?1 IS NULL", &None::<u32>)?,
+            seasons: SqliteRepository::find_seasons(connection, "?1 IS NULL", &None::<u32>)?,
+            trophies: SqliteRepository::find_trophies(connection, "?1 IS NULL", &None::<u32>)?,
+            kudos: SqliteRepository::find_kudos(connection, "?1 IS NULL", &None::<u32>)?,
+            score_claims: SqliteRepository::find_claims(connection, "?1 IS NULL", &None::<u32>)?,
+            challenges: SqliteRepository::find_challenges(connection, "?1 IS NULL", &None::<u32>)?,
+            schedules: SqliteRepository::find_all_schedules(connection)?,
+            webhooks: SqliteRepository::find_all_webhooks(connection)?.iter().map(WebhookRecord::from).collect(),
+            dead_letters: SqliteRepository::find_all_dead_letters(connection)?,
+        }))
+    }
+
+    async fn import_snapshot(&self, snapshot: RepositorySnapshot) -> Result<(), String> {
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(format!("Snapshot version {} is newer than the supported version {}", snapshot.version, SNAPSHOT_VERSION));
+        }
+
+        let setting_values = snapshot.setting_changes.iter()
+            .map(|change| Ok((serde_json::to_string(&change.old_value)?, serde_json::to_string(&change.new_value)?)))
+            .collect::<Result<Vec<(String, String)>, serde_json::Error>>()
+            .map_err(|err| err.to_string())?;
+        let ledger = snapshot.ledger_or_derived();
+
+        // Either everything is replaced or nothing, as a foreign key violation rolls back the whole transaction
+        self.run("import_snapshot", |connection| {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch("DELETE FROM sessions; DELETE FROM team_members; DELETE FROM teams; DELETE FROM scores; DELETE FROM setting_changes; \
+                DELETE FROM redemptions; DELETE FROM rewards; DELETE FROM point_transactions; DELETE FROM kudos; DELETE FROM score_claims; DELETE FROM challenges; DELETE FROM schedules; DELETE FROM trophies; DELETE FROM season_standings; DELETE FROM seasons; DELETE FROM users; DELETE FROM tasks; \
+                DELETE FROM webhooks; DELETE FROM webhook_dead_letters;")?;
+
+            for task in snapshot.tasks.iter() {
+                SqliteRepository::insert_task(&transaction, task)?;
+            }
+            for user in snapshot.users.iter() {
+                transaction.execute("INSERT INTO users (id, username, display_name, is_admin, pwd_hash, enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![user.id, user.username, user.display_name, user.is_admin, user.pwd_hash, user.enabled])?;
+                for score in user.scores.iter() {
+                    transaction.execute("INSERT INTO scores (user_id, task_id, points, scored_at) VALUES (?1, ?2, ?3, ?4)", params![user.id, score.task.id, score.points, score.scored_at])?;
+                }
+            }
+            for team in snapshot.teams.iter() {
+                transaction.execute("INSERT INTO teams (id, name, manager_id) VALUES (?1, ?2, ?3)", params![team.id, team.name, team.manager_id])?;
+                for member_id in team.member_ids.iter() {
+                    transaction.execute("INSERT INTO team_members (team_id, user_id) VALUES (?1, ?2)", params![team.id, member_id])?;
+                }
+            }
+            for (change, (old_value, new_value)) in snapshot.setting_changes.iter().zip(setting_values.iter()) {
+                transaction.execute("INSERT INTO setting_changes (key, old_value, new_value, changed_by, changed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![change.key, old_value, new_value, change.changed_by, change.changed_at])?;
+            }
+            for reward in snapshot.rewards.iter() {
+                SqliteRepository::insert_reward(&transaction, reward)?;
+            }
+            for redemption in snapshot.redemptions.iter() {
+                SqliteRepository::insert_redemption(&transaction, redemption)?;
+            }
+            for season in snapshot.seasons.iter() {
+                SqliteRepository::insert_season(&transaction, season)?;
+            }
+            for point_transaction in ledger.iter() {
+                SqliteRepository::insert_transaction(&transaction, point_transaction)?;
+            }
+            for trophy in snapshot.trophies.iter() {
+                SqliteRepository::insert_trophy(&transaction, trophy)?;
+            }
+            for kudos in snapshot.kudos.iter() {
+                SqliteRepository::insert_kudos(&transaction, kudos)?;
+            }
+            for claim in snapshot.score_claims.iter() {
+                SqliteRepository::insert_claim(&transaction, claim)?;
+            }
+            for challenge in snapshot.challenges.iter() {
+                SqliteRepository::insert_challenge(&transaction, challenge)?;
+            }
+            for schedule in snapshot.schedules.iter() {
+                SqliteRepository::insert_schedule(&transaction, schedule)?;
+            }
+            for webhook in snapshot.webhooks.iter() {
+                SqliteRepository::insert_webhook(&transaction, &Webhook::from(webhook.clone()))?;
+            }
+            for delivery in snapshot.dead_letters.iter() {
+                SqliteRepository::insert_dead_letter(&transaction, delivery)?;
+            }
+
+            transaction.commit()
+        })
+    }
+
+    async fn add_in_bulk(&self, users: Vec<ImportedUser>, tasks: Vec<Task>) -> Result<(), String> {
+        let (created, memberships) = self.run("add_in_bulk", |connection| {
+            let transaction = connection.transaction()?;
+            let mut created = vec![];
+            let mut memberships = vec![];
+
+            // Returning early drops the transaction, which rolls back everything added so far
+            for ImportedUser { user, team } in users.iter() {
+                if SqliteRepository::find_user(&transaction, "username = ?1", &user.username)?.is_some() {
+                    return Ok(Err(format!("Username '{}' is not available", user.username)));
+                }
+                transaction.execute("INSERT INTO users (username, display_name, is_admin, pwd_hash, enabled) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![user.username, user.display_name, user.is_admin, user.pwd_hash_components, user.enabled])?;
+                let user_id = transaction.last_insert_rowid() as u32;
+                created.push(ActivityEvent::UserCreated { user_id, username: user.username.clone(), display_name: user.display_name.clone() });
+
+                if let Some(team_name) = team {
+                    let team_id = match SqliteRepository::find_team(&transaction, team_name)? {
+                        Some(team) => team.id,
+                        None => return Ok(Err(format!("Team with name '{}'does not exist", team_name)))
+                    };
+                    transaction.execute("INSERT INTO team_members (team_id, user_id) VALUES (?1, ?2)", params![team_id, user_id])?;
+                    memberships.push((team_id, ActivityEvent::TeamMemberAdded { team_id, name: team_name.clone(), user_id }));
+                }
+            }
+            for task in tasks.iter() {
+                transaction.execute("INSERT INTO tasks (name, points, enabled, requires_verification) VALUES (?1, ?2, ?3, ?4)",
+                    params![task.name, task.points, task.enabled, task.requires_verification])?;
+                created.push(ActivityEvent::task_saved(&Task { id: transaction.last_insert_rowid() as u32, ..task.clone() }, true));
+            }
+
+            transaction.commit()?;
+            Ok(Ok((created, memberships)))
+        })??;
+
+        created.into_iter().for_each(|event| self.event_bus.publish(vec![], event));
+        memberships.into_iter().for_each(|(team_id, event)| self.event_bus.publish(vec![team_id], event));
+        Ok(())
+    }
+    async fn get_all_teams(&self) -> Vec<TeamRecord> {
+        self.run("get_all_teams", |connection| SqliteRepository::find_all_teams(connection)).unwrap_or_default()
+    }
+
+    // Summed up by the database, so only one row per user and task is loaded
+    async fn aggregate_scores(&self, filter: &ScoreFilter) -> Result<Vec<ScoreAggregate>, String> {
+        self.run("aggregate_scores", |connection| {
+            let mut statement = connection.prepare(
+                "SELECT s.user_id, u.display_name, s.task_id, t.name, COUNT(*), SUM(s.points) FROM scores s \
+                JOIN users u ON u.id = s.user_id JOIN tasks t ON t.id = s.task_id \
+                WHERE (?1 IS NULL OR s.scored_at >= ?1) AND (?2 IS NULL OR s.scored_at <= ?2) AND (?3 IS NULL OR s.task_id = ?3) \
+                GROUP BY s.user_id, s.task_id ORDER BY s.user_id, s.task_id")?;
+            let aggregates = statement.query_map(params![filter.from, filter.to, filter.task_id], |row| Ok(ScoreAggregate {
+                user_id: row.get(0)?,
+                display_name: row.get(1)?,
+                task_id: row.get(2)?,
+                task_name: row.get(3)?,
+                count: row.get(4)?,
+                points: row.get(5)?,
+            }))?;
+
+            aggregates.collect()
+        })
+    }
+    async fn get_all_rewards(&self) -> Vec<Reward> {
+        self.run("get_all_rewards", |connection| SqliteRepository::find_rewards(connection, "?1 IS NULL", &None::<u32>)).unwrap_or_default()
+    }
+
+    async fn get_reward(&self, id: u32) -> Option<Reward> {
+        self.run("get_reward", |connection| SqliteRepository::find_rewards(connection, "id = ?1", &id)).ok()?.pop()
+    }
+
+    async fn save_reward(&self, mut reward: Reward) -> Result<Reward, String> {
+        let id = self.run("save_reward", |connection| {
+            if SqliteRepository::find_rewards(connection, "name = ?1", &reward.name)?.iter().any(|r| r.id != reward.id) {
+                return Ok(Err(format!("Reward '{}' already exists", reward.name)));
+            }
+            if reward.id == 0 {
+                connection.execute("INSERT INTO rewards (name, cost, stock, enabled) VALUES (?1, ?2, ?3, ?4)", params![reward.name, reward.cost, reward.stock, reward.enabled])?;
+                return Ok(Ok(connection.last_insert_rowid() as u32));
+            }
+
+            connection.execute("INSERT INTO rewards (id, name, cost, stock, enabled) VALUES (?1, ?2, ?3, ?4, ?5) \
+                ON CONFLICT (id) DO UPDATE SET name = excluded.name, cost = excluded.cost, stock = excluded.stock, enabled = excluded.enabled",
+                params![reward.id, reward.name, reward.cost, reward.stock, reward.enabled])?;
+            Ok(Ok(reward.id))
+        })??;
+
+        reward.id = id;
+        Ok(reward)
+    }
+
+    async fn redeem(&self, user_id: u32, reward_id: u32) -> Result<Redemption, String> {
+        let (redemption, display_name, team_ids) = self.run("redeem", |connection| {
+            let transaction = connection.transaction()?;
+            let user = match SqliteRepository::find_user(&transaction, "id = ?1", &user_id)? {
+                Some(user) => user,
+                None => return Ok(Err(format!("User with id {} does not exist", user_id)))
+            };
+            let reward = match SqliteRepository::find_rewards(&transaction, "id = ?1", &reward_id)?.pop() {
+                Some(reward) => reward,
+                None => return Ok(Err(format!("Reward with id {} does not exist", reward_id)))
+            };
+
+            let ledger = SqliteRepository::find_transactions(&transaction, "user_id = ?1", &user_id)?;
+            if let Err(err_msg) = reward.check_redeemable(Balance::create(user_id, &ledger).balance) {
+                return Ok(Err(err_msg));
+            }
+
+            transaction.execute("UPDATE rewards SET stock = stock - 1 WHERE id = ?1 AND stock IS NOT NULL", [reward_id])?;
+            let mut redemption = Redemption::new(0, &reward, user_id);
+            transaction.execute("INSERT INTO redemptions (reward_id, reward_name, user_id, cost, status, requested_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![redemption.reward_id, redemption.reward_name, user_id, redemption.cost, redemption.status.as_str(), redemption.requested_at])?;
+            redemption.id = transaction.last_insert_rowid() as u32;
+            let cost = PointTransaction::new(user_id, TransactionKind::Redeem, -(redemption.cost as i64), Some(redemption.reward_name.clone()), None);
+            SqliteRepository::insert_transaction(&transaction, &cost)?;
+            let team_ids = SqliteRepository::team_ids_of(&transaction, user_id)?;
+            transaction.commit()?;
+
+            Ok(Ok((redemption, user.display_name, team_ids)))
+        })??;
+
+        self.event_bus.publish(team_ids, ActivityEvent::RewardRedeemed { redemption_id: redemption.id, user_id, display_name,
+            reward_id, reward_name: redemption.reward_name.clone(), cost: redemption.cost });
+        Ok(redemption)
+    }
+
+    async fn get_redemptions(&self, user_id: Option<u32>) -> Vec<Redemption> {
+        self.run("get_redemptions", |connection| SqliteRepository::find_redemptions(connection, "?1 IS NULL OR user_id = ?1", &user_id)).unwrap_or_default()
+    }
+
+    async fn decide_redemption(&self, redemption_id: u32, approved: bool, decided_by: String) -> Result<Redemption, String> {
+        let (redemption, team_ids) = self.run("decide_redemption", |connection| {
+            let transaction = connection.transaction()?;
+            let mut redemption = match SqliteRepository::find_redemptions(&transaction, "id = ?1", &redemption_id)?.pop() {
+                Some(redemption) => redemption,
+                None => return Ok(Err(format!("Redemption with id {} does not exist", redemption_id)))
+            };
+            if let Err(err_msg) = redemption.decide(approved, decided_by) {
+                return Ok(Err(err_msg));
+            }
+
+            transaction.execute("UPDATE redemptions SET status = ?1, decided_by = ?2, decided_at = ?3 WHERE id = ?4",
+                params![redemption.status.as_str(), redemption.decided_by, redemption.decided_at, redemption_id])?;
+            // A rejected redemption gives back the points and the item
+            if !approved {
+                transaction.execute("UPDATE rewards SET stock = stock + 1 WHERE id = ?1 AND stock IS NOT NULL", [redemption.reward_id])?;
+                let refund = PointTransaction::new(redemption.user_id, TransactionKind::Refund, redemption.cost as i64, Some(redemption.reward_name.clone()), redemption.decided_by.clone());
+                SqliteRepository::insert_transaction(&transaction, &refund)?;
+            }
+            let team_ids = SqliteRepository::team_ids_of(&transaction, redemption.user_id)?;
+            transaction.commit()?;
+
+            Ok(Ok((redemption, team_ids)))
+        })??;
+
+        self.event_bus.publish(team_ids, ActivityEvent::RedemptionDecided { redemption_id, user_id: redemption.user_id,
+            reward_name: redemption.reward_name.clone(), status: redemption.status });
+        Ok(redemption)
+    }
+
+    async fn get_ledger(&self, user_id: Option<u32>) -> Vec<PointTransaction> {
+        self.run("get_ledger", |connection| SqliteRepository::find_transactions(connection, "?1 IS NULL OR user_id = ?1", &user_id)).unwrap_or_default()
+    }
+
+    async fn add_transaction(&self, mut point_transaction: PointTransaction) -> Result<PointTransaction, String> {
+        let user_id = point_transaction.user_id;
+        let (total_points, team_ids, leaderboard_before, leaderboard_after) = self.run("add_transaction", |connection| {
+            let transaction = connection.transaction()?;
+            if SqliteRepository::find_user(&transaction, "id = ?1", &user_id)?.is_none() {
+                return Ok(Err(format!("User with id {} does not exist", user_id)));
+            }
+
+            let leaderboard_before = SqliteRepository::leaderboard(&transaction)?;
+            if point_transaction.kind.is_earned() {
+                point_transaction.season_id = SqliteRepository::active_season_id(&transaction)?;
+            }
+            point_transaction.id = SqliteRepository::insert_transaction(&transaction, &point_transaction)?;
+            let total_points = SqliteRepository::lifetime_points(&transaction, user_id)?;
+            let leaderboard_after = SqliteRepository::leaderboard(&transaction)?;
+            let team_ids = SqliteRepository::team_ids_of(&transaction, user_id)?;
+            transaction.commit()?;
+
+            Ok(Ok((total_points, team_ids, leaderboard_before, leaderboard_after)))
+        })??;
+
+        self.event_bus.publish(team_ids, ActivityEvent::PointsBooked { transaction_id: point_transaction.id, user_id,
+            kind: point_transaction.kind, amount: point_transaction.amount, total_points });
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
+        Ok(point_transaction)
+    }
+
+    async fn get_seasons(&self) -> Vec<Season> {
+        self.run("get_seasons", |connection| SqliteRepository::find_seasons(connection, "?1 IS NULL", &None::<u32>)).unwrap_or_default()
+    }
+
+    async fn start_season(&self, season: Season) -> Result<Season, String> {
+        let season = self.run("start_season", |connection| {
+            let transaction = connection.transaction()?;
+            if let Some(running) = transaction.query_row("SELECT name FROM seasons WHERE ended_at IS NULL", [], |row| row.get::<_, String>(0)).optional()? {
+                return Ok(Err(format!("Season '{}' is still running", running)));
+            }
+            if !SqliteRepository::find_seasons(&transaction, "name = ?1", &season.name)?.is_empty() {
+                return Ok(Err(format!("Season '{}' already exists", season.name)));
+            }
+
+            transaction.execute("INSERT INTO seasons (name, started_at, ends_at) VALUES (?1, ?2, ?3)", params![season.name, season.start, season.ends_at])?;
+            let season = Season { id: transaction.last_insert_rowid() as u32, ..season.clone() };
+            transaction.commit()?;
+            Ok(Ok(season))
+        })??;
+
+        self.event_bus.publish(vec![], ActivityEvent::SeasonStarted { season_id: season.id, name: season.name.clone() });
+        Ok(season)
+    }
+
+    async fn end_season(&self, id: u32) -> Result<Season, String> {
+        let (season, awarded) = self.run("end_season", |connection| {
+            let transaction = connection.transaction()?;
+            let mut season = match SqliteRepository::find_seasons(&transaction, "id = ?1", &id)?.pop() {
+                Some(season) => season,
+                None => return Ok(Err(format!("Season with id {} does not exist", id)))
+            };
+            if let Err(err) = season.finish(SqliteRepository::season_standings(&transaction, id)?, Utc::now()) {
+                return Ok(Err(err));
+            }
+
+            transaction.execute("UPDATE seasons SET ended_at = ?1 WHERE id = ?2", params![season.end, id])?;
+            SqliteRepository::insert_standings(&transaction, &season)?;
+            let mut awarded = vec![];
+            for mut trophy in season.trophies() {
+                trophy.id = SqliteRepository::insert_trophy(&transaction, &trophy)?;
+                let team_ids = SqliteRepository::team_ids_of(&transaction, trophy.user_id)?;
+                awarded.push((trophy, team_ids));
+            }
+            transaction.commit()?;
+            Ok(Ok((season, awarded)))
+        })??;
+
+        self.event_bus.publish(vec![], ActivityEvent::SeasonEnded { season_id: id, name: season.name.clone(), standings: season.standings.clone() });
+        awarded.into_iter().for_each(|(trophy, team_ids)| self.event_bus.publish(team_ids, season.trophy_awarded(&trophy)));
+        Ok(season)
+    }
+
+    async fn end_due_seasons(&self, now: DateTime<Utc>) -> Vec<Season> {
+        let due: Vec<u32> = self.get_seasons().await.into_iter().filter(|season| season.is_due(now)).map(|season| season.id).collect();
+        let mut ended = vec![];
+        for id in due {
+            match self.end_season(id).await {
+                Ok(season) => ended.push(season),
+                Err(err_msg) => error!(season_id = id, error = %err_msg, "Unable to end a season past its planned end"),
+            }
+        }
+        ended
+    }
+
+    async fn get_season_leaderboard(&self, id: u32) -> Option<Vec<LeaderboardEntry>> {
+        self.run("get_season_leaderboard", |connection| {
+            let season = match SqliteRepository::find_seasons(connection, "id = ?1", &id)?.pop() {
+                Some(season) => season,
+                None => return Ok(None)
+            };
+            if !season.is_active() {
+                return Ok(Some(season.standings));
+            }
+            SqliteRepository::season_standings(connection, id).map(Some)
+        }).ok().flatten()
+    }
+
+    async fn get_trophies(&self, user_id: Option<u32>) -> Vec<Trophy> {
+        self.run("get_trophies", |connection| SqliteRepository::find_trophies(connection, "?1 IS NULL OR user_id = ?1", &user_id)).unwrap_or_default()
+    }
+
+    async fn give_kudos(&self, mut kudos: Kudos, limits: KudosLimits) -> Result<Kudos, String> {
+        let (kudos_event, team_ids, leaderboard_before, leaderboard_after) = self.run("give_kudos", |connection| {
+            let transaction = connection.transaction()?;
+            let from = SqliteRepository::find_user(&transaction, "id = ?1", &kudos.from_user_id)?;
+            let to = SqliteRepository::find_user(&transaction, "id = ?1", &kudos.to_user_id)?;
+            let (from, to) = match (from, to) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return Ok(Err("User does not exist".to_owned()))
+            };
+            if let Err(err) = limits.check(&kudos, &SqliteRepository::find_kudos(&transaction, "from_user_id = ?1", &kudos.from_user_id)?) {
+                return Ok(Err(err));
+            }
+
+            let leaderboard_before = SqliteRepository::leaderboard(&transaction)?;
+            kudos.id = SqliteRepository::insert_kudos(&transaction, &kudos)?;
+            let season_id = SqliteRepository::active_season_id(&transaction)?;
+            SqliteRepository::insert_transaction(&transaction, &PointTransaction { season_id, ..kudos.transaction(&from.display_name) })?;
+            let leaderboard_after = SqliteRepository::leaderboard(&transaction)?;
+            let mut team_ids = SqliteRepository::team_ids_of(&transaction, from.id)?;
+            team_ids.extend(SqliteRepository::team_ids_of(&transaction, to.id)?);
+            team_ids.sort_unstable();
+            team_ids.dedup();
+            transaction.commit()?;
+
+            Ok(Ok((kudos.event(from.display_name, to.display_name), team_ids, leaderboard_before, leaderboard_after)))
+        })??;
+
+        self.event_bus.publish(team_ids, kudos_event);
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
+        Ok(kudos)
+    }
+
+    async fn get_kudos(&self, user_id: Option<u32>) -> Vec<Kudos> {
+        self.run("get_kudos", |connection| SqliteRepository::find_kudos(connection, "?1 IS NULL OR from_user_id = ?1 OR to_user_id = ?1", &user_id)).unwrap_or_default()
+    }
+
+    async fn claim_score(&self, user_id: u32, task_id: u32) -> Result<ScoreClaim, String> {
+        let (claim, display_name, team_ids) = self.run("claim_score", |connection| {
+            let transaction = connection.transaction()?;
+            let user = match SqliteRepository::find_user(&transaction, "id = ?1", &user_id)? {
+                Some(user) => user,
+                None => return Ok(Err(format!("User with id {} does not exist", user_id)))
+            };
+            let task = match SqliteRepository::find_task(&transaction, task_id)? {
+                Some(task) => task,
+                None => return Ok(Err("Task does not exist".to_owned()))
+            };
+            if !task.enabled {
+                return Ok(Err("Task is not enabled".to_owned()));
+            }
+            if !task.requires_verification {
+                return Ok(Err("Task does not require verification".to_owned()));
+            }
+
+            let mut claim = ScoreClaim::new(0, &task, user_id);
+            claim.id = SqliteRepository::insert_claim(&transaction, &claim)?;
+            let team_ids = SqliteRepository::team_ids_of(&transaction, user_id)?;
+            transaction.commit()?;
+
+            Ok(Ok((claim, user.display_name, team_ids)))
+        })??;
+
+        self.event_bus.publish(team_ids, ActivityEvent::ScoreClaimed { claim_id: claim.id, user_id, display_name, task_id,
+            task_name: claim.task_name.clone(), points: claim.points });
+        Ok(claim)
+    }
+
+    async fn get_score_claims(&self, user_id: Option<u32>) -> Vec<ScoreClaim> {
+        self.run("get_score_claims", |connection| SqliteRepository::find_claims(connection, "?1 IS NULL OR user_id = ?1", &user_id)).unwrap_or_default()
+    }
+
+    async fn decide_score_claim(&self, claim_id: u32, confirmed: bool, decided_by: String) -> Result<ScoreClaim, String> {
+        let (claim, score_event, team_ids, leaderboard_before, leaderboard_after) = self.run("decide_score_claim", |connection| {
+            let transaction = connection.transaction()?;
+            let mut claim = match SqliteRepository::find_claims(&transaction, "id = ?1", &claim_id)?.pop() {
+                Some(claim) => claim,
+                None => return Ok(Err(format!("Claim with id {} does not exist", claim_id)))
+            };
+            let (user, task) = match (SqliteRepository::find_user(&transaction, "id = ?1", &claim.user_id)?, SqliteRepository::find_task(&transaction, claim.task_id)?) {
+                (Some(user), Some(task)) => (user, task),
+                _ => return Ok(Err(format!("Claim {} refers to a user or task which does not exist", claim_id)))
+            };
+            if let Err(err_msg) = claim.decide(confirmed, decided_by.clone()) {
+                return Ok(Err(err_msg));
+            }
+
+            let leaderboard_before = SqliteRepository::leaderboard(&transaction)?;
+            SqliteRepository::update_claim(&transaction, &claim)?;
+            // Only a confirmed claim becomes a score
+            let mut score_event = None;
+            if confirmed {
+                let score = claim.score(task);
+                transaction.execute("INSERT INTO scores (user_id, task_id, points, scored_at) VALUES (?1, ?2, ?3, ?4)", params![claim.user_id, claim.task_id, score.points, score.scored_at])?;
+                let season_id = SqliteRepository::active_season_id(&transaction)?;
+                SqliteRepository::insert_transaction(&transaction, &PointTransaction { season_id, created_by: Some(decided_by), ..PointTransaction::for_score(claim.user_id, &score) })?;
+                let total_points = SqliteRepository::lifetime_points(&transaction, claim.user_id)?;
+                score_event = Some(ActivityEvent::Score { user_id: claim.user_id, display_name: user.display_name, task_id: claim.task_id,
+                    task_name: claim.task_name.clone(), points: claim.points, total_points });
+            }
+            let leaderboard_after = SqliteRepository::leaderboard(&transaction)?;
+            let team_ids = SqliteRepository::team_ids_of(&transaction, claim.user_id)?;
+            transaction.commit()?;
+
+            Ok(Ok((claim, score_event, team_ids, leaderboard_before, leaderboard_after)))
+        })??;
+
+        self.event_bus.publish(team_ids.clone(), ActivityEvent::ScoreClaimDecided { claim_id, user_id: claim.user_id,
+            task_name: claim.task_name.clone(), status: claim.status });
+        if let Some(score_event) = score_event {
+            self.event_bus.publish(team_ids, score_event);
+        }
+        if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+            self.event_bus.publish(vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after });
+        }
+        Ok(claim)
+    }
+
+    async fn expire_score_claims(&self, cutoff: DateTime<Utc>) -> Vec<ScoreClaim> {
+        let expired = self.run("expire_score_claims", |connection| {
+            let transaction = connection.transaction()?;
+            let mut expired = vec![];
+            for mut claim in SqliteRepository::find_claims(&transaction, "status = ?1", &ClaimStatus::Pending.as_str())? {
+                if claim.expire(cutoff) {
+                    SqliteRepository::update_claim(&transaction, &claim)?;
+                    let team_ids = SqliteRepository::team_ids_of(&transaction, claim.user_id)?;
+                    expired.push((claim, team_ids));
+                }
+            }
+            transaction.commit()?;
+            Ok(expired)
+        }).unwrap_or_default();
+
+        for (claim, team_ids) in expired.iter() {
+            self.event_bus.publish(team_ids.clone(), ActivityEvent::ScoreClaimDecided { claim_id: claim.id, user_id: claim.user_id,
+                task_name: claim.task_name.clone(), status: claim.status });
+        }
+        expired.into_iter().map(|(claim, _)| claim).collect()
+    }
+
+    async fn propose_challenge(&self, mut challenge: Challenge) -> Result<Challenge, String> {
+        let team_ids = self.run("propose_challenge", |connection| {
+            let transaction = connection.transaction()?;
+            if !SqliteRepository::find_challenges(&transaction, "name = ?1", &challenge.name)?.is_empty() {
+                return Ok(Err(format!("Challenge '{}' already exists", challenge.name)));
+            }
+            challenge.id = SqliteRepository::insert_challenge(&transaction, &challenge)?;
+            let team_ids = challenge.team_ids(&SqliteRepository::find_all_teams(&transaction)?);
+            transaction.commit()?;
+
+            Ok(Ok(team_ids))
+        })??;
+
+        self.event_bus.publish(team_ids, challenge.event());
+        Ok(challenge)
+    }
+
+    async fn get_challenges(&self) -> Vec<Challenge> {
+        self.run("get_challenges", |connection| SqliteRepository::find_challenges(connection, "?1 IS NULL", &None::<u32>)).unwrap_or_default()
+    }
+
+    async fn decide_challenge(&self, challenge_id: u32, accepted: bool, decided_by: String) -> Result<Challenge, String> {
+        let (challenge, team_ids) = self.run("decide_challenge", |connection| {
+            let transaction = connection.transaction()?;
+            let mut challenge = match SqliteRepository::find_challenges(&transaction, "id = ?1", &challenge_id)?.pop() {
+                Some(challenge) => challenge,
+                None => return Ok(Err(format!("Challenge with id {} does not exist", challenge_id)))
+            };
+            if let Err(err_msg) = challenge.decide(accepted, decided_by, Utc::now()) {
+                return Ok(Err(err_msg));
+            }
+            SqliteRepository::update_challenge(&transaction, &challenge)?;
+            let team_ids = challenge.team_ids(&SqliteRepository::find_all_teams(&transaction)?);
+            transaction.commit()?;
+
+            Ok(Ok((challenge, team_ids)))
+        })??;
+
+        self.event_bus.publish(team_ids, challenge.event());
+        Ok(challenge)
+    }
+
+    async fn resolve_challenges(&self, now: DateTime<Utc>) -> Vec<Challenge> {
+        let (resolved, events) = self.run("resolve_challenges", |connection| {
+            let transaction = connection.transaction()?;
+            let mut due: Vec<Challenge> = SqliteRepository::find_challenges(&transaction, "status = ?1", &ChallengeStatus::Accepted.as_str())?
+                .into_iter()
+                .filter(|challenge| challenge.is_due(now))
+                .collect();
+            if due.is_empty() {
+                return Ok((vec![], vec![]));
+            }
+
+            let users = SqliteRepository::find_all_users(&transaction)?;
+            let teams = SqliteRepository::find_all_teams(&transaction)?;
+            let leaderboard_before = SqliteRepository::leaderboard(&transaction)?;
+            let season_id = SqliteRepository::active_season_id(&transaction)?;
+            let mut events = vec![];
+            for challenge in due.iter_mut() {
+                for mut stake in challenge.resolve(&users, &teams) {
+                    stake.season_id = season_id;
+                    stake.id = SqliteRepository::insert_transaction(&transaction, &stake)?;
+                    let total_points = SqliteRepository::lifetime_points(&transaction, stake.user_id)?;
+                    events.push((SqliteRepository::team_ids_of(&transaction, stake.user_id)?, ActivityEvent::PointsBooked { transaction_id: stake.id,
+                        user_id: stake.user_id, kind: stake.kind, amount: stake.amount, total_points }));
+                }
+                SqliteRepository::update_challenge(&transaction, challenge)?;
+                events.push((challenge.team_ids(&teams), challenge.event()));
+
+                for mut trophy in challenge.trophies(&teams) {
+                    trophy.id = SqliteRepository::insert_trophy(&transaction, &trophy)?;
+                    let display_name = users.iter().find(|user| user.id == trophy.user_id).map(|user| user.display_name.clone()).unwrap_or_default();
+                    events.push((SqliteRepository::team_ids_of(&transaction, trophy.user_id)?, ActivityEvent::Trophy { user_id: trophy.user_id, display_name, trophy: trophy.name }));
+                }
+            }
+            let leaderboard_after = SqliteRepository::leaderboard(&transaction)?;
+            transaction.commit()?;
+
+            if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+                events.push((vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after }));
+            }
+            Ok((due, events))
+        }).unwrap_or_default();
+
+        events.into_iter().for_each(|(team_ids, event)| self.event_bus.publish(team_ids, event));
+        resolved
+    }
+
+    async fn get_schedules(&self) -> Vec<Schedule> {
+        self.run("get_schedules", |connection| SqliteRepository::find_all_schedules(connection)).unwrap_or_default()
+    }
+
+    async fn save_schedule(&self, schedule: Schedule) -> Result<Schedule, String> {
+        self.run("save_schedule", |connection| {
+            if SqliteRepository::find_task(connection, schedule.task_id)?.is_none() {
+                return Ok(Err(format!("Task with id {} does not exist", schedule.task_id)));
+            }
+            SqliteRepository::insert_schedule(connection, &schedule)?;
+            Ok(Ok(schedule))
+        })?
+    }
+
+    async fn remove_schedule(&self, task_id: u32) -> Result<(), String> {
+        match self.run("remove_schedule", |connection| connection.execute("DELETE FROM schedules WHERE task_id = ?1", params![task_id]))? {
+            0 => Err(format!("Task with id {} has no schedule", task_id)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn settle_schedules(&self, now: DateTime<Utc>) -> Vec<PointTransaction> {
+        let (settled, events) = self.run("settle_schedules", |connection| {
+            let transaction = connection.transaction()?;
+            let schedules = SqliteRepository::find_all_schedules(&transaction)?;
+            if schedules.iter().all(|schedule| schedule.next_due().map_or(true, |due| now < due)) {
+                return Ok((vec![], vec![]));
+            }
+
+            let users = SqliteRepository::find_all_users(&transaction)?;
+            let teams = SqliteRepository::find_all_teams(&transaction)?;
+            let tasks = SqliteRepository::find_all_tasks(&transaction)?;
+            let leaderboard_before = SqliteRepository::leaderboard(&transaction)?;
+            let season_id = SqliteRepository::active_season_id(&transaction)?;
+            let mut settled = vec![];
+            let mut events = vec![];
+            for mut schedule in schedules {
+                let task = match tasks.iter().find(|task| task.id == schedule.task_id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+                for mut point_transaction in schedule.settle(task, &users, &teams, now) {
+                    point_transaction.season_id = season_id;
+                    point_transaction.id = SqliteRepository::insert_transaction(&transaction, &point_transaction)?;
+                    let total_points = SqliteRepository::lifetime_points(&transaction, point_transaction.user_id)?;
+                    events.push((SqliteRepository::team_ids_of(&transaction, point_transaction.user_id)?, ActivityEvent::PointsBooked { transaction_id: point_transaction.id,
+                        user_id: point_transaction.user_id, kind: point_transaction.kind, amount: point_transaction.amount, total_points }));
+                    settled.push(point_transaction);
+                }
+                transaction.execute("UPDATE schedules SET settled_until = ?1, settled_turns = ?2 WHERE task_id = ?3", params![schedule.settled_until, schedule.settled_turns, schedule.task_id])?;
+            }
+            let leaderboard_after = SqliteRepository::leaderboard(&transaction)?;
+            transaction.commit()?;
+
+            if leaderboard_before.iter().map(|e| e.user_id).ne(leaderboard_after.iter().map(|e| e.user_id)) {
+                events.push((vec![], ActivityEvent::LeaderboardChange { leaderboard: leaderboard_after }));
+            }
+            Ok((settled, events))
+        }).unwrap_or_default();
+
+        events.into_iter().for_each(|(team_ids, event)| self.event_bus.publish(team_ids, event));
+        settled
+    }
+
+    async fn get_webhooks(&self) -> Vec<Webhook> {
+        self.run("get_webhooks", |connection| SqliteRepository::find_all_webhooks(connection)).unwrap_or_default()
+    }
+
+    async fn add_webhook(&self, webhook: Webhook) -> Result<Webhook, String> {
+        self.run("add_webhook", |connection| SqliteRepository::insert_webhook(connection, &Webhook { id: 0, ..webhook.clone() }))
+            .map(|id| Webhook { id, ..webhook })
+    }
+
+    async fn remove_webhook(&self, id: u32) -> Result<(), String> {
+        match self.run("remove_webhook", |connection| connection.execute("DELETE FROM webhooks WHERE id = ?1", params![id]))? {
+            0 => Err(format!("Webhook with id {} does not exist", id)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_dead_letters(&self) -> Vec<WebhookDelivery> {
+        self.run("get_dead_letters", |connection| SqliteRepository::find_all_dead_letters(connection)).unwrap_or_default()
+    }
+
+    async fn add_dead_letter(&self, delivery: WebhookDelivery) -> Result<(), String> {
+        self.run("add_dead_letter", |connection| {
+            let transaction = connection.transaction()?;
+            SqliteRepository::insert_dead_letter(&transaction, &delivery)?;
+            transaction.execute("DELETE FROM webhook_dead_letters WHERE seq NOT IN (SELECT seq FROM webhook_dead_letters ORDER BY seq DESC LIMIT ?1)", params![DEAD_LETTER_LEN])?;
+            transaction.commit()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rocket::futures::executor::block_on;
+
+    use crate::repository::conformance::repository_conformance_tests;
+    use crate::repository::demo_data::seed_demo_data;
+    use crate::repository::repository::Repository;
+
+    use super::SqliteRepository;
+
+    async fn fixture(_test_name: &str) -> SqliteRepository {
+        SqliteRepository::init_repository().await
+    }
+
+    repository_conformance_tests!(fixture);
+
+    #[test]
+    fn test_reopening_keeps_data_and_schema() {
+        let path = env::temp_dir().join(format!("taskscore-test-{}.sqlite", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let repository = block_on(SqliteRepository::open(path)).unwrap();
+        assert!(block_on(repository.get_all_users()).is_empty());
+        block_on(seed_demo_data(&repository)).unwrap();
+        let points = block_on(repository.score(4, 4)).unwrap();
+        drop(repository);
+
+        let reopened = block_on(SqliteRepository::open(path)).unwrap();
+        assert_eq!(points, block_on(reopened.get_user(4)).unwrap().points);
+        assert_eq!(4, block_on(reopened.get_all_users()).len());
+        assert_eq!(13, reopened.get_schema_version().unwrap());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
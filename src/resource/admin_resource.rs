@@ -0,0 +1,76 @@
+use rocket::{http::Status, State};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket_okapi::openapi;
+
+use crate::config::app_config::{AppConfig, RepositoryKind};
+use crate::model::Session;
+use crate::model::snapshot::{RepositorySnapshot, ImportReport};
+use crate::model::bulk_import::{CsvImportReport, USER_TEMPLATE, TASK_TEMPLATE};
+use crate::repository::{backup, bulk_import};
+use crate::repository::repository::DynRepository;
+
+use super::http::authorization::require_admin;
+use super::http::responder::CsvFileResponder;
+
+// The graph keeps only users and settings, a snapshot of it would lose everything else and an import would not reach the graph
+fn require_snapshot_support(config: &AppConfig) -> Result<(), Custom<String>> {
+    match config.repository {
+        RepositoryKind::Neo4j => Err(Custom(Status::NotImplemented, "Export and import are not available for the neo4j repository".to_owned())),
+        _ => Ok(()),
+    }
+}
+
+// Contains the password hashes of all users, so it has to be kept as safe as the database itself
+#[openapi(tag = "Admin")]
+#[get("/admin/export")]
+pub async fn export_data<'a>(session: Session, repository: &State<DynRepository>, config: &State<AppConfig>) -> Result<Json<RepositorySnapshot>, Custom<String>> {
+    require_admin(&session)?;
+    require_snapshot_support(config)?;
+    repository.export_snapshot().await.map(Json).map_err(|msg| Custom(Status::InternalServerError, msg))
+}
+
+// Replaces all data, which ends every session including the one of the importing admin
+#[openapi(tag = "Admin")]
+#[post("/admin/import?<dry_run>", data = "<snapshot>")]
+pub async fn import_data<'a>(session: Session, dry_run: Option<bool>, snapshot: Json<RepositorySnapshot>, repository: &State<DynRepository>, config: &State<AppConfig>) -> Result<Custom<Json<ImportReport>>, Custom<String>> {
+    require_admin(&session)?;
+    require_snapshot_support(config)?;
+    let report = backup::import(repository.inner().as_ref(), snapshot.into_inner(), dry_run.unwrap_or(false)).await
+        .map_err(|msg| Custom(Status::InternalServerError, msg))?;
+
+    let status = if report.errors.is_empty() { Status::Ok } else { Status::UnprocessableEntity };
+    Ok(Custom(status, Json(report)))
+}
+
+fn csv_import_response(result: Result<CsvImportReport, String>) -> Result<Custom<Json<CsvImportReport>>, Custom<String>> {
+    let report = result.map_err(|msg| Custom(Status::BadRequest, msg))?;
+    let status = if report.is_valid() { Status::Ok } else { Status::UnprocessableEntity };
+    Ok(Custom(status, Json(report)))
+}
+
+// Generated passwords are part of the response and not shown again
+#[openapi(tag = "Admin")]
+#[post("/admin/import/users?<dry_run>", data = "<csv>")]
+pub async fn import_users_csv<'a>(session: Session, dry_run: Option<bool>, csv: String, repository: &State<DynRepository>) -> Result<Custom<Json<CsvImportReport>>, Custom<String>> {
+    require_admin(&session)?;
+    csv_import_response(bulk_import::import_users(repository.inner().as_ref(), &csv, dry_run.unwrap_or(false)).await)
+}
+
+#[openapi(tag = "Admin")]
+#[post("/admin/import/tasks?<dry_run>", data = "<csv>")]
+pub async fn import_tasks_csv<'a>(session: Session, dry_run: Option<bool>, csv: String, repository: &State<DynRepository>) -> Result<Custom<Json<CsvImportReport>>, Custom<String>> {
+    require_admin(&session)?;
+    csv_import_response(bulk_import::import_tasks(repository.inner().as_ref(), &csv, dry_run.unwrap_or(false)).await)
+}
+
+// Not part of the OpenAPI document, like the metrics the response is no json
+#[get("/admin/import/users/template")]
+pub fn get_users_csv_template() -> CsvFileResponder {
+    CsvFileResponder::create("users.csv", USER_TEMPLATE.to_owned())
+}
+
+#[get("/admin/import/tasks/template")]
+pub fn get_tasks_csv_template() -> CsvFileResponder {
+    CsvFileResponder::create("tasks.csv", TASK_TEMPLATE.to_owned())
+}
@@ -0,0 +1,91 @@
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::logging::request_tracing::RequestId;
+use crate::model::{Session, User};
+use crate::model::challenge::{Challenge, ChallengeRequest, ChallengeStatus, Participant};
+use crate::model::snapshot::TeamRecord;
+use crate::repository::repository::DynRepository;
+
+#[openapi(tag = "Challenge")]
+#[get("/challenge/all")]
+pub async fn get_all_challenges<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Challenge>> {
+    Json(repository.get_challenges().instrument(request_id.span()).await)
+}
+
+// Running challenges come with the standings so far
+#[openapi(tag = "Challenge")]
+#[get("/challenge/<id>")]
+pub async fn get_challenge<'a>(id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Challenge>> {
+    let mut challenge = repository.get_challenges().instrument(request_id.span()).await.into_iter().find(|challenge| challenge.id == id)?;
+    if challenge.status == ChallengeStatus::Accepted {
+        let users = repository.get_all_users().instrument(request_id.span()).await;
+        let teams = repository.get_all_teams().instrument(request_id.span()).await;
+        challenge.results = challenge.standings(&users, &teams);
+    }
+    Some(Json(challenge))
+}
+
+#[openapi(tag = "Challenge")]
+#[post("/challenge", data = "<challenge>")]
+pub async fn propose_challenge<'a>(session: Session, challenge: Json<ChallengeRequest>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Challenge>, Custom<String>> {
+    let user = session.user.lock().unwrap().clone();
+    let challenge = Challenge::new(user.username.clone(), challenge.into_inner());
+    challenge.check(Utc::now()).map_err(|msg| Custom(Status::BadRequest, msg))?;
+
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    for participant in [challenge.challenger, challenge.opponent] {
+        let exists = match participant {
+            Participant::User(user_id) => repository.get_user(user_id).instrument(request_id.span()).await.is_some(),
+            Participant::Team(team_id) => teams.iter().any(|team| team.id == team_id),
+        };
+        if !exists {
+            return Err(Custom(Status::NotFound, format!("Participant {:?} does not exist", participant)));
+        }
+    }
+    for task_id in challenge.task_ids.iter() {
+        repository.get_task(*task_id).instrument(request_id.span()).await.ok_or(Custom(Status::BadRequest, format!("Task with id {} does not exist", task_id)))?;
+    }
+    if !may_act_for(&user, &challenge.challenger, &teams) {
+        return Err(Custom(Status::Forbidden, "Only the user or the manager of the team are able to challenge others".to_owned()));
+    }
+
+    repository.propose_challenge(challenge).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Challenge")]
+#[put("/challenge/<id>/accept")]
+pub async fn accept_challenge<'a>(session: Session, id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Challenge>, Custom<String>> {
+    decide_challenge(session, id, true, repository, request_id).await
+}
+
+#[openapi(tag = "Challenge")]
+#[put("/challenge/<id>/decline")]
+pub async fn decline_challenge<'a>(session: Session, id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Challenge>, Custom<String>> {
+    decide_challenge(session, id, false, repository, request_id).await
+}
+
+// Users speak for themselves and managers for their teams, admins for everybody
+fn may_act_for(user: &User, participant: &Participant, teams: &[TeamRecord]) -> bool {
+    match participant {
+        Participant::User(user_id) => user.is_admin || user.id == *user_id,
+        Participant::Team(team_id) => user.is_admin || teams.iter().any(|team| team.id == *team_id && team.manager_id == user.id),
+    }
+}
+
+async fn decide_challenge(session: Session, id: u32, accepted: bool, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Challenge>, Custom<String>> {
+    let challenge = repository.get_challenges().instrument(request_id.span()).await.into_iter().find(|challenge| challenge.id == id)
+        .ok_or(Custom(Status::NotFound, format!("Challenge with id {} does not exist", id)))?;
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    if !may_act_for(&user, &challenge.opponent, &teams) {
+        return Err(Custom(Status::Forbidden, "Only the challenged user or the manager of the challenged team are able to answer".to_owned()));
+    }
+
+    repository.decide_challenge(id, accepted, user.username).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
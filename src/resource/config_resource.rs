@@ -1,22 +1,63 @@
-use dotenv::dotenv;
-use rocket::{http::Status};
+use chrono::Utc;
+use rocket::{http::Status, State};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
 use rocket_okapi::openapi;
-use std::env::{self};
 
+use crate::config::app_config::AppConfig;
+use crate::config::settings::Settings;
 use crate::model::{Session};
+use crate::model::setting::{Setting, SettingValue, SettingChange};
+use crate::repository::repository::DynRepository;
 
+use super::http::authorization::require_admin;
 use super::http::responder::KeyValueListResponder;
 
 
 #[openapi(tag = "Config")]
 #[get("/config")]
-pub fn get_config<'a>(session: Session) -> KeyValueListResponder<String, String> {
+pub fn get_config<'a>(session: Session, config: &State<AppConfig>) -> KeyValueListResponder<String, String> {
     if !session.user.lock().unwrap().is_admin {
         return KeyValueListResponder::create(Status::Forbidden, vec![]);
     }
 
-    dotenv().ok();
-    let config_vec = env::vars().collect();
+    KeyValueListResponder::create_ok(config.to_key_values())
+}
 
-    KeyValueListResponder::create_ok(config_vec)
-}
\ No newline at end of file
+#[openapi(tag = "Config")]
+#[get("/config/settings")]
+pub fn get_all_settings<'a>(session: Session, settings: &State<Settings>) -> Result<Json<Vec<Setting>>, Custom<String>> {
+    require_admin(&session)?;
+    Ok(Json(settings.get_all()))
+}
+
+#[openapi(tag = "Config")]
+#[get("/config/settings/<key>")]
+pub fn get_setting<'a>(session: Session, key: String, settings: &State<Settings>) -> Result<Json<Setting>, Custom<String>> {
+    require_admin(&session)?;
+    settings.get(&key).map(Json).ok_or(Custom(Status::NotFound, format!("Setting '{}' does not exist", key)))
+}
+
+#[openapi(tag = "Config")]
+#[put("/config/settings/<key>", data = "<value>")]
+pub async fn put_setting<'a>(session: Session, key: String, value: Json<SettingValue>, settings: &State<Settings>, repository: &State<DynRepository>) -> Result<Json<Setting>, Custom<String>> {
+    require_admin(&session)?;
+    let value = value.into_inner();
+    let old_value = settings.get(&key).ok_or(Custom(Status::NotFound, format!("Setting '{}' does not exist", key)))?.value;
+    Settings::validate(&key, &value).map_err(|msg| Custom(Status::BadRequest, msg))?;
+
+    let changed_by = session.user.lock().unwrap().username.clone();
+    let change = SettingChange { key: key.clone(), old_value, new_value: value.clone(), changed_by, changed_at: Utc::now() };
+    repository.save_setting(change).await.map_err(|msg| Custom(Status::InternalServerError, msg))?;
+
+    // Takes effect right away, no restart required
+    settings.set(&key, value).map_err(|msg| Custom(Status::BadRequest, msg))?;
+    settings.get(&key).map(Json).ok_or(Custom(Status::NotFound, format!("Setting '{}' does not exist", key)))
+}
+
+#[openapi(tag = "Config")]
+#[get("/config/settings/<key>/history")]
+pub async fn get_setting_history<'a>(session: Session, key: String, repository: &State<DynRepository>) -> Result<Json<Vec<SettingChange>>, Custom<String>> {
+    require_admin(&session)?;
+    Ok(Json(repository.get_setting_history(&key).await))
+}
@@ -0,0 +1,50 @@
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Shutdown, State};
+use crate::model::Session;
+use crate::model::event::{ActivityMessage, LastEventId};
+use crate::repository::repository::DynRepository;
+
+// Not part of the OpenAPI document, as okapi is unable to describe event streams
+#[get("/events?<team_id>")]
+pub async fn get_events<'a>(_session: Session, team_id: Option<u32>, last_event_id: LastEventId, repository: &State<DynRepository>, mut shutdown: Shutdown) -> EventStream![] {
+    let event_bus = repository.event_bus();
+
+    // Subscribe before looking at the history, so no message published in between gets lost
+    let mut receiver = event_bus.subscribe();
+    let missed = last_event_id.0.map_or(vec![], |id| event_bus.published_after(id));
+    let mut last_sent_id = missed.last().map_or(last_event_id.0.unwrap_or(0), |message| message.id);
+
+    EventStream! {
+        for message in missed {
+            if team_id.map_or(true, |team_id| message.concerns_team(team_id)) {
+                yield to_sse_event(&message);
+            }
+        }
+
+        loop {
+            let message = select! {
+                received = receiver.recv() => match received {
+                    Ok(message) => message,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            if message.id <= last_sent_id || !team_id.map_or(true, |team_id| message.concerns_team(team_id)) {
+                continue;
+            }
+
+            last_sent_id = message.id;
+            yield to_sse_event(&message);
+        }
+    }
+}
+
+fn to_sse_event(message: &ActivityMessage) -> Event {
+    Event::json(&message.event)
+        .id(message.id.to_string())
+        .event(message.event.name())
+}
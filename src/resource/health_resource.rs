@@ -0,0 +1,24 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use crate::model::health::{HealthReport, HealthStatus};
+use crate::repository::repository::DynRepository;
+use tracing::Instrument;
+use crate::logging::request_tracing::RequestId;
+
+#[openapi(tag = "Health")]
+#[get("/health/live")]
+pub async fn get_liveness<'a>() -> Json<HealthReport> {
+    Json(HealthReport::create(vec![]))
+}
+
+#[openapi(tag = "Health")]
+#[get("/health/ready")]
+pub async fn get_readiness<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Custom<Json<HealthReport>> {
+    let report = HealthReport::create(repository.check_health().instrument(request_id.span()).await);
+    let status = if report.status == HealthStatus::Up { Status::Ok } else { Status::ServiceUnavailable };
+
+    Custom(status, Json(report))
+}
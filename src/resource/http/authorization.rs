@@ -0,0 +1,18 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
+
+use crate::model::{Session, User};
+use crate::model::snapshot::TeamRecord;
+
+pub fn require_admin(session: &Session) -> Result<(), Custom<String>> {
+    if session.user.lock().unwrap().is_admin {
+        Ok(())
+    } else {
+        Err(Custom(Status::Forbidden, "You are not an admin".to_owned()))
+    }
+}
+
+// Admins manage everyone, team managers the members of their teams
+pub fn manages(user: &User, user_id: u32, teams: &[TeamRecord]) -> bool {
+    user.is_admin || teams.iter().any(|team| team.manager_id == user.id && team.member_ids.contains(&user_id))
+}
@@ -1,2 +1,3 @@
 pub mod responder;
+pub mod authorization;
 mod okapi;
\ No newline at end of file
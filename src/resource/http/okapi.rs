@@ -1,8 +1,10 @@
+use rocket::serde::json::Json;
 use rocket_okapi::{response::OpenApiResponderInner, gen::OpenApiGenerator, OpenApiError};
 use okapi::{openapi3::Responses, Map};
+use schemars::JsonSchema;
 use serde::Serialize;
 
-use super::responder::{MessageResponder, KeyValueListResponder};
+use super::responder::{MessageResponder, KeyValueListResponder, JsonOrCsvResponder};
 
 impl <A> OpenApiResponderInner for MessageResponder<A> where A: ToString {
     fn responses(_generator: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
@@ -94,3 +96,10 @@ impl <A, B> OpenApiResponderInner for KeyValueListResponder<A, B> where A: ToStr
         })
     }
 }
+
+// Only the json variant can be described, the CSV download has the same content
+impl <T> OpenApiResponderInner for JsonOrCsvResponder<T> where T: Serialize + JsonSchema + Send {
+    fn responses(generator: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
+        Json::<T>::responses(generator)
+    }
+}
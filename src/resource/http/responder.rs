@@ -1,7 +1,9 @@
 use std::io::Cursor;
 use serde::{Serialize};
 
-use rocket::{Request, Response, http::{Header, Status, ContentType}, response::{Responder}};
+use rocket::{Request, Response, http::{Header, Status, ContentType}, response::{Responder, stream::TextStream}};
+use rocket::futures::stream;
+use rocket::serde::json::Json;
 
 pub struct MessageResponder<A> where A: ToString {
     pub content: Option<A>,
@@ -27,6 +29,11 @@ impl <A> MessageResponder<A> where A: ToString {
     pub fn create_ok_empty() -> MessageResponder<A> {
         MessageResponder{ content: None, message: None, status: Status::Ok }
     }
+
+    #[allow(unused)]
+    pub fn status(&self) -> Status {
+        self.status
+    }
 }
 
 impl <'r, A> Responder<'r, 'static> for MessageResponder<A> where A: ToString {
@@ -83,3 +90,45 @@ impl <'r, A, B> Responder<'r, 'static> for KeyValueListResponder<A, B> where A:
     }
 
 }
+
+// A CSV document which browsers offer to save under the given file name, streamed line by line
+pub struct CsvFileResponder {
+    file_name: String,
+    lines: Box<dyn Iterator<Item = String> + Send>,
+}
+
+impl CsvFileResponder {
+    pub fn create(file_name: &str, content: String) -> CsvFileResponder {
+        CsvFileResponder::stream(file_name, std::iter::once(content))
+    }
+
+    pub fn stream(file_name: &str, lines: impl Iterator<Item = String> + Send + 'static) -> CsvFileResponder {
+        CsvFileResponder { file_name: file_name.to_owned(), lines: Box::new(lines) }
+    }
+}
+
+impl <'r> Responder<'r, 'r> for CsvFileResponder {
+    fn respond_to(self, request: &'r Request<'_>) ->  rocket::response::Result<'r> {
+
+        let mut response = TextStream(stream::iter(self.lines)).respond_to(request)?;
+        response.set_header(ContentType::CSV);
+        response.set_header(Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", self.file_name)));
+
+        Ok(response)
+    }
+}
+
+// The same content as json or as CSV download, whichever the client asked for
+pub enum JsonOrCsvResponder<T> where T: Serialize {
+    Json(Json<T>),
+    Csv(CsvFileResponder),
+}
+
+impl <'r, T> Responder<'r, 'r> for JsonOrCsvResponder<T> where T: Serialize {
+    fn respond_to(self, request: &'r Request<'_>) ->  rocket::response::Result<'r> {
+        match self {
+            JsonOrCsvResponder::Json(json) => json.respond_to(request),
+            JsonOrCsvResponder::Csv(csv) => csv.respond_to(request),
+        }
+    }
+}
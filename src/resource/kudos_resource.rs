@@ -0,0 +1,48 @@
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::config::settings::Settings;
+use crate::logging::request_tracing::RequestId;
+use crate::model::Session;
+use crate::model::kudos::{Kudos, KudosAllowance, KudosRequest};
+use crate::repository::repository::DynRepository;
+
+// The points come out of the giver's weekly allowance and are booked for the recipient
+#[openapi(tag = "Kudos")]
+#[post("/kudos", data = "<request>")]
+pub async fn give_kudos<'a>(session: Session, request: Json<KudosRequest>, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<Kudos>, Custom<String>> {
+    let user_id = session.user.lock().unwrap().id;
+    let kudos = Kudos::new(user_id, request.into_inner());
+    kudos.check().map_err(|msg| Custom(Status::BadRequest, msg))?;
+    repository.get_user(kudos.to_user_id).instrument(request_id.span()).await
+        .ok_or(Custom(Status::NotFound, format!("User with id {} does not exist", kudos.to_user_id)))?;
+
+    repository.give_kudos(kudos, settings.kudos_limits()).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Kudos")]
+#[get("/kudos")]
+pub async fn get_own_kudos<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Kudos>> {
+    let user_id = session.user.lock().unwrap().id;
+    Json(repository.get_kudos(Some(user_id)).instrument(request_id.span()).await)
+}
+
+#[openapi(tag = "Kudos")]
+#[get("/kudos/allowance")]
+pub async fn get_kudos_allowance<'a>(session: Session, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Json<KudosAllowance> {
+    let user_id = session.user.lock().unwrap().id;
+    let kudos = repository.get_kudos(Some(user_id)).instrument(request_id.span()).await;
+    Json(KudosAllowance::create(user_id, &kudos, &settings.kudos_limits(), Utc::now()))
+}
+
+// Given or received by the user
+#[openapi(tag = "Kudos")]
+#[get("/kudos/<user_id>")]
+pub async fn get_kudos_of_user<'a>(user_id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Kudos>> {
+    Json(repository.get_kudos(Some(user_id)).instrument(request_id.span()).await)
+}
@@ -0,0 +1,20 @@
+use chrono::Utc;
+use rocket::http::{ContentType, Status};
+use rocket::response::status::Custom;
+use rocket::State;
+use crate::config::settings::Settings;
+use crate::metrics::metrics::Metrics;
+use crate::repository::repository::DynRepository;
+
+// Not part of the OpenAPI document, the Prometheus text format is no json
+#[get("/metrics")]
+pub async fn get_metrics<'a>(repository: &State<DynRepository>, settings: &State<Settings>) -> Result<(ContentType, String), Custom<String>> {
+    // Sessions end in too many ways (logout, expiry, disabled users, imports) to count them along, so they are counted on scrape
+    let sessions = repository.count_sessions(Utc::now() - settings.session_lifetime()).await;
+    Metrics::global().active_sessions.set(sessions as i64);
+
+    let content_type = ContentType::new("text", "plain").with_params(("version", "0.0.4"));
+    Metrics::global().render()
+        .map(|rendered| (content_type, rendered))
+        .map_err(|msg| Custom(Status::InternalServerError, msg))
+}
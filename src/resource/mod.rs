@@ -5,4 +5,17 @@ pub mod session_resource;
 pub mod user_resource;
 pub mod task_resource;
 pub mod score_resource;
+pub mod event_resource;
+pub mod webhook_resource;
+pub mod health_resource;
+pub mod metrics_resource;
+pub mod admin_resource;
+pub mod report_resource;
+pub mod reward_resource;
+pub mod points_resource;
+pub mod season_resource;
+pub mod kudos_resource;
+pub mod verification_resource;
+pub mod challenge_resource;
+pub mod schedule_resource;
 pub mod http;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::logging::request_tracing::RequestId;
+use crate::model::Session;
+use crate::model::history::ScoreFilter;
+use crate::model::ledger::{PeriodPoints, PointBooking, PointTransaction};
+use crate::repository::repository::DynRepository;
+
+use super::http::authorization::{manages, require_admin};
+
+#[openapi(tag = "Points")]
+#[get("/points/ledger")]
+pub async fn get_own_ledger<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<PointTransaction>> {
+    let user_id = session.user.lock().unwrap().id;
+    Json(repository.get_ledger(Some(user_id)).instrument(request_id.span()).await)
+}
+
+#[openapi(tag = "Points")]
+#[get("/points/<user_id>/ledger")]
+pub async fn get_ledger_of_user<'a>(session: Session, user_id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Vec<PointTransaction>>, Custom<String>> {
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    if user.id != user_id && !manages(&user, user_id, &teams) {
+        return Err(Custom(Status::Forbidden, "Only managers of the user's teams are able to see the ledger".to_owned()));
+    }
+    Ok(Json(repository.get_ledger(Some(user_id)).instrument(request_id.span()).await))
+}
+
+// Points earned between the given dates, both bounds are inclusive and optional
+#[openapi(tag = "Points")]
+#[get("/points/<user_id>/period?<from>&<to>")]
+pub async fn get_period_points<'a>(user_id: u32, from: Option<String>, to: Option<String>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<PeriodPoints>, Custom<String>> {
+    let filter = ScoreFilter::parse(from.as_deref(), to.as_deref(), None).map_err(|msg| Custom(Status::BadRequest, msg))?;
+    repository.get_user(user_id).instrument(request_id.span()).await.ok_or(Custom(Status::NotFound, format!("User with id {} does not exist", user_id)))?;
+
+    let ledger = repository.get_ledger(Some(user_id)).instrument(request_id.span()).await;
+    Ok(Json(PeriodPoints::create(user_id, &ledger, &filter)))
+}
+
+// Bonuses, revokes and adjustments are booked by admins, the other kinds follow from scoring and redeeming
+#[openapi(tag = "Points")]
+#[post("/points/<user_id>", data = "<booking>")]
+pub async fn book_points<'a>(session: Session, user_id: u32, booking: Json<PointBooking>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<PointTransaction>, Custom<String>> {
+    require_admin(&session)?;
+    let booked_by = session.user.lock().unwrap().username.clone();
+    let booking = booking.into_inner();
+
+    let transaction = PointTransaction::new(user_id, booking.kind, booking.amount, booking.reference, Some(booked_by));
+    transaction.check_manual().map_err(|msg| Custom(Status::BadRequest, msg))?;
+    repository.add_transaction(transaction).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::NotFound, msg))
+}
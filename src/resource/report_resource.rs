@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::logging::request_tracing::RequestId;
+use crate::model::Session;
+use crate::model::history::ScoreFilter;
+use crate::model::report::{ReportFormat, ReportGrouping, ScoreReport};
+use crate::model::snapshot::TeamRecord;
+use crate::repository::repository::DynRepository;
+
+use super::http::responder::{CsvFileResponder, JsonOrCsvResponder};
+
+// Admins see everyone, team managers only the members of their teams. CSV is the default, as the reports mostly end up in spreadsheets.
+#[openapi(tag = "Report")]
+#[get("/report/scores?<from>&<to>&<task_id>&<group_by>&<format>")]
+pub async fn get_score_report<'a>(session: Session, from: Option<String>, to: Option<String>, task_id: Option<u32>, group_by: Option<ReportGrouping>, format: Option<ReportFormat>,
+        repository: &State<DynRepository>, request_id: RequestId) -> Result<JsonOrCsvResponder<ScoreReport>, Custom<String>> {
+    let filter = ScoreFilter::parse(from.as_deref(), to.as_deref(), task_id).map_err(|msg| Custom(Status::BadRequest, msg))?;
+    let (user_id, is_admin) = {
+        let user = session.user.lock().unwrap();
+        (user.id, user.is_admin)
+    };
+
+    let teams: Vec<TeamRecord> = repository.get_all_teams().instrument(request_id.span()).await.into_iter()
+        .filter(|team| is_admin || team.manager_id == user_id)
+        .collect();
+    let visible_user_ids: Option<HashSet<u32>> = match is_admin {
+        true => None,
+        false if teams.is_empty() => return Err(Custom(Status::Forbidden, "Only admins and team managers are able to see reports".to_owned())),
+        false => Some(teams.iter().flat_map(|team| team.member_ids.iter().cloned()).collect()),
+    };
+
+    let aggregates = repository.aggregate_scores(&filter).instrument(request_id.span()).await.map_err(|msg| Custom(Status::InternalServerError, msg))?;
+    let report = ScoreReport::create(aggregates, &teams, visible_user_ids.as_ref(), &filter, group_by.unwrap_or(ReportGrouping::User));
+
+    Ok(match format.unwrap_or(ReportFormat::Csv) {
+        ReportFormat::Csv => JsonOrCsvResponder::Csv(CsvFileResponder::stream("score-report.csv", report.into_csv_lines())),
+        ReportFormat::Json => JsonOrCsvResponder::Json(Json(report)),
+    })
+}
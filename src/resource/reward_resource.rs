@@ -0,0 +1,127 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::logging::request_tracing::RequestId;
+use crate::model::{Session, User};
+use crate::model::ledger::Balance;
+use crate::model::reward::{Redemption, RedemptionStatus, Reward};
+use crate::model::snapshot::TeamRecord;
+use crate::repository::repository::DynRepository;
+
+use super::http::authorization::{manages, require_admin};
+
+#[openapi(tag = "Reward")]
+#[get("/reward/all")]
+pub async fn get_all_rewards<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Reward>> {
+    Json(repository.get_all_rewards().instrument(request_id.span()).await)
+}
+
+#[openapi(tag = "Reward")]
+#[get("/reward/<id>")]
+pub async fn get_reward<'a>(id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Reward>> {
+    repository.get_reward(id).instrument(request_id.span()).await.map(Json)
+}
+
+#[openapi(tag = "Reward")]
+#[post("/reward", data = "<reward>")]
+pub async fn add_reward<'a>(session: Session, reward: Json<Reward>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Reward>, Custom<String>> {
+    require_admin(&session)?;
+    let reward = Reward { id: 0, ..reward.into_inner() };
+    save_reward(reward, repository, request_id).await
+}
+
+#[openapi(tag = "Reward")]
+#[put("/reward/<id>", data = "<reward>")]
+pub async fn update_reward<'a>(session: Session, id: u32, reward: Json<Reward>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Reward>, Custom<String>> {
+    require_admin(&session)?;
+    repository.get_reward(id).await.ok_or(Custom(Status::NotFound, format!("Reward with id {} does not exist", id)))?;
+    let reward = Reward { id, ..reward.into_inner() };
+    save_reward(reward, repository, request_id).await
+}
+
+async fn save_reward(reward: Reward, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Reward>, Custom<String>> {
+    if reward.name.trim().is_empty() {
+        return Err(Custom(Status::BadRequest, "The name of a reward must not be empty".to_owned()));
+    }
+    repository.save_reward(reward).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Reward")]
+#[post("/reward/<id>/redeem")]
+pub async fn redeem_reward<'a>(session: Session, id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Redemption>, Custom<String>> {
+    let user_id = session.user.lock().unwrap().id;
+    repository.get_reward(id).await.ok_or(Custom(Status::NotFound, format!("Reward with id {} does not exist", id)))?;
+    repository.redeem(user_id, id).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Reward")]
+#[get("/reward/balance")]
+pub async fn get_balance<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Balance> {
+    let user_id = session.user.lock().unwrap().id;
+    let ledger = repository.get_ledger(Some(user_id)).instrument(request_id.span()).await;
+    Json(Balance::create(user_id, &ledger))
+}
+
+#[openapi(tag = "Reward")]
+#[get("/reward/redemptions")]
+pub async fn get_own_redemptions<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Redemption>> {
+    let user_id = session.user.lock().unwrap().id;
+    Json(repository.get_redemptions(Some(user_id)).instrument(request_id.span()).await)
+}
+
+#[openapi(tag = "Reward")]
+#[get("/reward/redemptions/<user_id>")]
+pub async fn get_redemptions_of_user<'a>(session: Session, user_id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Vec<Redemption>>, Custom<String>> {
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    if user.id != user_id && !manages(&user, user_id, &teams) {
+        return Err(Custom(Status::Forbidden, "Only managers of the user's teams are able to see the redemptions".to_owned()));
+    }
+    Ok(Json(repository.get_redemptions(Some(user_id)).instrument(request_id.span()).await))
+}
+
+// The pending redemptions the current user is allowed to decide on
+#[openapi(tag = "Reward")]
+#[get("/reward/redemptions/pending")]
+pub async fn get_pending_redemptions<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Redemption>> {
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    let redemptions = repository.get_redemptions(None).instrument(request_id.span()).await;
+
+    Json(redemptions.into_iter()
+        .filter(|redemption| redemption.status == RedemptionStatus::Pending && may_decide(&user, redemption, &teams))
+        .collect())
+}
+
+#[openapi(tag = "Reward")]
+#[put("/reward/redemption/<id>/approve")]
+pub async fn approve_redemption<'a>(session: Session, id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Redemption>, Custom<String>> {
+    decide_redemption(session, id, true, repository, request_id).await
+}
+
+#[openapi(tag = "Reward")]
+#[put("/reward/redemption/<id>/reject")]
+pub async fn reject_redemption<'a>(session: Session, id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Redemption>, Custom<String>> {
+    decide_redemption(session, id, false, repository, request_id).await
+}
+
+// Managers must not approve what they redeemed themselves, admins are trusted to
+fn may_decide(user: &User, redemption: &Redemption, teams: &[TeamRecord]) -> bool {
+    manages(user, redemption.user_id, teams) && (user.is_admin || user.id != redemption.user_id)
+}
+
+async fn decide_redemption(session: Session, id: u32, approved: bool, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Redemption>, Custom<String>> {
+    let redemption = repository.get_redemptions(None).instrument(request_id.span()).await.into_iter().find(|redemption| redemption.id == id)
+        .ok_or(Custom(Status::NotFound, format!("Redemption with id {} does not exist", id)))?;
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    if !may_decide(&user, &redemption, &teams) {
+        return Err(Custom(Status::Forbidden, "Only managers of the user's teams are able to decide on the redemption".to_owned()));
+    }
+
+    repository.decide_redemption(id, approved, user.username).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
@@ -0,0 +1,73 @@
+use chrono::{Duration, Utc};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::logging::request_tracing::RequestId;
+use crate::model::Session;
+use crate::model::schedule::{Assignment, Rota, Schedule, ScheduleRequest};
+use crate::repository::repository::DynRepository;
+
+use super::http::authorization::require_admin;
+
+const DEFAULT_DAYS: u32 = 7;
+const MAX_DAYS: u32 = 90;
+
+#[openapi(tag = "Schedule")]
+#[get("/schedule/all")]
+pub async fn get_all_schedules<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Schedule>> {
+    Json(repository.get_schedules().instrument(request_id.span()).await)
+}
+
+// Replaces the schedule of the task, turns are counted anew from its start
+#[openapi(tag = "Schedule")]
+#[put("/schedule/<task_id>", data = "<schedule>")]
+pub async fn put_schedule<'a>(session: Session, task_id: u32, schedule: Json<ScheduleRequest>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Schedule>, Custom<String>> {
+    require_admin(&session)?;
+    repository.get_task(task_id).instrument(request_id.span()).await.ok_or(Custom(Status::NotFound, format!("Task with id {} does not exist", task_id)))?;
+    let schedule = Schedule::new(task_id, schedule.into_inner()).map_err(|msg| Custom(Status::BadRequest, msg))?;
+    match &schedule.rota {
+        Rota::Users(user_ids) => for user_id in user_ids.iter() {
+            repository.get_user(*user_id).instrument(request_id.span()).await.ok_or(Custom(Status::BadRequest, format!("User with id {} does not exist", user_id)))?;
+        },
+        Rota::Team(team_id) => if !repository.get_all_teams().instrument(request_id.span()).await.iter().any(|team| team.id == *team_id) {
+            return Err(Custom(Status::BadRequest, format!("Team with id {} does not exist", team_id)));
+        },
+    }
+
+    repository.save_schedule(schedule).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Schedule")]
+#[delete("/schedule/<task_id>")]
+pub async fn remove_schedule<'a>(session: Session, task_id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<()>, Custom<String>> {
+    require_admin(&session)?;
+    repository.remove_schedule(task_id).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::NotFound, msg))
+}
+
+// What the user has been and will be assigned within the given number of days
+#[openapi(tag = "Schedule")]
+#[get("/user/<id>/assignments?<days>")]
+pub async fn get_assignments_of_user<'a>(id: u32, days: Option<u32>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Vec<Assignment>>, Custom<String>> {
+    let days = days.unwrap_or(DEFAULT_DAYS);
+    if days > MAX_DAYS {
+        return Err(Custom(Status::BadRequest, format!("Assignments are only listed for up to {} days", MAX_DAYS)));
+    }
+    repository.get_user(id).instrument(request_id.span()).await.ok_or(Custom(Status::NotFound, format!("User with id {} does not exist", id)))?;
+
+    let now = Utc::now();
+    let users = repository.get_all_users().instrument(request_id.span()).await;
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let tasks = repository.get_all_tasks().instrument(request_id.span()).await;
+
+    let mut assignments: Vec<Assignment> = repository.get_schedules().instrument(request_id.span()).await.iter()
+        .filter_map(|schedule| tasks.iter().find(|task| task.id == schedule.task_id && task.enabled).map(|task| (schedule, task)))
+        .flat_map(|(schedule, task)| schedule.assignments(task, &users, &teams, now - Duration::days(days as i64), now + Duration::days(days as i64), now))
+        .filter(|assignment| assignment.user_id == id)
+        .collect();
+    assignments.sort_by_key(|assignment| assignment.due);
+    Ok(Json(assignments))
+}
@@ -1,33 +1,97 @@
 use futures::executor::block_on;
-use rocket::response::status::NotFound;
+use rocket::http::Status;
+use rocket::response::status::Custom;
 use rocket::serde::json::Json;
 use rocket::State;
 use rocket_okapi::openapi;
-use crate::repository::repository::Repository;
+use crate::repository::repository::DynRepository;
+use tracing::Instrument;
+use crate::config::settings::Settings;
+use crate::logging::request_tracing::RequestId;
 use crate::model::{Score, Session};
-use crate::repository::neo4j_repsitory::Neo4JRepository;
+use crate::model::event::ActivityEvent;
+use crate::model::history::{HistoryGrouping, ScoreFilter, ScoreHistory};
+use crate::model::ledger::{PointTransaction, TransactionKind};
+use crate::model::streak::streak_bonuses;
 
 #[openapi(tag = "Score")]
 #[post("/score/<task_id>")]
-pub async fn score<'a>(session: Session, task_id: u32, repository: &State<Neo4JRepository>) -> Result<Json<u16>, NotFound<String>> {
+pub async fn score<'a>(session: Session, task_id: u32, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<u64>, Custom<String>> {
+    if settings.scoring_paused() {
+        return Err(Custom(Status::Forbidden, "Scoring is paused".to_owned()));
+    }
+
     let user_mutex_guard = session.user.lock().unwrap();
     let user_id = user_mutex_guard.id;
     std::mem::drop(user_mutex_guard);
 
-    match block_on(repository.score(user_id, task_id)) {
-        Ok(new_score) => Ok(Json(new_score)),
-        Err(msg) => Err(NotFound(msg))
+    if repository.get_task(task_id).instrument(request_id.span()).await.map_or(false, |task| task.requires_verification) {
+        return Err(Custom(Status::Conflict, format!("Task {} requires verification, claim the score instead", task_id)));
+    }
+
+    let points_before = repository.get_user(user_id).instrument(request_id.span()).await.map_or(0, |user| user.points);
+    match block_on(repository.score(user_id, task_id).instrument(request_id.span())) {
+        Ok(new_score) => Ok(Json(process_score(user_id, points_before, new_score, settings, repository).instrument(request_id.span()).await)),
+        Err(msg) => Err(Custom(Status::NotFound, msg))
+    }
+}
+
+// Everything that follows a booked score, no matter if it was scored right away or claimed and confirmed.
+// Returns the lifetime points including the streak bonuses
+pub async fn process_score(user_id: u32, points_before: u64, points: u64, settings: &Settings, repository: &DynRepository) -> u64 {
+    let total_points = book_streak_bonuses(user_id, settings, repository).await.unwrap_or(points);
+    publish_level_up(user_id, points_before, total_points, settings, repository).await;
+    total_points
+}
+
+// Announces the level the points are enough for, if it is higher than the one before scoring
+async fn publish_level_up(user_id: u32, points_before: u64, points: u64, settings: &Settings, repository: &DynRepository) {
+    let curve = settings.level_curve();
+    let level = curve.level_of(points);
+    if level <= curve.level_of(points_before) {
+        return;
+    }
+
+    if let Some(user) = repository.get_user(user_id).await {
+        let team_ids = repository.get_all_teams().await.into_iter().filter(|team| team.member_ids.contains(&user_id)).map(|team| team.id).collect();
+        repository.event_bus().publish(team_ids, ActivityEvent::LevelUp { user_id, display_name: user.display_name, level, title: curve.title_of(level) });
+    }
+}
+
+// Books a bonus for each streak the score has extended to a multiple of the interval, returning the new lifetime points if there was one
+async fn book_streak_bonuses(user_id: u32, settings: &Settings, repository: &DynRepository) -> Option<u64> {
+    let (interval, points) = settings.streak_bonus()?;
+    let scores = repository.get_user(user_id).await?.scores;
+
+    let mut booked = false;
+    for streak in streak_bonuses(&scores, &settings.streak_calendar(), interval) {
+        let bonus = PointTransaction::new(user_id, TransactionKind::Bonus, points, Some(streak.bonus_reference()), None);
+        booked |= repository.add_transaction(bonus).await.is_ok();
     }
+    if !booked {
+        return None;
+    }
+    repository.get_user(user_id).await.map(|user| user.points)
 }
 
 #[openapi(tag = "Score")]
 #[get("/score/<user_id>")]
-pub async fn get_score_of_user<'a>(user_id: u32, repository: &State<Neo4JRepository>) -> Option<Json<Vec<Score>>> {
-    repository.get_user(user_id).await.and_then(|user| Some(Json(user.scores)))
+pub async fn get_score_of_user<'a>(user_id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Vec<Score>>> {
+    repository.get_user(user_id).instrument(request_id.span()).await.and_then(|user| Some(Json(user.scores)))
 }
 
 #[openapi(tag = "Score")]
 #[get("/score")]
 pub async fn get_score_of_current_user<'a>(session: Session) -> Json<Vec<Score>> {
     Json(session.user.lock().unwrap().clone().scores)
+}
+
+#[openapi(tag = "Score")]
+#[get("/score/<user_id>/history?<from>&<to>&<task_id>&<group_by>")]
+pub async fn get_score_history_of_user<'a>(user_id: u32, from: Option<String>, to: Option<String>, task_id: Option<u32>, group_by: Option<HistoryGrouping>,
+        repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<ScoreHistory>, Custom<String>> {
+    let filter = ScoreFilter::parse(from.as_deref(), to.as_deref(), task_id).map_err(|msg| Custom(Status::BadRequest, msg))?;
+    let user = repository.get_user(user_id).instrument(request_id.span()).await.ok_or(Custom(Status::NotFound, format!("User with id {} does not exist", user_id)))?;
+
+    Ok(Json(ScoreHistory::create(user.id, &user.scores, &filter, group_by)))
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use tracing::Instrument;
+
+use crate::logging::request_tracing::RequestId;
+use crate::model::Session;
+use crate::model::event::LeaderboardEntry;
+use crate::model::season::{Season, SeasonRequest};
+use crate::model::trophy::Trophy;
+use crate::repository::repository::DynRepository;
+
+use super::http::authorization::require_admin;
+
+#[openapi(tag = "Season")]
+#[get("/season/all")]
+pub async fn get_all_seasons<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Season>> {
+    Json(repository.get_seasons().instrument(request_id.span()).await)
+}
+
+#[openapi(tag = "Season")]
+#[get("/season/active")]
+pub async fn get_active_season<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Season>> {
+    repository.get_seasons().instrument(request_id.span()).await.into_iter().find(|season| season.is_active()).map(Json)
+}
+
+#[openapi(tag = "Season")]
+#[get("/season/<id>")]
+pub async fn get_season<'a>(id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Season>> {
+    repository.get_seasons().instrument(request_id.span()).await.into_iter().find(|season| season.id == id).map(Json)
+}
+
+// Live while the season is running, the archived standings once it has ended
+#[openapi(tag = "Season")]
+#[get("/season/<id>/leaderboard")]
+pub async fn get_season_leaderboard<'a>(id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Vec<LeaderboardEntry>>> {
+    repository.get_season_leaderboard(id).instrument(request_id.span()).await.map(Json)
+}
+
+#[openapi(tag = "Season")]
+#[post("/season", data = "<season>")]
+pub async fn start_season<'a>(session: Session, season: Json<SeasonRequest>, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Season>, Custom<String>> {
+    require_admin(&session)?;
+    let season = season.into_inner();
+    let name = season.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(Custom(Status::BadRequest, "The name of a season must not be empty".to_owned()));
+    }
+    let now = Utc::now();
+    let start = season.start.unwrap_or(now);
+    if season.end.map_or(false, |end| end <= start || end <= now) {
+        return Err(Custom(Status::BadRequest, "The end of a season must lie after its start and in the future".to_owned()));
+    }
+    repository.start_season(Season::new(0, name, start, season.end)).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+// Archives the standings and awards trophies to the best three
+#[openapi(tag = "Season")]
+#[put("/season/<id>/end")]
+pub async fn end_season<'a>(session: Session, id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<Season>, Custom<String>> {
+    require_admin(&session)?;
+    if !repository.get_seasons().await.iter().any(|season| season.id == id) {
+        return Err(Custom(Status::NotFound, format!("Season with id {} does not exist", id)));
+    }
+    repository.end_season(id).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Season")]
+#[get("/trophy/<user_id>")]
+pub async fn get_trophies_of_user<'a>(user_id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Trophy>> {
+    Json(repository.get_trophies(Some(user_id)).instrument(request_id.span()).await)
+}
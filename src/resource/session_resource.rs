@@ -3,22 +3,28 @@ use rocket::response::status::NotFound;
 use rocket::serde::json::Json;
 use rocket::State;
 use rocket_okapi::openapi;
+use crate::metrics::metrics::Metrics;
 use crate::model::session::LoginRequest;
 use crate::model::{Session};
-use crate::repository::neo4j_repsitory::Neo4JRepository;
-use crate::repository::repository::Repository;
+use crate::repository::repository::DynRepository;
+use tracing::Instrument;
+use crate::logging::request_tracing::RequestId;
 
 #[openapi(tag = "Session")]
 #[post("/session/login")]
-pub async fn login<'a>(login_request: LoginRequest, repository: &State<Neo4JRepository>, jar: &CookieJar<'_>) -> Result<Json<Session>, NotFound<String>> {
-    let session_result = repository.login(login_request).await;
+pub async fn login<'a>(login_request: LoginRequest, repository: &State<DynRepository>, jar: &CookieJar<'_>, request_id: RequestId) -> Result<Json<Session>, NotFound<String>> {
+    let session_result = repository.login(login_request).instrument(request_id.span()).await;
     match session_result {
         Ok(session) => {
             let session_id: &str = session.id.as_str();
             jar.add(Cookie::new("sid", session_id).into_owned());
+            Metrics::global().logins.inc();
             Ok(Json(session))
         },
-        Err(error) => Err(NotFound(error))
+        Err(error) => {
+            Metrics::global().failed_logins.inc();
+            Err(NotFound(error))
+        }
     }
 }
 
@@ -30,9 +36,8 @@ pub async fn get_current_session<'a>(session: Session) -> Json<Session> {
 
 #[openapi(tag = "Session")]
 #[delete("/session/logout")]
-pub async fn logout<'a>(session: Session, repository: &State<Neo4JRepository>) -> Result<Json<()>, NotFound<String>> {
-    match repository.logout(&session.id).await {
-        Ok(_) => Ok(Json(())),
-        Err(msg) => Err(NotFound(msg))
-    }
+pub async fn logout<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Result<Json<()>, NotFound<String>> {
+    repository.logout(&session.id).instrument(request_id.span()).await
+        .map(Json)
+        .map_err(NotFound)
 }
\ No newline at end of file
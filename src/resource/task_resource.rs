@@ -1,18 +1,19 @@
 use rocket::serde::json::Json;
 use rocket::State;
 use rocket_okapi::openapi;
-use crate::repository::repository::Repository;
+use crate::repository::repository::DynRepository;
+use tracing::Instrument;
+use crate::logging::request_tracing::RequestId;
 use crate::model::{Task};
-use crate::repository::neo4j_repsitory::Neo4JRepository;
 
 #[openapi(tag = "Task")]
 #[get("/task/<id>")]
-pub async fn get_task<'a>(id: u32, repository: &State<Neo4JRepository>) -> Option<Json<Task>> {
-    repository.get_task(id).await.map_or(None, |task| Some(Json(task)))
+pub async fn get_task<'a>(id: u32, repository: &State<DynRepository>, request_id: RequestId) -> Option<Json<Task>> {
+    repository.get_task(id).instrument(request_id.span()).await.map_or(None, |task| Some(Json(task)))
 }
 
 #[openapi(tag = "Task")]
 #[get("/task/all")]
-pub async fn get_all_tasks<'a>(repository: &State<Neo4JRepository>) -> Json<Vec<Task>> {
-    Json(repository.get_all_tasks().await)
+pub async fn get_all_tasks<'a>(repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<Task>> {
+    Json(repository.get_all_tasks().instrument(request_id.span()).await)
 }
\ No newline at end of file
@@ -1,39 +1,79 @@
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::response::status::Custom;
 use rocket::serde::json::Json;
 use rocket::State;
 use rocket_okapi::openapi;
-use crate::repository::repository::Repository;
+use crate::repository::repository::DynRepository;
+use tracing::Instrument;
+use crate::config::settings::Settings;
+use crate::logging::request_tracing::RequestId;
 use crate::model::{User, Session};
-use crate::repository::neo4j_repsitory::Neo4JRepository;
+use crate::model::ledger::PointTransaction;
+use crate::model::level::LevelInfo;
+use crate::model::streak::StreakInfo;
+use crate::model::user::{Registration, UserDetails};
 
 use super::http::responder::MessageResponder;
 
+// Level and streaks are derived on every request, so changed settings apply right away
+fn user_details(user: User, ledger: &[PointTransaction], settings: &Settings, with_streaks: bool) -> UserDetails {
+    let level = LevelInfo::create(user.id, user.points, ledger, &settings.level_curve());
+    let streaks = with_streaks.then(|| StreakInfo::create(&user.scores, &settings.streak_calendar(), Utc::now().naive_utc().date()));
+    UserDetails { user, level, streaks }
+}
+
 #[openapi(tag = "User")]
 #[get("/user/<id>")]
-pub async fn get_user<'a>(id: u32, repository: &State<Neo4JRepository>) -> Option<Json<User>> {
-    // Option.map_or() returns a default value if the option is None, or otherwise applies a function to the in the Some(x) contained value, returning an option
-    repository.get_user(id).await.map_or(None, |user| Some(Json(user)))
+pub async fn get_user<'a>(id: u32, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Option<Json<UserDetails>> {
+    let user = repository.get_user(id).instrument(request_id.span()).await?;
+    let ledger = repository.get_ledger(Some(id)).instrument(request_id.span()).await;
+    Some(Json(user_details(user, &ledger, settings, true)))
 }
 
 #[openapi(tag = "User")]
 #[get("/user/username/<username>")]
-pub async fn get_user_by_username<'a>(username: String, repository: &State<Neo4JRepository>) -> Option<Json<User>> {
-    repository.find_user_by_username_const(&username).await.map_or(None, |user| Some(Json(user)))
+pub async fn get_user_by_username<'a>(username: String, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Option<Json<UserDetails>> {
+    let user = repository.find_user_by_username_const(&username).instrument(request_id.span()).await?;
+    let ledger = repository.get_ledger(Some(user.id)).instrument(request_id.span()).await;
+    Some(Json(user_details(user, &ledger, settings, true)))
 }
 
 #[openapi(tag = "User")]
 #[get("/user")]
-pub async fn get_current_user<'a>(session: Session) -> Json<User> {
-    Json(session.user.lock().unwrap().clone())
+pub async fn get_current_user<'a>(session: Session, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Json<UserDetails> {
+    let user = session.user.lock().unwrap().clone();
+    let ledger = repository.get_ledger(Some(user.id)).instrument(request_id.span()).await;
+    Json(user_details(user, &ledger, settings, true))
 }
 
 #[openapi(tag = "User")]
 #[get("/user/all")]
-pub async fn get_all_users<'a>(repository: &State<Neo4JRepository>) -> Json<Vec<User>> {
-    Json(repository.get_all_users().await)
+pub async fn get_all_users<'a>(repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Json<Vec<UserDetails>> {
+    let ledger = repository.get_ledger(None).instrument(request_id.span()).await;
+    let users = repository.get_all_users().instrument(request_id.span()).await;
+    Json(users.into_iter().map(|user| user_details(user, &ledger, settings, false)).collect())
 }
 
 #[openapi(tag = "User")]
 #[post("/user")]
-pub async fn add_user<'a>(session: Session, user: User, repository: &State<Neo4JRepository>) -> MessageResponder<u32> {
-    repository.add_user(&session, user).await
-}
\ No newline at end of file
+pub async fn add_user<'a>(session: Session, user: User, repository: &State<DynRepository>, request_id: RequestId) -> MessageResponder<u32> {
+    repository.add_user(&session, user).instrument(request_id.span()).await
+}
+
+#[openapi(tag = "User")]
+#[post("/user/register", data = "<registration>")]
+pub async fn register_user<'a>(registration: Json<Registration>, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<u32>, Custom<String>> {
+    if !settings.self_registration_enabled() {
+        return Err(Custom(Status::Forbidden, "Self-registration is disabled".to_owned()));
+    }
+    let Registration { username, display_name, password } = registration.into_inner();
+    if username.trim().is_empty() || display_name.trim().is_empty() || password.is_empty() {
+        return Err(Custom(Status::BadRequest, "Username, display name and password must not be empty".to_owned()));
+    }
+
+    let user = repository.create_and_add_user(username.trim().to_owned(), display_name.trim().to_owned(), password, false).instrument(request_id.span()).await
+        .map_err(|msg| Custom(Status::Conflict, msg))?;
+    let id = user.lock().unwrap().id;
+    Ok(Json(id))
+}
@@ -0,0 +1,86 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use chrono::Utc;
+use tracing::Instrument;
+
+use crate::config::settings::Settings;
+use crate::logging::request_tracing::RequestId;
+use crate::model::{Session, User};
+use crate::model::snapshot::TeamRecord;
+use crate::model::verification::{ClaimStatus, ScoreClaim};
+use crate::repository::repository::DynRepository;
+
+use super::http::authorization::manages;
+use super::score_resource::process_score;
+
+#[openapi(tag = "Verification")]
+#[post("/score/<task_id>/claim")]
+pub async fn claim_score<'a>(session: Session, task_id: u32, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<ScoreClaim>, Custom<String>> {
+    if settings.scoring_paused() {
+        return Err(Custom(Status::Forbidden, "Scoring is paused".to_owned()));
+    }
+    let user_id = session.user.lock().unwrap().id;
+    repository.get_task(task_id).instrument(request_id.span()).await.ok_or(Custom(Status::NotFound, format!("Task with id {} does not exist", task_id)))?;
+    repository.claim_score(user_id, task_id).instrument(request_id.span()).await.map(Json).map_err(|msg| Custom(Status::Conflict, msg))
+}
+
+#[openapi(tag = "Verification")]
+#[get("/verification")]
+pub async fn get_own_claims<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<ScoreClaim>> {
+    let user_id = session.user.lock().unwrap().id;
+    Json(repository.get_score_claims(Some(user_id)).instrument(request_id.span()).await)
+}
+
+// The pending claims the current user is allowed to decide on
+#[openapi(tag = "Verification")]
+#[get("/verification/pending")]
+pub async fn get_pending_claims<'a>(session: Session, repository: &State<DynRepository>, request_id: RequestId) -> Json<Vec<ScoreClaim>> {
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    let claims = repository.get_score_claims(None).instrument(request_id.span()).await;
+
+    Json(claims.into_iter()
+        .filter(|claim| claim.status == ClaimStatus::Pending && may_decide(&user, claim, &teams))
+        .collect())
+}
+
+#[openapi(tag = "Verification")]
+#[put("/verification/<id>/confirm")]
+pub async fn confirm_claim<'a>(session: Session, id: u32, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<ScoreClaim>, Custom<String>> {
+    decide_claim(session, id, true, repository, settings, request_id).await
+}
+
+#[openapi(tag = "Verification")]
+#[put("/verification/<id>/reject")]
+pub async fn reject_claim<'a>(session: Session, id: u32, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<ScoreClaim>, Custom<String>> {
+    decide_claim(session, id, false, repository, settings, request_id).await
+}
+
+// Nobody verifies their own claims, not even admins. Team mates and managers of the claimant may
+fn may_decide(user: &User, claim: &ScoreClaim, teams: &[TeamRecord]) -> bool {
+    let shares_team = teams.iter().any(|team| team.member_ids.contains(&user.id) && team.member_ids.contains(&claim.user_id));
+    user.id != claim.user_id && (shares_team || manages(user, claim.user_id, teams))
+}
+
+async fn decide_claim(session: Session, id: u32, confirmed: bool, repository: &State<DynRepository>, settings: &State<Settings>, request_id: RequestId) -> Result<Json<ScoreClaim>, Custom<String>> {
+    // The housekeeping expires claims only once a minute, a claim that expired since must not be decided anymore
+    repository.expire_score_claims(Utc::now() - settings.verification_expiry()).instrument(request_id.span()).await;
+    let claim = repository.get_score_claims(None).instrument(request_id.span()).await.into_iter().find(|claim| claim.id == id)
+        .ok_or(Custom(Status::NotFound, format!("Claim with id {} does not exist", id)))?;
+    let teams = repository.get_all_teams().instrument(request_id.span()).await;
+    let user = session.user.lock().unwrap().clone();
+    if !may_decide(&user, &claim, &teams) {
+        return Err(Custom(Status::Forbidden, "Only team mates and managers of the user are able to verify the score".to_owned()));
+    }
+
+    let points_before = repository.get_user(claim.user_id).instrument(request_id.span()).await.map_or(0, |user| user.points);
+    let claim = repository.decide_score_claim(id, confirmed, user.username).instrument(request_id.span()).await.map_err(|msg| Custom(Status::Conflict, msg))?;
+    if confirmed {
+        let points = repository.get_user(claim.user_id).instrument(request_id.span()).await.map_or(0, |user| user.points);
+        process_score(claim.user_id, points_before, points, settings, repository).instrument(request_id.span()).await;
+    }
+    Ok(Json(claim))
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use crate::event::webhook_service::WebhookService;
+use crate::model::Session;
+use crate::model::webhook::{NewWebhook, Webhook, WebhookDelivery};
+
+use super::http::authorization::require_admin;
+
+#[openapi(tag = "Webhook")]
+#[get("/webhook/all")]
+pub async fn get_all_webhooks<'a>(session: Session, webhook_service: &State<Arc<WebhookService>>) -> Result<Json<Vec<Webhook>>, Custom<String>> {
+    require_admin(&session)?;
+    Ok(Json(webhook_service.get_all_webhooks().await))
+}
+
+#[openapi(tag = "Webhook")]
+#[post("/webhook", data = "<new_webhook>")]
+pub async fn add_webhook<'a>(session: Session, new_webhook: Json<NewWebhook>, webhook_service: &State<Arc<WebhookService>>) -> Result<Json<Webhook>, Custom<String>> {
+    require_admin(&session)?;
+    webhook_service.add_webhook(new_webhook.into_inner()).await
+        .map(Json)
+        .map_err(|msg| Custom(Status::BadRequest, msg))
+}
+
+#[openapi(tag = "Webhook")]
+#[delete("/webhook/<id>")]
+pub async fn remove_webhook<'a>(session: Session, id: u32, webhook_service: &State<Arc<WebhookService>>) -> Result<Json<()>, Custom<String>> {
+    require_admin(&session)?;
+    webhook_service.remove_webhook(id).await
+        .map(Json)
+        .map_err(|msg| Custom(Status::NotFound, msg))
+}
+
+#[openapi(tag = "Webhook")]
+#[get("/webhook/<id>/deliveries")]
+pub async fn get_webhook_deliveries<'a>(session: Session, id: u32, webhook_service: &State<Arc<WebhookService>>) -> Result<Json<Vec<WebhookDelivery>>, Custom<String>> {
+    require_admin(&session)?;
+    Ok(Json(webhook_service.get_deliveries(id)))
+}
+
+#[openapi(tag = "Webhook")]
+#[get("/webhook/dead_letters")]
+pub async fn get_webhook_dead_letters<'a>(session: Session, webhook_service: &State<Arc<WebhookService>>) -> Result<Json<Vec<WebhookDelivery>>, Custom<String>> {
+    require_admin(&session)?;
+    Ok(Json(webhook_service.get_dead_letters().await))
+}
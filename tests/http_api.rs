@@ -0,0 +1,489 @@
+use rocket::figment::{Figment, providers::Serialized};
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::Client;
+
+use task_score::build_rocket;
+use task_score::config::app_config::AppConfig;
+
+// A fresh application with the in-memory demo data for each test
+async fn client() -> Client {
+    let config = AppConfig::load(&Figment::new().merge(Serialized::default("repository", "memory"))).unwrap();
+    Client::tracked(build_rocket(config).await.unwrap()).await.unwrap()
+}
+
+fn basic_auth(username: &str, password: &str) -> Header<'static> {
+    let credentials = base64::encode_config(format!("{}:{}", username, password), base64::URL_SAFE);
+    Header::new("Authorization", format!("Basic {}", credentials))
+}
+
+async fn login(client: &Client, username: &str, password: &str) -> Status {
+    client.post("/rest/session/login").header(basic_auth(username, password)).dispatch().await.status()
+}
+
+#[rocket::async_test]
+async fn test_login_score_logout() {
+    let client = client().await;
+
+    let response = client.post("/rest/session/login").header(basic_auth("roterkohl", "Flori1234")).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert!(response.cookies().get("sid").is_some());
+
+    let response = client.get("/rest/user").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert!(response.into_string().await.unwrap().contains("\"username\":\"roterkohl\""));
+
+    let response = client.post("/rest/score/4").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert_eq!("232", response.into_string().await.unwrap());
+
+    let response = client.delete("/rest/session/logout").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+
+    let response = client.get("/rest/user").dispatch().await;
+    assert_eq!(Status::Unauthorized, response.status());
+}
+
+#[rocket::async_test]
+async fn test_login_wrong_password() {
+    let client = client().await;
+    assert_eq!(Status::NotFound, login(&client, "roterkohl", "Flori5678").await);
+}
+
+#[rocket::async_test]
+async fn test_login_without_credentials() {
+    let client = client().await;
+    assert_eq!(Status::Unauthorized, client.post("/rest/session/login").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_session_required() {
+    let client = client().await;
+    assert_eq!(Status::BadRequest, client.get("/rest/session").dispatch().await.status());
+    assert_eq!(Status::BadRequest, client.post("/rest/score/4").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_score_disabled_task() {
+    let client = client().await;
+    login(&client, "dliwespf", "Franki1234").await;
+
+    let response = client.post("/rest/score/2").dispatch().await;
+    assert_eq!(Status::NotFound, response.status());
+    assert_eq!("Task is not enabled", response.into_string().await.unwrap());
+}
+
+#[rocket::async_test]
+async fn test_admin_routes_forbidden_for_users() {
+    let client = client().await;
+    assert_eq!(Status::Ok, login(&client, "dliwespf", "Franki1234").await);
+
+    assert_eq!(Status::Forbidden, client.get("/rest/config").dispatch().await.status());
+    assert_eq!(Status::Forbidden, client.get("/rest/config/settings").dispatch().await.status());
+    assert_eq!(Status::Forbidden, client.get("/rest/webhook/all").dispatch().await.status());
+
+    let response = client.post("/rest/user").header(Header::new("username", "newbie")).dispatch().await;
+    assert_eq!(Status::Forbidden, response.status());
+    assert_eq!(Status::NotFound, client.get("/rest/user/username/newbie").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_admin_adds_user() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let response = client.post("/rest/user").header(Header::new("username", "newbie")).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert_eq!("5", response.into_string().await.unwrap());
+    assert_eq!(Status::Ok, client.get("/rest/user/username/newbie").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_paused_scoring() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let response = client.put("/rest/config/settings/scoring_paused").header(ContentType::JSON).body(r#"{"type": "bool", "value": true}"#).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert_eq!(Status::Forbidden, client.post("/rest/score/4").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_self_registration() {
+    let client = client().await;
+    let register = |username: &str| client.post("/rest/user/register").header(ContentType::JSON)
+        .body(format!(r#"{{"username": "{}", "display_name": "Newbie", "password": "Newbie1234"}}"#, username));
+    assert_eq!(Status::Forbidden, register("newbie").dispatch().await.status());
+
+    login(&client, "roterkohl", "Flori1234").await;
+    let response = client.put("/rest/config/settings/self_registration_enabled").header(ContentType::JSON).body(r#"{"type": "bool", "value": true}"#).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let response = register("newbie").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert_eq!("5", response.into_string().await.unwrap());
+    assert_eq!(Status::Conflict, register("newbie").dispatch().await.status());
+    assert_eq!(Status::BadRequest, register(" ").dispatch().await.status());
+    assert_eq!(Status::Ok, login(&client, "newbie", "Newbie1234").await);
+}
+
+#[rocket::async_test]
+async fn test_public_routes() {
+    let client = client().await;
+
+    assert_eq!(Status::Ok, client.get("/rest/task/all").dispatch().await.status());
+    assert_eq!(Status::Ok, client.get("/rest/health/ready").dispatch().await.status());
+    assert_eq!(Status::NotFound, client.get("/rest/task/4711").dispatch().await.status());
+
+    let response = client.get("/rest/user/1").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert!(response.headers().get_one("X-Request-Id").is_some());
+}
+
+#[rocket::async_test]
+async fn test_openapi_document() {
+    let client = client().await;
+
+    let response = client.get("/rest/openapi.json").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let document: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    for path in ["/session/login", "/score/{task_id}", "/user/all", "/health/ready"] {
+        assert!(document["paths"].get(path).is_some(), "{} is not documented", path);
+    }
+}
+
+#[rocket::async_test]
+async fn test_export_and_import() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let response = client.get("/rest/admin/export").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let export = response.into_string().await.unwrap();
+    assert!(export.contains("\"pwd_hash\""));
+
+    client.post("/rest/user").header(Header::new("username", "newbie")).dispatch().await;
+
+    let response = client.post("/rest/admin/import?dry_run=true").header(ContentType::JSON).body(&export).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let report: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(false, report["applied"]);
+    assert_eq!("User 5 'newbie' is removed", report["conflicts"][0]);
+    assert_eq!(Status::Ok, client.get("/rest/user/username/newbie").dispatch().await.status());
+
+    let response = client.post("/rest/admin/import").header(ContentType::JSON).body(&export).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    // The import ends all sessions
+    assert_eq!(Status::Unauthorized, client.get("/rest/user").dispatch().await.status());
+    assert_eq!(Status::NotFound, client.get("/rest/user/username/newbie").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_import_refuses_inconsistent_data() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let snapshot = r#"{"version": 1, "users": [], "tasks": [], "teams": [{"id": 1, "name": "Babes", "manager_id": 1, "member_ids": []}], "setting_changes": []}"#;
+    let response = client.post("/rest/admin/import").header(ContentType::JSON).body(snapshot).dispatch().await;
+    assert_eq!(Status::UnprocessableEntity, response.status());
+    assert!(response.into_string().await.unwrap().contains("Manager 1 of team 'Babes' does not exist"));
+    assert_eq!(Status::Ok, client.get("/rest/user/username/topher").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_csv_import_of_users() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let csv = "username,display_name,team,password\nnewbie,Newbie,Church,\nrookie,Rookie,,Rookie1234\n";
+    let response = client.post("/rest/admin/import/users?dry_run=true").body(csv).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert_eq!(Status::NotFound, client.get("/rest/user/username/newbie").dispatch().await.status());
+
+    let response = client.post("/rest/admin/import/users").body(csv).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let report: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!("newbie", report["generated_passwords"][0]["username"]);
+    let password = report["generated_passwords"][0]["password"].as_str().unwrap().to_owned();
+    assert_eq!(Status::Ok, login(&client, "newbie", &password).await);
+    assert_eq!(Status::Ok, login(&client, "rookie", "Rookie1234").await);
+}
+
+#[rocket::async_test]
+async fn test_csv_import_is_all_or_nothing() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let csv = "name,points,enabled\nFenster putzen,20,true\nKaffee kochen,75,true\n";
+    let response = client.post("/rest/admin/import/tasks").body(csv).dispatch().await;
+    assert_eq!(Status::UnprocessableEntity, response.status());
+    assert!(response.into_string().await.unwrap().contains("Task 'Kaffee kochen' already exists"));
+    assert_eq!(Status::NotFound, client.get("/rest/task/5").dispatch().await.status());
+
+    let response = client.get("/rest/admin/import/tasks/template").dispatch().await;
+    assert_eq!(Some(ContentType::CSV), response.content_type());
+    assert!(response.into_string().await.unwrap().starts_with("name,points,enabled"));
+}
+
+#[rocket::async_test]
+async fn test_score_report_as_csv() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+
+    let response = client.get("/rest/report/scores").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert_eq!(Some(ContentType::CSV), response.content_type());
+    let csv = response.into_string().await.unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(vec!["id,name,count,points,average", "1,Flori,5,157,31.40", "2,Michi,3,137,45.67", "4,Topher,5,375,75.00", ",Total,13,669,51.46"], lines);
+}
+
+#[rocket::async_test]
+async fn test_score_report_for_team_managers() {
+    let client = client().await;
+    login(&client, "brutours.de", "Michi1234").await;
+
+    let response = client.get("/rest/report/scores?group_by=team&format=json&from=2020-01-01").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let report: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(1, report["rows"].as_array().unwrap().len());
+    assert_eq!("Church", report["rows"][0]["name"]);
+    assert_eq!(669, report["rows"][0]["points"]);
+    assert_eq!("2020-01-01", report["from"]);
+
+    login(&client, "dliwespf", "Franki1234").await;
+    assert_eq!(Status::Forbidden, client.get("/rest/report/scores").dispatch().await.status());
+    assert_eq!(Status::BadRequest, client.get("/rest/report/scores?from=yesterday").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_reward_redemption_and_approval() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+    let reward = r#"{"id": 0, "name": "Kinogutschein", "cost": 100, "stock": 5, "enabled": true}"#;
+    let response = client.post("/rest/reward").header(ContentType::JSON).body(reward).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+
+    login(&client, "topher", "Topheri1234").await;
+    let response = client.post("/rest/reward/1/redeem").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    let response = client.get("/rest/reward/balance").dispatch().await;
+    let balance: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(375, balance["lifetime_points"]);
+    assert_eq!(275, balance["balance"]);
+
+    login(&client, "dliwespf", "Franki1234").await;
+    assert_eq!(Status::Conflict, client.post("/rest/reward/1/redeem").dispatch().await.status());
+    assert_eq!(Status::Forbidden, client.put("/rest/reward/redemption/1/approve").dispatch().await.status());
+    assert_eq!(Status::Forbidden, client.post("/rest/reward").header(ContentType::JSON).body(reward).dispatch().await.status());
+
+    login(&client, "brutours.de", "Michi1234").await;
+    let response = client.get("/rest/reward/redemptions/pending").dispatch().await;
+    assert!(response.into_string().await.unwrap().contains("Kinogutschein"));
+    let response = client.put("/rest/reward/redemption/1/approve").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert!(response.into_string().await.unwrap().contains("\"status\":\"approved\""));
+    assert_eq!(Status::Conflict, client.put("/rest/reward/redemption/1/reject").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_points_ledger_and_bookings() {
+    let client = client().await;
+    login(&client, "brutours.de", "Michi1234").await;
+    let bonus = r#"{"kind": "bonus", "amount": 50, "reference": "Umzug"}"#;
+    assert_eq!(Status::Forbidden, client.post("/rest/points/3").header(ContentType::JSON).body(bonus).dispatch().await.status());
+    assert_eq!(Status::Ok, client.get("/rest/points/3/ledger").dispatch().await.status());
+
+    login(&client, "topher", "Topheri1234").await;
+    let response = client.post("/rest/points/3").header(ContentType::JSON).body(bonus).dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert!(response.into_string().await.unwrap().contains("\"created_by\":\"topher\""));
+    let score = r#"{"kind": "score", "amount": 50, "reference": null}"#;
+    assert_eq!(Status::BadRequest, client.post("/rest/points/3").header(ContentType::JSON).body(score).dispatch().await.status());
+    assert_eq!(Status::NotFound, client.post("/rest/points/4711").header(ContentType::JSON).body(bonus).dispatch().await.status());
+
+    let response = client.get("/rest/points/3/period?from=2020-01-01").dispatch().await;
+    let period: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(50, period["points"]);
+
+    login(&client, "dliwespf", "Franki1234").await;
+    let response = client.get("/rest/points/ledger").dispatch().await;
+    let ledger: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!("bonus", ledger[0]["kind"]);
+    assert_eq!(Status::Forbidden, client.get("/rest/points/4/ledger").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_seasons_and_trophies() {
+    let client = client().await;
+    login(&client, "brutours.de", "Michi1234").await;
+    let season = r#"{"name": "Herbst"}"#;
+    assert_eq!(Status::Forbidden, client.post("/rest/season").header(ContentType::JSON).body(season).dispatch().await.status());
+    assert_eq!(Status::NotFound, client.get("/rest/season/active").dispatch().await.status());
+
+    login(&client, "topher", "Topheri1234").await;
+    assert_eq!(Status::BadRequest, client.post("/rest/season").header(ContentType::JSON).body(r#"{"name": " "}"#).dispatch().await.status());
+    assert_eq!(Status::BadRequest, client.post("/rest/season").header(ContentType::JSON).body(r#"{"name": "Herbst", "end": "2020-01-01T00:00:00Z"}"#).dispatch().await.status());
+    assert_eq!(Status::Ok, client.post("/rest/season").header(ContentType::JSON).body(season).dispatch().await.status());
+    assert_eq!(Status::Conflict, client.post("/rest/season").header(ContentType::JSON).body(season).dispatch().await.status());
+    assert_eq!(Status::Ok, client.post("/rest/score/4").dispatch().await.status());
+
+    let response = client.get("/rest/season/1/leaderboard").dispatch().await;
+    let leaderboard: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(4, leaderboard[0]["user_id"]);
+    assert_eq!(75, leaderboard[0]["points"]);
+
+    assert_eq!(Status::Ok, client.put("/rest/season/1/end").dispatch().await.status());
+    assert_eq!(Status::Conflict, client.put("/rest/season/1/end").dispatch().await.status());
+    assert_eq!(Status::NotFound, client.put("/rest/season/4711/end").dispatch().await.status());
+
+    let response = client.get("/rest/trophy/4").dispatch().await;
+    assert!(response.into_string().await.unwrap().contains("\"name\":\"Herbst: 1st place\""));
+}
+
+#[rocket::async_test]
+async fn test_streak_bonus() {
+    let client = client().await;
+    login(&client, "topher", "Topheri1234").await;
+    let setting = |key: &str, value: &str| client.put(format!("/rest/config/settings/{}", key)).header(ContentType::JSON).body(value.to_owned());
+    assert_eq!(Status::Ok, setting("streak_bonus_interval", r#"{"type": "integer", "value": 1}"#).dispatch().await.status());
+    assert_eq!(Status::Ok, setting("streak_skip_weekends", r#"{"type": "bool", "value": false}"#).dispatch().await.status());
+    assert_eq!(Status::BadRequest, setting("streak_holidays", r#"{"type": "text", "value": "24.12.2022"}"#).dispatch().await.status());
+
+    // The first score of the day extends the activity streak and the task streak, each earning 25 points
+    login(&client, "dliwespf", "Franki1234").await;
+    assert_eq!("60", client.post("/rest/score/1").dispatch().await.into_string().await.unwrap());
+    assert_eq!("70", client.post("/rest/score/1").dispatch().await.into_string().await.unwrap());
+
+    let response = client.get("/rest/user/3").dispatch().await;
+    let user: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!("dliwespf", user["username"]);
+    assert_eq!(1, user["streaks"]["activity"]["current"]);
+    assert_eq!(1, user["streaks"]["tasks"][0]["task_id"]);
+}
+
+#[rocket::async_test]
+async fn test_levels() {
+    let client = client().await;
+    login(&client, "topher", "Topheri1234").await;
+    let curve = |value: &str| client.put("/rest/config/settings/level_curve").header(ContentType::JSON).body(format!(r#"{{"type": "text", "value": "{}"}}"#, value));
+    assert_eq!(Status::BadRequest, curve("10:Newcomer").dispatch().await.status());
+    assert_eq!(Status::Ok, curve("0:Newcomer, 50:Helper, 200:Regular").dispatch().await.status());
+
+    login(&client, "dliwespf", "Franki1234").await;
+    assert_eq!(Status::Ok, client.post("/rest/score/4").dispatch().await.status());
+
+    let response = client.get("/rest/user").dispatch().await;
+    let user: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(2, user["level"]["level"]);
+    assert_eq!("Helper", user["level"]["title"]);
+    assert_eq!(200, user["level"]["next_level_points"]);
+    assert_eq!(16, user["level"]["progress"]);
+    assert_eq!(1, user["level"]["history"].as_array().unwrap().len());
+
+    let response = client.get("/rest/user/all").dispatch().await;
+    let users: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(3, users[3]["level"]["level"]);
+    assert!(users[3].get("streaks").is_none());
+}
+
+#[rocket::async_test]
+async fn test_kudos() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+    let kudos = |body: &str| client.post("/rest/kudos").header(ContentType::JSON).body(body.to_owned());
+    assert_eq!(Status::BadRequest, kudos(r#"{"to_user_id": 1, "message": "Gut gemacht", "points": 5}"#).dispatch().await.status());
+    assert_eq!(Status::BadRequest, kudos(r#"{"to_user_id": 3, "message": " ", "points": 5}"#).dispatch().await.status());
+    assert_eq!(Status::NotFound, kudos(r#"{"to_user_id": 4711, "message": "Gut gemacht", "points": 5}"#).dispatch().await.status());
+    assert_eq!(Status::Ok, kudos(r#"{"to_user_id": 3, "message": "Gut gemacht", "points": 20}"#).dispatch().await.status());
+    assert_eq!(Status::Conflict, kudos(r#"{"to_user_id": 3, "message": "Nochmal", "points": 1}"#).dispatch().await.status());
+
+    let response = client.get("/rest/kudos/allowance").dispatch().await;
+    let allowance: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(50, allowance["weekly_allowance"]);
+    assert_eq!(30, allowance["remaining"]);
+
+    let response = client.get("/rest/kudos/3").dispatch().await;
+    assert!(response.into_string().await.unwrap().contains("\"message\":\"Gut gemacht\""));
+}
+
+#[rocket::async_test]
+async fn test_score_verification() {
+    let client = client().await;
+    login(&client, "roterkohl", "Flori1234").await;
+    let csv = "name,points,enabled,requires_verification\nFenster putzen,20,true,yes\n";
+    assert_eq!(Status::Ok, client.post("/rest/admin/import/tasks").body(csv).dispatch().await.status());
+    let setting = |key: &str, value: &str| client.put(format!("/rest/config/settings/{}", key)).header(ContentType::JSON).body(value.to_owned());
+    assert_eq!(Status::Ok, setting("streak_bonus_interval", r#"{"type": "integer", "value": 1}"#).dispatch().await.status());
+    assert_eq!(Status::Ok, setting("streak_skip_weekends", r#"{"type": "bool", "value": false}"#).dispatch().await.status());
+
+    assert_eq!(Status::Conflict, client.post("/rest/score/5").dispatch().await.status());
+    assert_eq!(Status::Conflict, client.post("/rest/score/1/claim").dispatch().await.status());
+    let response = client.post("/rest/score/5/claim").dispatch().await;
+    assert_eq!(Status::Ok, response.status());
+    assert!(response.into_string().await.unwrap().contains("\"status\":\"pending\""));
+
+    // Nobody confirms their own claim
+    assert_eq!("[]", client.get("/rest/verification/pending").dispatch().await.into_string().await.unwrap());
+    assert_eq!(Status::Forbidden, client.put("/rest/verification/1/confirm").dispatch().await.status());
+
+    login(&client, "dliwespf", "Franki1234").await;
+    assert!(client.get("/rest/verification/pending").dispatch().await.into_string().await.unwrap().contains("\"task_name\":\"Fenster putzen\""));
+    assert_eq!(Status::Ok, client.put("/rest/verification/1/confirm").dispatch().await.status());
+    assert_eq!(Status::Conflict, client.put("/rest/verification/1/reject").dispatch().await.status());
+    assert_eq!(Status::NotFound, client.put("/rest/verification/4711/reject").dispatch().await.status());
+
+    let user: serde_json::Value = serde_json::from_str(&client.get("/rest/user/1").dispatch().await.into_string().await.unwrap()).unwrap();
+    // Flori has been active today already, so only the streak of the new task earns a bonus
+    assert_eq!(202, user["points"]);
+}
+
+#[rocket::async_test]
+async fn test_challenges() {
+    let client = client().await;
+    login(&client, "dliwespf", "Franki1234").await;
+    let challenge = |challenger: u32, task_id: u32| format!(r#"{{"name": "Most coffees", "challenger": {{"user": {}}}, "opponent": {{"user": 2}}, "task_ids": [{}], "starts_at": "{}", "ends_at": "{}", "stake": 10}}"#,
+        challenger, task_id, chrono::Utc::now().to_rfc3339(), (chrono::Utc::now() + chrono::Duration::days(7)).to_rfc3339());
+    let propose = |body: String| client.post("/rest/challenge").header(ContentType::JSON).body(body);
+    assert_eq!(Status::Forbidden, propose(challenge(1, 4)).dispatch().await.status());
+    assert_eq!(Status::BadRequest, propose(challenge(3, 4711)).dispatch().await.status());
+    assert_eq!(Status::BadRequest, propose(challenge(2, 4)).dispatch().await.status());
+    assert_eq!(Status::Ok, propose(challenge(3, 4)).dispatch().await.status());
+    assert_eq!(Status::Conflict, propose(challenge(3, 4)).dispatch().await.status());
+
+    // Only Michi answers the challenge
+    assert_eq!(Status::Forbidden, client.put("/rest/challenge/1/accept").dispatch().await.status());
+    login(&client, "brutours.de", "Michi1234").await;
+    assert_eq!(Status::Ok, client.put("/rest/challenge/1/accept").dispatch().await.status());
+    assert_eq!(Status::Conflict, client.put("/rest/challenge/1/decline").dispatch().await.status());
+
+    client.post("/rest/score/4").dispatch().await;
+    let challenge: serde_json::Value = serde_json::from_str(&client.get("/rest/challenge/1").dispatch().await.into_string().await.unwrap()).unwrap();
+    assert_eq!("accepted", challenge["status"]);
+    assert_eq!(75, challenge["results"][1]["points"]);
+    assert_eq!(Status::NotFound, client.get("/rest/challenge/4711").dispatch().await.status());
+}
+
+#[rocket::async_test]
+async fn test_schedules() {
+    let client = client().await;
+    let schedule = |recurrence: &str| format!(r#"{{"recurrence": "{}", "rota": {{"users": [3, 2]}}, "starts_at": "{}", "bonus": 5}}"#,
+        recurrence, (chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339());
+    login(&client, "dliwespf", "Franki1234").await;
+    assert_eq!(Status::Forbidden, client.put("/rest/schedule/1").header(ContentType::JSON).body(schedule("daily")).dispatch().await.status());
+
+    login(&client, "topher", "Topheri1234").await;
+    assert_eq!(Status::NotFound, client.put("/rest/schedule/4711").header(ContentType::JSON).body(schedule("daily")).dispatch().await.status());
+    assert_eq!(Status::BadRequest, client.put("/rest/schedule/1").header(ContentType::JSON).body(schedule("hourly")).dispatch().await.status());
+    assert_eq!(Status::Ok, client.put("/rest/schedule/1").header(ContentType::JSON).body(schedule("daily")).dispatch().await.status());
+
+    // Franki's first turn was due yesterday
+    let assignments: serde_json::Value = serde_json::from_str(&client.get("/rest/user/3/assignments").dispatch().await.into_string().await.unwrap()).unwrap();
+    let assignments = assignments.as_array().unwrap();
+    assert!(assignments.iter().all(|assignment| assignment["user_id"] == 3 && assignment["task_name"] == "Blumen gießen"));
+    assert_eq!("missed", assignments[0]["status"]);
+    assert_eq!("open", assignments[1]["status"]);
+    assert_eq!(Status::BadRequest, client.get("/rest/user/3/assignments?days=365").dispatch().await.status());
+    assert_eq!(Status::NotFound, client.get("/rest/user/4711/assignments").dispatch().await.status());
+
+    assert_eq!(Status::Ok, client.delete("/rest/schedule/1").dispatch().await.status());
+    assert_eq!(Status::NotFound, client.delete("/rest/schedule/1").dispatch().await.status());
+}